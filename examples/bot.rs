@@ -0,0 +1,36 @@
+// A bot of your own, in a few lines: it goes wherever leaves the most room
+// to move around in, and towards the apple when that's a tie.
+//     cargo run --example bot
+use snake::{analysis, Coords, Direction::{self, *}, SnakeEnv};
+
+const GAMES: u64 = 10;
+
+fn main() {
+    let mut env = SnakeEnv::seeded(20, 10, 7);
+    let mut total = 0;
+
+    for _ in 0..GAMES {
+        env.reset();
+        while let (false, Some(apple)) = (env.is_done(), env.apple()) {
+            let (_, reward, _) = env.step(pick(&env, apple));
+            if reward > 0.0 {
+                total += 1;
+            }
+        }
+    }
+
+    println!("Mean score over {} games: {:.1}", GAMES, total as f64 / GAMES as f64);
+}
+
+// Most room first, and the closest to the apple among the ones tied for it
+fn pick(env: &SnakeEnv, apple: Coords) -> Direction {
+    let (snake, board) = (env.snake(), env.board());
+    let distance = |dir: &Direction| {
+        let pos = dir.step_from(snake.head());
+        (pos.0 as i32 - apple.0 as i32).abs() + (pos.1 as i32 - apple.1 as i32).abs()
+    };
+
+    *[Up, Down, Left, Right].iter()
+        .max_by_key(|dir| (analysis::free_space_after(snake, **dir, board), -distance(dir)))
+        .unwrap()
+}
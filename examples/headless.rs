@@ -0,0 +1,24 @@
+// A whole game with no terminal, the built-in AI playing on a seeded board:
+//     cargo run --example headless
+use snake::{ai, SnakeEnv};
+
+fn main() {
+    let mut env = SnakeEnv::seeded(20, 10, 42);
+    let mut score = 0;
+    let mut steps = 0;
+
+    while !env.is_done() {
+        let apple = match env.apple() {
+            Some(apple) => apple,
+            None => break, // The board is full
+        };
+        let dir = ai::next_direction(env.snake(), apple, env.board());
+        let (_, reward, _) = env.step(dir);
+        if reward > 0.0 {
+            score += 1;
+        }
+        steps += 1;
+    }
+
+    println!("Score: {} in {} steps, {} cells long", score, steps, env.snake().body().len());
+}
@@ -0,0 +1,116 @@
+use std::{fs, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::{paths, storage};
+
+pub struct Achievement {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+// Only rounds that go on the high scores count, so no bots and no help
+pub struct Round {
+    pub score: u64,
+    pub length: usize,
+    pub won: bool,
+    pub puzzle: bool, // A puzzle or a level
+}
+
+pub const ALL: [Achievement; 7] = [
+    Achievement { id: "first-apple", name: "First bite", description: "Eat an apple" },
+    Achievement { id: "score-10", name: "Peckish", description: "Score 10 in a round" },
+    Achievement { id: "score-50", name: "Ravenous", description: "Score 50 in a round" },
+    Achievement { id: "score-100", name: "Insatiable", description: "Score 100 in a round" },
+    Achievement { id: "length-100", name: "Long haul", description: "Grow to 100 cells long" },
+    Achievement { id: "won", name: "Winner", description: "Win a round" },
+    Achievement { id: "puzzle", name: "Puzzler", description: "Solve a puzzle or a level" },
+];
+
+// What a profile has unlocked so far, and when. Kept next to its scores like
+// the menu picks, one `id = timestamp_ms` per line.
+pub struct Achievements {
+    path: PathBuf,
+    unlocked: Vec<(String, u64)>,
+}
+
+impl Achievements {
+    // Missing or unreadable just means nothing unlocked yet
+    pub fn load(profile: Option<&str>) -> Self {
+        let path = paths::achievements_file(profile);
+        let unlocked = fs::read_to_string(&path).unwrap_or_default().lines()
+            .filter_map(|line| line.split_once('='))
+            .filter_map(|(id, when)| Some((id.trim().to_string(), when.trim().parse().ok()?)))
+            .collect();
+
+        Achievements { path, unlocked }
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.iter().any(|(unlocked, _)| unlocked == id)
+    }
+
+    pub fn count(&self) -> usize {
+        ALL.iter().filter(|achievement| self.is_unlocked(achievement.id)).count()
+    }
+
+    // The ones this round unlocked for the first time
+    pub fn unlock(&mut self, round: &Round) -> Vec<&'static Achievement> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+        let new: Vec<&'static Achievement> = ALL.iter()
+            .filter(|achievement| !self.is_unlocked(achievement.id) && earned(achievement.id, round))
+            .collect();
+        self.unlocked.extend(new.iter().map(|achievement| (achievement.id.to_string(), now)));
+        new
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let dir = self.path.parent().unwrap();
+        fs::create_dir_all(dir).map_err(|e| format!("Error creating {}: {}", dir.display(), e))?;
+        let text: String = self.unlocked.iter().map(|(id, when)| format!("{} = {}\n", id, when)).collect();
+        storage::write_atomic(&self.path, text).map_err(|e| format!("Error writing {}: {}", self.path.display(), e))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////
+
+fn earned(id: &str, round: &Round) -> bool {
+    match id {
+        "first-apple" => round.score >= 1,
+        "score-10" => round.score >= 10,
+        "score-50" => round.score >= 50,
+        "score-100" => round.score >= 100,
+        "length-100" => round.length >= 100,
+        "won" => round.won,
+        "puzzle" => round.won && round.puzzle,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round(score: u64, won: bool) -> Round {
+        Round { score, length: score as usize + 3, won, puzzle: false }
+    }
+
+    #[test]
+    fn each_one_unlocks_once() {
+        let mut achievements = Achievements { path: PathBuf::new(), unlocked: Vec::new() };
+        let ids = |new: Vec<&Achievement>| new.iter().map(|a| a.id).collect::<Vec<_>>();
+
+        assert!(achievements.unlock(&round(0, false)).is_empty());
+        assert_eq!(ids(achievements.unlock(&round(12, false))), ["first-apple", "score-10"]);
+        assert_eq!(ids(achievements.unlock(&round(12, true))), ["won"]);
+        assert!(achievements.unlock(&round(12, true)).is_empty());
+        assert_eq!(achievements.count(), 3);
+    }
+
+    #[test]
+    fn puzzles_need_a_win() {
+        let mut achievements = Achievements { path: PathBuf::new(), unlocked: Vec::new() };
+        assert!(achievements.unlock(&Round { score: 0, length: 3, won: false, puzzle: true }).is_empty());
+        let new = achievements.unlock(&Round { score: 0, length: 3, won: true, puzzle: true });
+        assert!(new.iter().any(|a| a.id == "puzzle"));
+    }
+}
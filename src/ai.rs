@@ -0,0 +1,46 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{analysis, Coords};
+use crate::board::Board;
+use crate::snake::{Snake, Direction::{*, self}};
+
+const DIRECTIONS: [Direction; 4] = [Up, Down, Left, Right];
+
+pub fn next_direction(snake: &Snake, apple: Coords, board: &Board) -> Direction {
+    let head = snake.head();
+    // The tail moves out of the way on the next step, so it's not an obstacle
+    let blocked: HashSet<Coords> = snake.body()[1..].iter().copied().collect();
+    let is_free = |pos: &Coords| board.is_playable(*pos) && !blocked.contains(pos);
+
+    // Shortest path to the apple, remembering the first move that led to each cell
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    visited.insert(head);
+
+    for dir in DIRECTIONS.iter() {
+        let pos = dir.step_from(head);
+        if is_free(&pos) && visited.insert(pos) {
+            queue.push_back((pos, *dir));
+        }
+    }
+
+    while let Some((pos, first_dir)) = queue.pop_front() {
+        if pos == apple {
+            return first_dir;
+        }
+
+        for dir in DIRECTIONS.iter() {
+            let next = dir.step_from(pos);
+            if is_free(&next) && visited.insert(next) {
+                queue.push_back((next, first_dir));
+            }
+        }
+    }
+
+    // The apple can't be reached, just stay alive in the biggest open area
+    DIRECTIONS.iter()
+        .filter(|dir| is_free(&dir.step_from(head)))
+        .max_by_key(|dir| analysis::free_space_after(snake, **dir, board))
+        .copied()
+        .unwrap_or_else(|| snake.get_direction())
+}
@@ -0,0 +1,209 @@
+use std::collections::VecDeque;
+
+use crate::{Coords, TermInt};
+use crate::board::Board;
+use crate::snake::{Snake, Direction::{self, *}, MoveResult::*};
+
+// Questions every bot ends up asking about the board, answered the way the
+// engine sees it: the tail moves out of the way on the next step, unless the
+// snake is about to grow.
+
+// How many steps away from the head each cell is, row by row
+pub struct DistanceMap {
+    width: TermInt,
+    steps: Vec<Option<u32>>,
+}
+
+impl DistanceMap {
+    // None for anything that can't be reached right now
+    pub fn get(&self, pos: Coords) -> Option<u32> {
+        if pos.0 >= self.width {
+            return None;
+        }
+        self.steps.get(pos.1 as usize * self.width as usize + pos.0 as usize).copied().flatten()
+    }
+}
+
+pub fn distances_from_head(snake: &Snake, board: &Board) -> DistanceMap {
+    let (width, height) = board.size();
+    let index = |pos: Coords| pos.1 as usize * width as usize + pos.0 as usize;
+    let mut blocked = vec![false; width as usize * height as usize];
+    for pos in staying_body(snake) {
+        blocked[index(*pos)] = true;
+    }
+
+    let mut map = DistanceMap { width, steps: vec![None; blocked.len()] };
+    let mut queue = VecDeque::new();
+    map.steps[index(snake.head())] = Some(0);
+    queue.push_back((snake.head(), 0));
+
+    while let Some((pos, steps)) = queue.pop_front() {
+        for next in [Up, Down, Left, Right].iter().map(|dir| dir.step_from(pos)) {
+            if board.is_playable(next) && !blocked[index(next)] && map.steps[index(next)].is_none() {
+                map.steps[index(next)] = Some(steps + 1);
+                queue.push_back((next, steps + 1));
+            }
+        }
+    }
+
+    map
+}
+
+// The room left to move around in after heading that way, 0 if it's a crash
+pub fn free_space_after(snake: &Snake, direction: Direction, board: &Board) -> usize {
+    if direction == snake.get_direction().opposite() || snake.would_crash(direction, board) {
+        return 0;
+    }
+
+    let new_head = direction.step_from(snake.head());
+    board.region_of(new_head, staying_body(snake)).size()
+}
+
+// Chasing its own tail is the one way a snake can always stay alive
+pub fn can_reach_tail(snake: &Snake, board: &Board) -> bool {
+    let body = snake.body();
+    if body.len() < 2 {
+        return true;
+    }
+
+    board.region_of(snake.head(), &body[1..body.len() - 1]).contains(body[0])
+}
+
+// Whether every way on from that move crashes within so many moves, counting
+// that one. Eating the apple on the way grows the snake, the next one could
+// show up anywhere so it's left out.
+pub fn is_doomed(snake: &Snake, direction: Direction, apple: Option<Coords>, board: &Board, moves: u32) -> bool {
+    !survives(snake, direction, apple, board, moves)
+}
+
+///////////////////////////////////////////////////////////////////////////
+
+fn survives(snake: &Snake, direction: Direction, apple: Option<Coords>, board: &Board, moves: u32) -> bool {
+    let mut next = snake.clone();
+    next.set_direction(direction);
+    let apple = match next.move_step(board) {
+        Crashed => return false,
+        Moved { new_head, .. } if Some(new_head) == apple => {
+            next.grow(1);
+            None
+        },
+        Moved { .. } => apple,
+    };
+
+    moves <= 1 || [Up, Down, Left, Right].iter()
+        .filter(|dir| **dir != next.get_direction().opposite())
+        .any(|dir| survives(&next, *dir, apple, board, moves - 1))
+}
+
+// Everything but the tail, which is gone after the next step
+fn staying_body(snake: &Snake) -> &[Coords] {
+    let body = snake.body();
+    if snake.is_growing() {body} else {&body[1..]}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // '#' for walls, anything else is free. Like on the real boards the edge
+    // is all wall, nothing ever steps off the grid.
+    fn board(rows: &[&str]) -> Board {
+        let mask: Vec<bool> = rows.iter().flat_map(|row| row.chars().map(|c| c != '#')).collect();
+        Board::from_mask(rows[0].len() as TermInt, rows.len() as TermInt, &mask)
+    }
+
+    fn corridor() -> Board {
+        board(&[
+            "#######",
+            "#.....#",
+            "#######",
+        ])
+    }
+
+    #[test]
+    fn distances_go_around_walls() {
+        let board = board(&[
+            "#######",
+            "#..#..#",
+            "#..#..#",
+            "#.....#",
+            "#######",
+        ]);
+        let snake = Snake::new((1, 1), 1, Down);
+        let map = distances_from_head(&snake, &board);
+        assert_eq!(map.get((1, 1)), Some(0));
+        assert_eq!(map.get((2, 1)), Some(1));
+        assert_eq!(map.get((4, 1)), Some(7));
+        assert_eq!(map.get((3, 1)), None);
+        assert_eq!(map.get((7, 1)), None);
+    }
+
+    #[test]
+    fn distances_go_through_the_tail_but_not_the_body() {
+        let snake = Snake::new((3, 1), 3, Right);
+        let map = distances_from_head(&snake, &corridor());
+        assert_eq!(map.get((5, 1)), Some(2));
+        assert_eq!(map.get((2, 1)), None);
+        assert_eq!(map.get((1, 1)), None);
+    }
+
+    #[test]
+    fn free_space_after_a_move_into_a_pocket() {
+        let board = board(&[
+            "#######",
+            "#.#...#",
+            "#.#...#",
+            "#.....#",
+            "#######",
+        ]);
+        let snake = Snake::new((1, 3), 3, Left);
+        assert_eq!(free_space_after(&snake, Up, &board), 2);
+        assert_eq!(free_space_after(&snake, Right, &board), 0);
+        assert_eq!(free_space_after(&snake, Left, &board), 0);
+        assert_eq!(free_space_after(&snake, Down, &board), 0);
+    }
+
+    #[test]
+    fn tail_is_reachable_on_an_open_board() {
+        let board = board(&[
+            "#######",
+            "#.....#",
+            "#.....#",
+            "#######",
+        ]);
+        assert!(can_reach_tail(&Snake::new((3, 1), 3, Right), &board));
+    }
+
+    #[test]
+    fn tail_is_out_of_reach_in_a_corridor() {
+        assert!(!can_reach_tail(&Snake::new((3, 1), 3, Right), &corridor()));
+    }
+
+    #[test]
+    fn dead_end_is_doomed_once_it_runs_out() {
+        let (board, snake) = (corridor(), Snake::new((3, 1), 3, Right));
+        assert!(!is_doomed(&snake, Right, None, &board, 2));
+        assert!(is_doomed(&snake, Right, None, &board, 3));
+        assert!(is_doomed(&snake, Up, None, &board, 1));
+    }
+
+    #[test]
+    fn eating_on_the_way_counts_the_growth() {
+        // Filling a 2x2, it can go round after its own tail forever, until it grows
+        let board = board(&[
+            "####",
+            "#..#",
+            "#..#",
+            "####",
+        ]);
+        let mut snake = Snake::new((1, 2), 1, Down);
+        for dir in [Right, Up, Left] {
+            snake.grow(1);
+            snake.set_direction(dir);
+            snake.move_step(&board);
+        }
+        assert!(!snake.is_growing());
+        assert!(!is_doomed(&snake, Down, None, &board, 8));
+        assert!(is_doomed(&snake, Down, Some((1, 2)), &board, 2));
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::VecDeque;
+
+use crate::{Coords, TermInt};
+use crate::level::Level;
+use crate::snake::Direction::{self, *};
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Shape {
+    Rectangle,
+    Circle,
+    Cross,
+    Donut,
+    Custom, // Loaded from a level file
+}
+
+/// The arena the snake moves in. Cells outside of the mask are walls, the
+/// outermost rows and columns always are.
+///
+/// ```
+/// use snake::{Board, Shape};
+///
+/// let mut board = Board::new(Shape::Rectangle, 10, 6);
+/// assert!(!board.is_playable((0, 0)));
+/// assert!(board.is_playable((1, 1)));
+/// assert_eq!(board.positions().len(), 8 * 4);
+///
+/// board.add_obstacles(&[(4, 3)]);
+/// assert!(!board.is_playable((4, 3)));
+/// assert_eq!(board.region_of((1, 1), &[]).size(), 8 * 4 - 1);
+/// ```
+pub struct Board {
+    width: TermInt,
+    height: TermInt,
+    shape: Shape,
+    origin: Coords,
+    mask: Vec<bool>,
+    positions: Vec<Coords>,
+    obstacles: Vec<Coords>,
+}
+
+// The cells that can be walked to from somewhere, see Board::region_of
+pub struct Region {
+    width: TermInt,
+    cells: Vec<bool>,
+    size: usize,
+}
+
+impl Shape {
+    pub const NAMES: &'static [&'static str] = &["rectangle", "circle", "cross", "donut"];
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "rectangle" => Some(Shape::Rectangle),
+            "circle" => Some(Shape::Circle),
+            "cross" => Some(Shape::Cross),
+            "donut" => Some(Shape::Donut),
+            _ => None,
+        }
+    }
+}
+
+impl Board {
+    pub fn new(shape: Shape, width: TermInt, height: TermInt) -> Self {
+        Self::build(shape, width, height, (0, 0), |pos| shape_contains(shape, pos, width, height))
+    }
+
+    // The level is centered in the available space
+    pub fn from_level(level: &Level, width: TermInt, height: TermInt) -> Self {
+        let origin = (width.saturating_sub(level.width) / 2, height.saturating_sub(level.height) / 2);
+        Self::build(Shape::Custom, width, height, origin, |pos| {
+            pos.0 >= origin.0 && pos.1 >= origin.1 && !level.is_wall((pos.0 - origin.0, pos.1 - origin.1))
+        })
+    }
+
+    // Rebuilt cell by cell, row by row, as saved in a recording
+    pub fn from_mask(width: TermInt, height: TermInt, mask: &[bool]) -> Self {
+        Self::build(Shape::Custom, width, height, (0, 0), |pos| mask[pos.1 as usize * width as usize + pos.0 as usize])
+    }
+
+    pub fn size(&self) -> Coords {
+        (self.width, self.height)
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    // Where the top left corner of a level ended up, (0, 0) for built-in shapes
+    pub fn origin(&self) -> Coords {
+        self.origin
+    }
+
+    pub fn is_playable(&self, pos: Coords) -> bool {
+        pos.0 < self.width && pos.1 < self.height && self.mask[self.index(pos)]
+    }
+
+    pub fn positions(&self) -> &[Coords] {
+        &self.positions
+    }
+
+    // Walls put in the middle of the board after building it
+    pub fn add_obstacles(&mut self, walls: &[Coords]) {
+        for pos in walls {
+            if self.is_playable(*pos) {
+                let i = self.index(*pos);
+                self.mask[i] = false;
+                self.obstacles.push(*pos);
+            }
+        }
+        self.positions.retain(|pos| !walls.contains(pos));
+    }
+
+    pub fn obstacles(&self) -> &[Coords] {
+        &self.obstacles
+    }
+
+    // Walls that touch the playable area, which are the only ones worth drawing
+    pub fn outline(&self) -> Vec<Coords> {
+        let mut walls = vec![];
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.is_playable((x, y)) {
+                    continue;
+                }
+
+                let touches_board = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                    .map(|(dx, dy)| (x as i32 + dx, y as i32 + dy))
+                    .filter(|(nx, ny)| *nx >= 0 && *ny >= 0)
+                    .any(|(nx, ny)| self.is_playable((nx as TermInt, ny as TermInt)));
+
+                if touches_board {
+                    walls.push((x, y));
+                }
+            }
+        }
+
+        walls
+    }
+
+    // What an apple is worth when far ones are worth more, from 1 next to the head to 9
+    pub fn distance_value(&self, head: Coords, apple: Coords) -> u64 {
+        let dist = (apple.0 as i32 - head.0 as i32).abs() + (apple.1 as i32 - head.1 as i32).abs();
+        let max_dist = (self.width + self.height) as i32;
+        (1 + dist * 9 / max_dist).clamp(1, 9) as u64
+    }
+
+    // Everything the start connects to without going through walls or the
+    // blocked cells, the start included, as long as it's playable itself
+    pub fn region_of(&self, start: Coords, blocked: &[Coords]) -> Region {
+        let mut open = self.mask.clone();
+        for pos in blocked.iter().filter(|pos| self.is_playable(**pos)) {
+            open[self.index(*pos)] = false;
+        }
+
+        let mut region = Region { width: self.width, cells: vec![false; self.mask.len()], size: 0 };
+        let mut queue = VecDeque::new();
+        if self.is_playable(start) {
+            region.cells[self.index(start)] = true;
+            queue.push_back(start);
+        }
+
+        while let Some(pos) = queue.pop_front() {
+            region.size += 1;
+            // Playable cells are never on the edge, so their neighbours are all on the board
+            for next in [Up, Down, Left, Right].iter().map(|dir| dir.step_from(pos)) {
+                let i = self.index(next);
+                if open[i] && !region.cells[i] {
+                    region.cells[i] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        region
+    }
+
+    // Closest spot to the center where a snake of the given length fits,
+    // heading right with the whole body on playable cells behind the head
+    pub fn spawn_point(&self, length: TermInt) -> Option<Coords> {
+        self.spawn_point_near((self.width / 2, self.height / 2), length, Right)
+    }
+
+    // Same, but as close as possible to the given spot and heading the given way
+    pub fn spawn_point_near(&self, target: Coords, length: TermInt, direction: Direction) -> Option<Coords> {
+        self.positions.iter()
+            .filter(|pos| self.fits_snake(**pos, length, direction))
+            .min_by_key(|pos| (pos.0 as i32 - target.0 as i32).abs() + (pos.1 as i32 - target.1 as i32).abs() * 2)
+            .copied()
+    }
+
+    // Whether the head can go there with the rest of the body on playable
+    // cells straight behind it
+    pub fn fits_snake(&self, head: Coords, length: TermInt, direction: Direction) -> bool {
+        let behind = |i: TermInt| match direction {
+            Right => head.0.checked_sub(i).map(|x| (x, head.1)),
+            Left => head.0.checked_add(i).map(|x| (x, head.1)),
+            Down => head.1.checked_sub(i).map(|y| (head.0, y)),
+            Up => head.1.checked_add(i).map(|y| (head.0, y)),
+        };
+        (0..length).all(|i| behind(i).is_some_and(|pos| self.is_playable(pos)))
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn build(shape: Shape, width: TermInt, height: TermInt, origin: Coords, is_playable: impl Fn(Coords) -> bool) -> Self {
+        let mut mask = vec![false; width as usize * height as usize];
+        let mut positions = vec![];
+
+        for y in 1..height.saturating_sub(1) {
+            for x in 1..width.saturating_sub(1) {
+                if is_playable((x, y)) {
+                    mask[width as usize * y as usize + x as usize] = true;
+                    positions.push((x, y));
+                }
+            }
+        }
+
+        Board { width, height, shape, origin, mask, positions, obstacles: vec![] }
+    }
+
+    fn index(&self, pos: Coords) -> usize {
+        self.width as usize * pos.1 as usize + pos.0 as usize
+    }
+}
+
+impl Region {
+    pub fn contains(&self, pos: Coords) -> bool {
+        pos.0 < self.width && self.cells.get(pos.1 as usize * self.width as usize + pos.0 as usize) == Some(&true)
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+fn shape_contains(shape: Shape, pos: Coords, width: TermInt, height: TermInt) -> bool {
+    // Position relative to the center, scaled so that the edges of the area are at 1.0
+    let half_w = (width as f64 - 2.0) / 2.0;
+    let half_h = (height as f64 - 2.0) / 2.0;
+    let dx = (pos.0 as f64 + 0.5 - width as f64 / 2.0) / half_w;
+    let dy = (pos.1 as f64 + 0.5 - height as f64 / 2.0) / half_h;
+    let dist = (dx * dx + dy * dy).sqrt();
+
+    match shape {
+        Shape::Rectangle => true,
+        Shape::Circle => dist <= 1.0,
+        Shape::Cross => dx.abs() <= 1.0 / 3.0 || dy.abs() <= 1.0 / 3.0,
+        Shape::Donut => (0.45..=1.0).contains(&dist),
+        Shape::Custom => false,
+    }
+}
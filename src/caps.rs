@@ -0,0 +1,172 @@
+use std::env;
+
+use crossterm::style::{Attribute, Color, ContentStyle};
+
+// The 16 colors every terminal with any color at all has, with roughly what
+// they look like in most default palettes
+const BASIC_COLORS: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)), (Color::DarkRed, (128, 0, 0)), (Color::DarkGreen, (0, 128, 0)),
+    (Color::DarkYellow, (128, 128, 0)), (Color::DarkBlue, (0, 0, 128)), (Color::DarkMagenta, (128, 0, 128)),
+    (Color::DarkCyan, (0, 128, 128)), (Color::Grey, (192, 192, 192)), (Color::DarkGrey, (128, 128, 128)),
+    (Color::Red, (255, 0, 0)), (Color::Green, (0, 255, 0)), (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (0, 0, 255)), (Color::Magenta, (255, 0, 255)), (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+// Which characters to draw with, auto goes by the locale
+#[derive(Copy, Clone, PartialEq)]
+pub enum Glyphs {
+    Auto,
+    Unicode,
+    Ascii,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum ColorDepth {
+    None,
+    Basic, // The 16 named colors
+    Indexed, // 256
+    TrueColor,
+}
+
+// What the terminal can show, worked out from the environment once at
+// startup. Themes are drawn as they are and brought down to this on the way
+// out, so none of them end up as garbage on a plain console.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Caps {
+    pub unicode: bool,
+    pub colors: ColorDepth,
+}
+
+impl Glyphs {
+    pub const NAMES: &'static [&'static str] = &["auto", "unicode", "ascii"];
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Glyphs::Auto),
+            "unicode" => Some(Glyphs::Unicode),
+            "ascii" => Some(Glyphs::Ascii),
+            _ => None,
+        }
+    }
+}
+
+impl Caps {
+    pub fn detect(glyphs: Glyphs) -> Self {
+        let unicode = match glyphs {
+            Glyphs::Auto => locale_is_utf8(),
+            Glyphs::Unicode => true,
+            Glyphs::Ascii => false,
+        };
+        Caps { unicode, colors: color_depth() }
+    }
+
+    // Colors where there's no room for them, and backgrounds turn into reversed
+    // cells so that block themes still show where things are
+    pub fn style(&self, style: ContentStyle) -> ContentStyle {
+        if self.colors == ColorDepth::TrueColor {
+            return style;
+        }
+
+        let mut out = ContentStyle { foreground_color: None, background_color: None, ..style };
+        if self.colors == ColorDepth::None {
+            if style.background_color.is_some() {
+                out = out.attribute(Attribute::Reverse);
+            }
+            return out;
+        }
+        out.foreground_color = style.foreground_color.map(|color| self.color(color));
+        out.background_color = style.background_color.map(|color| self.color(color));
+        out
+    }
+
+    pub fn glyph(&self, ch: char) -> char {
+        if self.unicode || ch.is_ascii() {
+            return ch;
+        }
+
+        match ch {
+            '█' | '▓' => 'o',
+            '▒' => '%',
+            '░' | '·' | '▄' => '.',
+            '▀' => '\'',
+            '↑' | '▲' => '^',
+            '↓' | '▼' => 'v',
+            '←' | '◀' => '<',
+            '→' | '▶' => '>',
+            '↗' | '↙' => '/',
+            '↘' | '↖' => '\\',
+            _ => '?',
+        }
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn color(&self, color: Color) -> Color {
+        let rgb = match color {
+            Color::Rgb { r, g, b } => (r, g, b),
+            Color::AnsiValue(value) if self.colors == ColorDepth::Basic => ansi_to_rgb(value),
+            other => return other,
+        };
+
+        let distance = |(r, g, b): (u8, u8, u8)| {
+            let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+            d(r, rgb.0) + d(g, rgb.1) + d(b, rgb.2)
+        };
+        if self.colors == ColorDepth::Indexed {
+            // Only the cube and the greys, the first 16 look different on every terminal
+            let value = (16..=255).min_by_key(|value| distance(ansi_to_rgb(*value))).unwrap();
+            return Color::AnsiValue(value);
+        }
+        BASIC_COLORS.iter().min_by_key(|(_, basic)| distance(*basic)).unwrap().0
+    }
+}
+
+// The first of these that's set is the one that counts, like for any program
+fn locale_is_utf8() -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter().filter_map(|var| env::var(var).ok()).find(|value| !value.is_empty());
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        },
+        None => cfg!(windows), // Windows terminals have managed for a while, and don't set any of these
+    }
+}
+
+// No terminfo to ask without a crate for it, but TERM names the same things
+fn color_depth() -> ColorDepth {
+    if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return ColorDepth::None;
+    }
+
+    let colorterm = env::var("COLORTERM").unwrap_or_default().to_lowercase();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        return ColorDepth::TrueColor;
+    }
+
+    match env::var("TERM") {
+        Ok(term) if term.is_empty() || term == "dumb" => ColorDepth::None,
+        Ok(term) if term.contains("direct") => ColorDepth::TrueColor,
+        Ok(term) if term.contains("256") => ColorDepth::Indexed,
+        Ok(_) => ColorDepth::Basic,
+        Err(_) if cfg!(windows) => ColorDepth::TrueColor,
+        Err(_) => ColorDepth::None,
+    }
+}
+
+// The 16 named colors first, then a 6x6x6 cube and a grey ramp
+fn ansi_to_rgb(value: u8) -> (u8, u8, u8) {
+    match value {
+        0..=15 => BASIC_COLORS[value as usize].1,
+        16..=231 => {
+            let i = value - 16;
+            let level = |c: u8| if c == 0 {0} else {55 + c * 40};
+            (level(i / 36), level(i / 6 % 6), level(i % 6))
+        },
+        _ => {
+            let grey = 8 + (value - 232) * 10;
+            (grey, grey, grey)
+        },
+    }
+}
@@ -0,0 +1,83 @@
+use crate::config::{Config, Mirror};
+use crate::TermInt;
+
+use rand::{seq::SliceRandom, Rng};
+
+const FOG_RADIUS: TermInt = 6;
+
+// The twists a chaos round can come with, each one just flips a setting for
+// the round. One or two of them are picked every round.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Modifier {
+    Mirrored,
+    Fog,
+    DoubleSpeed,
+    ExtraApples,
+    FarApples,
+    Magnets,
+}
+
+// The settings modifiers touch, as they were before the round
+pub struct Ruleset {
+    mirror: Mirror,
+    fog: Option<TermInt>,
+    double_speed: bool,
+    extra_apples: bool,
+    distance_scoring: bool,
+    magnets: bool,
+}
+
+impl Modifier {
+    pub const ALL: [Modifier; 6] = [
+        Modifier::Mirrored, Modifier::Fog, Modifier::DoubleSpeed, Modifier::ExtraApples, Modifier::FarApples, Modifier::Magnets
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Modifier::Mirrored => "Mirrored controls",
+            Modifier::Fog => "Fog",
+            Modifier::DoubleSpeed => "Double speed",
+            Modifier::ExtraApples => "Extra apples",
+            Modifier::FarApples => "Far apples are worth more",
+            Modifier::Magnets => "Magnets",
+        }
+    }
+
+    pub fn apply(&self, config: &mut Config) {
+        match self {
+            Modifier::Mirrored => config.mirror = Mirror::Always,
+            Modifier::Fog => config.fog = Some(config.fog.map_or(FOG_RADIUS, |radius| radius.min(FOG_RADIUS))),
+            Modifier::DoubleSpeed => config.double_speed = true,
+            Modifier::ExtraApples => config.extra_apples = true,
+            Modifier::FarApples => config.distance_scoring = true,
+            Modifier::Magnets => config.magnets = true,
+        }
+    }
+
+    pub fn roll(rng: &mut impl Rng) -> Vec<Modifier> {
+        let count = rng.gen_range(1..=2);
+        Modifier::ALL.choose_multiple(rng, count).copied().collect()
+    }
+}
+
+impl Ruleset {
+    pub fn of(config: &Config) -> Self {
+        Ruleset {
+            mirror: config.mirror,
+            fog: config.fog,
+            double_speed: config.double_speed,
+            extra_apples: config.extra_apples,
+            distance_scoring: config.distance_scoring,
+            magnets: config.magnets,
+        }
+    }
+
+    pub fn restore(self, config: &mut Config) {
+        config.mirror = self.mirror;
+        config.fog = self.fog;
+        config.double_speed = self.double_speed;
+        config.extra_apples = self.extra_apples;
+        config.distance_scoring = self.distance_scoring;
+        config.magnets = self.magnets;
+    }
+}
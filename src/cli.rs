@@ -0,0 +1,371 @@
+use std::{env, fs, path::Path, process::exit};
+
+use crate::board::Shape;
+use crate::caps::Glyphs;
+use crate::config::{name_of, Config, Mirror, Mode};
+use crate::control::ControlSource;
+use crate::controls::{ControlScheme, KeyPreset};
+use crate::level::Level;
+use crate::{pack, paths};
+use crate::recent::Recent;
+use crate::scores::ScoresCommand;
+use crate::sim::Strategy;
+use crate::term::Renderer;
+use crate::speed::{Acceleration, BoardScaling};
+use crate::theme::Theme;
+
+const USAGE: &str = "\
+Usage: snake [OPTIONS]
+       snake scores [list | export FILE | import FILE]
+       snake watch FILE
+       snake verify FILE
+       snake paths
+
+Options can also be set in config.toml, in the directory shown by `snake paths`,
+one per line without the dashes: `theme = \"rainbow\"`, `ghost = true`.
+Profiles can override them in profiles/NAME.toml next to it.
+Finished games are kept in replays/ under the data directory, for `snake watch`,
+and `snake verify` plays one again to check it adds up to its score.
+
+Options:
+  --mode MODE              classic, timed, obstacles, chaos, drifting, two-player, versus
+                           or co-op, skipping the menu
+  --screensaver            The computer plays on its own forever, any key exits
+  --theme NAME             classic, color, blocks, gradient or rainbow
+  --vertical-speed FACTOR  How much slower the snake moves vertically
+  --acceleration CURVE     linear, stepped, capped or none
+  --scale-speed HOW        none, axes or area, faster on boards bigger than 80x24 and slower on smaller ones
+  --shape SHAPE            rectangle, circle, cross or donut
+  --puzzle FILE            Collect the apples of a puzzle within its move limit
+  --level NAME             Play one of the levels that come with the game, like box, rooms or maze
+  --renderer NAME          auto, ansi or simple, for terminals without cursor movement
+  --glyphs SET             auto, unicode or ascii, auto goes by the locale
+  --edit FILE              Draw a puzzle level, creating the file if needed
+  --step                   Only move when space is pressed, showing the game state
+  --quick-quit             CTRL+C quits right away mid-game, instead of asking first
+  --help-pauses            Pause the game while the key help from ? or F1 is up
+  --distance-scoring       Apples far away from the snake are worth more
+  --controls SCHEME        absolute, or relative to only turn left and right
+  --keys PRESET            standard (WASD), ijkl, numpad (8456) or arrows only, arrows always work
+  --mirror MODE            off, always or toggle every 15 seconds, swaps opposite controls
+  --fog RADIUS             Only see that many rows around the head
+  --sprint                 Hold shift with a direction to move twice as fast
+  --magnets                Power-ups that pull the apple towards the snake for a while
+  --shrink                 In classic mode, a new block of wall every 25 points
+  --target-length LENGTH   Win the round once the snake is that long
+  --apples COUNT           Win the round after eating that many apples
+  --garbage                In versus mode, every apple eaten puts a block of wall on the other board
+  --hunger STEPS           Lose a tail segment every that many steps without eating, and starve at none
+  --growth SEGMENTS        How much longer every apple makes the snake, 1 by default
+  --growth-ramp POINTS     One more segment per apple for every that many points
+  --ghost                  Mark the cell the snake is about to move into, and a drifting apple
+  --confirm-turns          Show a turn as an arrow first, and only make it on Enter
+  --hints                  Mark the turns that crash within 3 moves, H toggles them
+  --assist                 Ignore turns straight into a wall or the snake, for beginners
+  --break-reminder MINUTES Pause and suggest a break after playing that long
+  --idle-pause SECONDS     Pause on its own when no key was pressed for that long
+  --event-log              List what just happened next to the board, for fast games
+  --pip                    Show a small game played by the computer next to the board
+  --heatmap                Show where the snake went the most after each game
+  --lan                    Share scores with other players on the network
+  --name NAME              Player name shown to others
+  --discord CLIENT_ID      Show the game on your Discord profile, if built with it
+  --profile NAME           Keep scores and settings apart for each player
+  --control SOURCE         Take directions from another program, on stdin or a Unix socket,
+                           or votes from an IRC channel like irc://irc.chat.twitch.tv/name
+  --json-events            Write game events as JSON lines to stdout, if it's not a terminal
+  --summary                Print the rounds played and their scores as JSON to stdout on exit
+  --export-metrics FILE    Write the score, length, speed and head of every step to a CSV file
+                           after each round, replacing the last one
+  --simulate GAMES         Play that many games without a screen and print statistics
+  --strategy NAME          Only simulate pathfinding, greedy, random or hamiltonian play,
+                           or with --screensaver, watch pathfinding or hamiltonian play
+  --check-update           Ask GitHub whether there's a newer release, and exit";
+
+pub fn parse() -> Config {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let profile = take_profile(&mut args);
+    parse_args(args, profile).unwrap_or_else(|e| usage_error(&e))
+}
+
+// The same command line again, as seen by another profile. It got past the
+// first time, so anything wrong is in that profile's config file, and with
+// the game already on screen it's up to the caller to show it.
+pub fn parse_for_profile(profile: &str) -> Result<Config, String> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    take_profile(&mut args);
+    parse_args(args, Some(profile.to_string()))
+}
+
+fn parse_args(args: Vec<String>, profile: Option<String>) -> Result<Config, String> {
+    let mut config = Config::default();
+    let mut args = args.into_iter().peekable();
+
+    if let Some(name) = &profile {
+        config.player_name = name.clone();
+    }
+    config.profile = profile;
+
+    if args.peek().is_some_and(|arg| arg == "paths") {
+        println!("config: {}", paths::config_dir().display());
+        println!("data:   {}", paths::data_dir().display());
+        println!("cache:  {}", paths::cache_dir().display());
+        exit(0);
+    }
+
+    if args.peek().is_some_and(|arg| arg == "scores") {
+        args.next();
+        config.scores_command = Some(match args.next().as_deref() {
+            None | Some("list") => ScoresCommand::List,
+            Some("export") => ScoresCommand::Export(value(&mut args, "export")?),
+            Some("import") => ScoresCommand::Import(value(&mut args, "import")?),
+            Some(other) => return Err(format!("Unknown scores command: {}", other)),
+        });
+        return Ok(config);
+    }
+
+    if args.peek().is_some_and(|arg| arg == "verify") {
+        args.next();
+        config.verify = Some(value(&mut args, "verify")?);
+        return Ok(config);
+    }
+
+    if args.peek().is_some_and(|arg| arg == "watch") {
+        args.next();
+        config.watch = Some(value(&mut args, "watch")?);
+    }
+
+    // What was last picked in the menus goes over the files, and the command
+    // line goes last so it wins over everything
+    let mut file_args = config_file_args(&paths::config_file())?;
+    if let Some(name) = &config.profile {
+        file_args.extend(config_file_args(&paths::profile_config_file(name))?);
+    }
+    apply_args(&mut config, file_args)?;
+    Recent::load(config.profile.as_deref()).apply(&mut config);
+    apply_args(&mut config, args)?;
+
+    if config.mode.is_some_and(|mode| mode.needs_two()) && (config.screensaver || config.control.is_some()) {
+        return Err("Two-player modes need both players on the keyboard, it can't go with --screensaver or --control".into());
+    }
+
+    if config.target_length.is_some() && config.target_apples.is_some() {
+        return Err("Pick one goal, --target-length or --apples".into());
+    }
+
+    if config.screensaver && matches!(config.strategy, Some(Strategy::Greedy | Strategy::Random)) {
+        return Err("The screensaver only plays pathfinding or hamiltonian, the others are just for --simulate".into());
+    }
+
+    let conflicts = config.keys.conflicts();
+    if !conflicts.is_empty() {
+        let keys: Vec<String> = conflicts.iter().map(char::to_string).collect();
+        return Err(format!("The {} keys can't steer, {} already mean something else", name_of(KeyPreset::NAMES, KeyPreset::by_name, &config.keys), keys.join(", ")));
+    }
+
+    Ok(config)
+}
+
+fn apply_args(config: &mut Config, args: impl IntoIterator<Item = String>) -> Result<(), String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--screensaver" => config.screensaver = true,
+            "--theme" => {
+                let name = value(&mut args, &arg)?;
+                config.theme = Theme::by_name(&name)
+                    .ok_or_else(|| format!("Unknown theme {}, expected one of: {}", name, Theme::NAMES.join(", ")))?;
+            },
+            "--vertical-speed" => {
+                let factor = value(&mut args, &arg)?.parse().ok().filter(|f: &f64| *f > 0.0);
+                config.vertical_speed_factor = Some(factor.ok_or("Invalid vertical speed factor")?);
+            },
+            "--lan" => config.lan = true,
+            "--name" => config.player_name = value(&mut args, &arg)?,
+            "--ghost" => config.ghost = true,
+            "--confirm-turns" => config.confirm_turns = true,
+            "--hints" => config.hints = true,
+            "--magnets" => config.magnets = true,
+            "--shrink" => config.shrink = true,
+            "--sprint" => config.sprint = true,
+            "--quick-quit" => config.confirm_quit = false,
+            "--help-pauses" => config.help_pauses = true,
+            "--assist" => config.assist = true,
+            "--check-update" => config.check_update = true,
+            "--controls" => {
+                let name = value(&mut args, &arg)?;
+                config.controls = ControlScheme::by_name(&name)
+                    .ok_or_else(|| format!("Unknown controls {}, expected one of: {}", name, ControlScheme::NAMES.join(", ")))?;
+            },
+            "--keys" => {
+                let name = value(&mut args, &arg)?;
+                config.keys = KeyPreset::by_name(&name)
+                    .ok_or_else(|| format!("Unknown keys {}, expected one of: {}", name, KeyPreset::NAMES.join(", ")))?;
+            },
+            "--mode" => {
+                let name = value(&mut args, &arg)?;
+                config.mode = Some(Mode::by_name(&name)
+                    .ok_or_else(|| format!("Unknown mode {}, expected one of: {}", name, Mode::NAMES.join(", ")))?);
+            },
+            "--mirror" => {
+                let name = value(&mut args, &arg)?;
+                config.mirror = Mirror::by_name(&name)
+                    .ok_or_else(|| format!("Unknown mirror mode {}, expected one of: {}", name, Mirror::NAMES.join(", ")))?;
+            },
+            "--fog" => {
+                let radius = value(&mut args, &arg)?.parse().ok().filter(|r| *r > 0);
+                config.fog = Some(radius.ok_or("Invalid fog radius")?);
+            },
+            "--target-length" => {
+                let length = value(&mut args, &arg)?.parse().ok().filter(|l| *l > 1);
+                config.target_length = Some(length.ok_or("Invalid target length")?);
+            },
+            "--apples" => {
+                let apples = value(&mut args, &arg)?.parse().ok().filter(|a| *a > 0);
+                config.target_apples = Some(apples.ok_or("Invalid number of apples")?);
+            },
+            "--garbage" => config.garbage = true,
+            "--hunger" => {
+                let steps = value(&mut args, &arg)?.parse().ok().filter(|s| *s > 0);
+                config.hunger = Some(steps.ok_or("Invalid number of steps")?);
+            },
+            "--growth" => {
+                let segments = value(&mut args, &arg)?.parse().ok().filter(|s| *s > 0);
+                config.growth.per_apple = segments.ok_or("Invalid number of segments")?;
+            },
+            "--growth-ramp" => {
+                let points = value(&mut args, &arg)?.parse().ok().filter(|p| *p > 0);
+                config.growth.ramp = Some(points.ok_or("Invalid number of points")?);
+            },
+            "--break-reminder" => {
+                let minutes = value(&mut args, &arg)?.parse().ok().filter(|m| *m > 0);
+                config.break_reminder = Some(minutes.ok_or("Invalid number of minutes")?);
+            },
+            "--idle-pause" => {
+                let secs = value(&mut args, &arg)?.parse().ok().filter(|s| *s > 0);
+                config.idle_pause = Some(secs.ok_or("Invalid number of seconds")?);
+            },
+            "--acceleration" => {
+                let name = value(&mut args, &arg)?;
+                config.acceleration = Acceleration::by_name(&name)
+                    .ok_or_else(|| format!("Unknown acceleration {}, expected one of: {}", name, Acceleration::NAMES.join(", ")))?;
+            },
+            "--scale-speed" => {
+                let name = value(&mut args, &arg)?;
+                config.board_scaling = BoardScaling::by_name(&name)
+                    .ok_or_else(|| format!("Unknown scaling {}, expected one of: {}", name, BoardScaling::NAMES.join(", ")))?;
+            },
+            "--distance-scoring" => config.distance_scoring = true,
+            "--heatmap" => config.heatmap = true,
+            "--event-log" => config.event_log = true,
+            "--pip" => config.pip = true,
+            "--shape" => {
+                let name = value(&mut args, &arg)?;
+                config.shape = Shape::by_name(&name)
+                    .ok_or_else(|| format!("Unknown shape {}, expected one of: {}", name, Shape::NAMES.join(", ")))?;
+            },
+            "--puzzle" => {
+                let path = value(&mut args, &arg)?;
+                config.puzzle = Some(Level::load(&path)?);
+            },
+            "--level" => {
+                let name = value(&mut args, &arg)?;
+                config.puzzle = Some(pack::load(&name)
+                    .ok_or_else(|| format!("Unknown level {}, expected one of: {}", name, pack::names().join(", ")))?);
+            },
+            "--step" => config.step_mode = true,
+            "--json-events" => config.json_events = true,
+            "--summary" => config.summary = true,
+            "--export-metrics" => config.export_metrics = Some(value(&mut args, &arg)?),
+            "--control" => config.control = Some(ControlSource::by_name(&value(&mut args, &arg)?)),
+            "--simulate" => {
+                let games = value(&mut args, &arg)?.parse().ok().filter(|n| *n > 0);
+                config.simulate = Some(games.ok_or("Invalid number of games")?);
+            },
+            "--strategy" => {
+                let name = value(&mut args, &arg)?;
+                config.strategy = Some(Strategy::by_name(&name)
+                    .ok_or_else(|| format!("Unknown strategy {}, expected one of: {}", name, Strategy::NAMES.join(", ")))?);
+            },
+            "--glyphs" => {
+                let name = value(&mut args, &arg)?;
+                config.glyphs = Glyphs::by_name(&name)
+                    .ok_or_else(|| format!("Unknown glyphs {}, expected one of: {}", name, Glyphs::NAMES.join(", ")))?;
+            },
+            "--renderer" => {
+                let name = value(&mut args, &arg)?;
+                config.renderer = Renderer::by_name(&name)
+                    .ok_or_else(|| format!("Unknown renderer {}, expected one of: {}", name, Renderer::NAMES.join(", ")))?;
+            },
+            "--discord" => {
+                let client_id = value(&mut args, &arg)?;
+                if !cfg!(feature = "discord") {
+                    return Err("This build has no Discord support, build it with --features discord".into());
+                }
+                config.discord = Some(client_id);
+            },
+            "--edit" => config.edit = Some(value(&mut args, &arg)?),
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+    Ok(())
+}
+
+// Can be anywhere on the command line, since it decides which config files apply
+fn take_profile(args: &mut Vec<String>) -> Option<String> {
+    let i = args.iter().position(|arg| arg == "--profile")?;
+    if i + 1 >= args.len() {
+        usage_error("Missing value for --profile");
+    }
+
+    let name = args.remove(i + 1);
+    args.remove(i);
+
+    // It ends up in paths, keep it to something that's safe there
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        usage_error("Profile names can only have letters, digits, - and _");
+    }
+
+    Some(name)
+}
+
+// Turns each `option = value` line into the matching command line arguments
+fn config_file_args(path: &Path) -> Result<Vec<String>, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(_) => return Ok(vec![]),
+    };
+
+    let mut args = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = line.split_once('=')
+            .ok_or_else(|| format!("Error in {} line {}: expected option = value", path.display(), i + 1))?;
+        let value = match value.trim() {
+            quoted if quoted.starts_with('"') => quoted[1..].split('"').next().unwrap_or(""),
+            plain => plain.split('#').next().unwrap().trim(),
+        };
+
+        match value {
+            "true" => args.push(format!("--{}", key.trim())),
+            "false" => {},
+            value => args.extend([format!("--{}", key.trim()), value.to_string()]),
+        }
+    }
+
+    Ok(args)
+}
+
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("Missing value for {}", flag))
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    eprintln!("{}", USAGE);
+    exit(2);
+}
@@ -0,0 +1,221 @@
+use std::env;
+
+use crate::board::Shape;
+use crate::caps::Glyphs;
+use crate::control::ControlSource;
+use crate::growth::Growth;
+use crate::controls::{ControlScheme, KeyPreset};
+use crate::level::Level;
+use crate::recording;
+use crate::scores::ScoresCommand;
+use crate::sim::Strategy;
+use crate::term::Renderer;
+use crate::TermInt;
+use crate::speed::{Acceleration, BoardScaling};
+use crate::theme::Theme;
+
+pub struct Config {
+    pub screensaver: bool,
+    pub theme: Theme,
+    pub vertical_speed_factor: Option<f64>,
+    pub lan: bool,
+    pub player_name: String,
+    pub ghost: bool,
+    pub confirm_turns: bool, // Turns wait for Enter, with a preview until then
+    pub hints: bool,
+    pub acceleration: Acceleration,
+    pub board_scaling: BoardScaling,
+    pub distance_scoring: bool,
+    pub heatmap: bool,
+    pub event_log: bool,
+    pub pip: bool,
+    pub shape: Shape,
+    pub puzzle: Option<Level>,
+    pub step_mode: bool,
+    pub json_events: bool,
+    pub export_metrics: Option<String>, // A CSV file for every step of the last round
+    pub summary: bool, // Printed on the way out
+    pub control: Option<ControlSource>,
+    pub simulate: Option<u64>,
+    pub strategy: Option<Strategy>,
+    pub renderer: Renderer,
+    pub glyphs: Glyphs,
+    pub edit: Option<String>,
+    pub magnets: bool,
+    pub double_speed: bool, // Only ever set for a chaos round
+    pub extra_apples: bool, // Same
+    pub shrink: bool, // Classic games get a new wall every so many points
+    pub target_length: Option<usize>, // Puzzles can have their own
+    pub target_apples: Option<u64>,
+    pub hunger: Option<u16>, // Steps without eating before losing a segment
+    pub growth: Growth,
+    pub garbage: bool, // In versus, every apple puts a wall on the other board
+    pub sprint: bool,
+    pub fog: Option<TermInt>,
+    pub mirror: Mirror,
+    pub controls: ControlScheme,
+    pub keys: KeyPreset,
+    pub scores_command: Option<ScoresCommand>,
+    pub profile: Option<String>,
+    pub mode: Option<Mode>, // Picked from the menu if not given
+    pub confirm_quit: bool,
+    pub help_pauses: bool, // The key help pauses the game while it's up
+    pub assist: bool,
+    pub check_update: bool,
+    pub watch: Option<String>,
+    pub verify: Option<String>,
+    pub break_reminder: Option<u64>, // Minutes
+    pub idle_pause: Option<u64>, // Seconds without a key pressed
+    pub discord: Option<String>, // The application's client ID
+}
+
+impl Config {
+    // Everything that changes how a game plays out, looks don't count
+    pub fn rules_hash(&self) -> u64 {
+        let rules = format!("mode={} shape={} acceleration={} scaling={} vertical={:?} far_apples={} magnets={} double_speed={} extra_apples={} shrink={} target_length={:?} target_apples={:?} hunger={:?} growth={}+{:?} garbage={} sprint={} fog={:?} mirror={} assist={} puzzle={}",
+            self.mode.unwrap_or(Mode::Classic).name(), name_of(Shape::NAMES, Shape::by_name, &self.shape),
+            name_of(Acceleration::NAMES, Acceleration::by_name, &self.acceleration),
+            name_of(BoardScaling::NAMES, BoardScaling::by_name, &self.board_scaling), self.vertical_speed_factor,
+            self.distance_scoring, self.magnets, self.double_speed, self.extra_apples, self.shrink, self.target_length, self.target_apples, self.hunger, self.growth.per_apple, self.growth.ramp, self.garbage, self.sprint, self.fog, name_of(Mirror::NAMES, Mirror::by_name, &self.mirror),
+            self.assist, self.puzzle.as_ref().map_or(String::new(), Level::to_text));
+        recording::checksum(rules.as_bytes())
+    }
+}
+
+// The name an option was given by, for those that only go from names to values
+pub fn name_of<T: PartialEq>(names: &[&'static str], by_name: fn(&str) -> Option<T>, current: &T) -> &'static str {
+    names.iter().find(|name| by_name(name).as_ref() == Some(current)).copied().unwrap_or(names[0])
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Mode {
+    Classic,
+    Timed,     // As many points as possible in TIMED_MODE_SECS
+    Obstacles, // Random walls scattered around each round
+    Chaos,     // Classic with one or two random modifiers every round
+    Drifting,  // Apples that move around on their own
+    TwoPlayer, // Two snakes on one keyboard, last one alive wins
+    Versus,    // Two boards side by side, racing to a score
+    Coop,      // One snake, one player turning it left and right and the other up and down
+}
+
+impl Mode {
+    pub const NAMES: &'static [&'static str] = &["classic", "timed", "obstacles", "chaos", "drifting", "two-player", "versus", "co-op"];
+    pub const ALL: [Mode; 8] = [Mode::Classic, Mode::Timed, Mode::Obstacles, Mode::Chaos, Mode::Drifting, Mode::TwoPlayer, Mode::Versus, Mode::Coop];
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Mode::Classic),
+            "timed" => Some(Mode::Timed),
+            "obstacles" => Some(Mode::Obstacles),
+            "chaos" => Some(Mode::Chaos),
+            "drifting" => Some(Mode::Drifting),
+            "two-player" => Some(Mode::TwoPlayer),
+            "versus" => Some(Mode::Versus),
+            "co-op" => Some(Mode::Coop),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Classic => "classic",
+            Mode::Timed => "timed",
+            Mode::Obstacles => "obstacles",
+            Mode::Chaos => "chaos",
+            Mode::Drifting => "drifting",
+            Mode::TwoPlayer => "two-player",
+            Mode::Versus => "versus",
+            Mode::Coop => "co-op",
+        }
+    }
+
+    // Both on the keyboard, and no high scores
+    pub fn is_two_player(&self) -> bool {
+        matches!(self, Mode::TwoPlayer | Mode::Versus)
+    }
+
+    // Both on the keyboard, co-op too though there's one snake and a score for it
+    pub fn needs_two(&self) -> bool {
+        self.is_two_player() || *self == Mode::Coop
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Mirror {
+    Off,
+    Always,
+    Toggle, // Every MIRROR_PERIOD_SECS, with a warning first
+}
+
+impl Mirror {
+    pub const NAMES: &'static [&'static str] = &["off", "always", "toggle"];
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Mirror::Off),
+            "always" => Some(Mirror::Always),
+            "toggle" => Some(Mirror::Toggle),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            screensaver: false,
+            theme: Theme::classic(),
+            vertical_speed_factor: None,
+            lan: false,
+            player_name: env::var("USER").or_else(|_| env::var("USERNAME")).unwrap_or_else(|_| "player".into()),
+            ghost: false,
+            confirm_turns: false,
+            hints: false,
+            acceleration: Acceleration::Linear,
+            board_scaling: BoardScaling::None,
+            distance_scoring: false,
+            heatmap: false,
+            event_log: false,
+            pip: false,
+            shape: Shape::Rectangle,
+            puzzle: None,
+            step_mode: false,
+            json_events: false,
+            export_metrics: None,
+            summary: false,
+            control: None,
+            simulate: None,
+            strategy: None,
+            renderer: Renderer::Auto,
+            glyphs: Glyphs::Auto,
+            edit: None,
+            magnets: false,
+            double_speed: false,
+            extra_apples: false,
+            shrink: false,
+            target_length: None,
+            target_apples: None,
+            hunger: None,
+            growth: Growth::CLASSIC,
+            garbage: false,
+            sprint: false,
+            fog: None,
+            mirror: Mirror::Off,
+            controls: ControlScheme::Absolute,
+            keys: KeyPreset::Standard,
+            scores_command: None,
+            profile: None,
+            mode: None,
+            confirm_quit: true,
+            help_pauses: false,
+            assist: false,
+            check_update: false,
+            watch: None,
+            verify: None,
+            break_reminder: None,
+            idle_pause: None,
+            discord: None,
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::io::{self, BufRead, BufReader, Write, stdin};
+use std::net::TcpStream;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::{thread, time::Duration};
+
+use crate::snake::Direction::{self, *};
+
+const IRC_PORT: u16 = 6667;
+const IRC_RECONNECT_WAIT: Duration = Duration::from_secs(5);
+
+// Lets another program steer the snake by writing one direction per line,
+// either on our stdin or on a Unix socket. Anything that isn't a direction
+// is ignored, so bots can be sloppy about what they send.
+pub enum ControlSource {
+    Stdin,
+    Socket(String),
+    // Everyone in an IRC channel votes, like Twitch chat, given as irc://host[:port]/channel
+    Irc { server: String, channel: String },
+}
+
+pub struct Control {
+    commands: Receiver<Command>,
+    voting: bool,
+}
+
+// Who sent it only matters when voting, so nobody gets more than one vote per step
+struct Command {
+    voter: String,
+    direction: Direction,
+}
+
+impl ControlSource {
+    pub fn by_name(name: &str) -> Self {
+        match name {
+            "stdin" | "-" => ControlSource::Stdin,
+            url if url.starts_with("irc://") => {
+                let (server, channel) = url["irc://".len()..].split_once('/').unwrap_or((&url["irc://".len()..], ""));
+                ControlSource::Irc { server: server.into(), channel: channel.trim_start_matches('#').to_lowercase() }
+            },
+            path => ControlSource::Socket(path.into()),
+        }
+    }
+}
+
+impl Control {
+    pub fn start(source: &ControlSource) -> io::Result<Self> {
+        let (sender, directions) = channel();
+
+        match source {
+            ControlSource::Stdin => {
+                thread::spawn(move || read_commands(stdin().lock(), &sender));
+            },
+            ControlSource::Socket(path) => listen(path, sender)?,
+            ControlSource::Irc { server, channel } => join_irc(server, channel, sender)?,
+        }
+
+        Ok(Control { commands: directions, voting: matches!(source, ControlSource::Irc { .. }) })
+    }
+
+    // Meant to be called once per step. Only the latest command counts if the bot
+    // sent several since the last one, when voting it's the one most voters want,
+    // counting each voter's last vote, and ties go to whichever came last.
+    pub fn next_direction(&self) -> Option<Direction> {
+        let commands: Vec<Command> = self.commands.try_iter().collect();
+        if !self.voting {
+            return commands.last().map(|command| command.direction);
+        }
+
+        let votes: Vec<(usize, &Command)> = commands.iter().enumerate()
+            .filter(|(i, command)| !commands[i + 1..].iter().any(|later| later.voter == command.voter))
+            .collect();
+        [Up, Down, Left, Right].iter()
+            .filter_map(|dir| {
+                let count = votes.iter().filter(|(_, vote)| vote.direction == *dir).count();
+                let last = votes.iter().rev().find(|(_, vote)| vote.direction == *dir)?.0;
+                Some((count, last, *dir))
+            })
+            .max_by_key(|(count, last, _)| (*count, *last))
+            .map(|(_, _, dir)| dir)
+    }
+}
+
+#[cfg(unix)]
+fn listen(path: &str, sender: Sender<Command>) -> io::Result<()> {
+    use std::os::unix::net::UnixListener;
+
+    // Left over from a previous run, nobody else could be listening on it
+    std::fs::remove_file(path).ok();
+    let listener = UnixListener::bind(path)?;
+
+    // One bot at a time, the next one can connect once the current one hangs up
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if !read_commands(BufReader::new(stream), &sender) {
+                break;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn listen(_path: &str, _sender: Sender<Command>) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "sockets are only supported on Unix"))
+}
+
+// Connecting once right away, so a wrong address shows up before the game starts.
+// After that it keeps coming back if the connection drops.
+fn join_irc(server: &str, channel: &str, sender: Sender<Command>) -> io::Result<()> {
+    if channel.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "IRC needs a channel, like irc://irc.chat.twitch.tv/name"));
+    }
+
+    let address = if server.contains(':') {server.to_string()} else {format!("{}:{}", server, IRC_PORT)};
+    let mut stream = irc_connect(&address, channel)?;
+    let channel = channel.to_string();
+
+    thread::spawn(move || loop {
+        if !read_chat(stream, &sender) {
+            return;
+        }
+
+        stream = loop {
+            thread::sleep(IRC_RECONNECT_WAIT);
+            if let Ok(stream) = irc_connect(&address, &channel) {
+                break stream;
+            }
+        };
+    });
+
+    Ok(())
+}
+
+// Read only, which Twitch lets anyone do with a justinfan nick and no password
+fn irc_connect(address: &str, channel: &str) -> io::Result<TcpStream> {
+    let mut stream = TcpStream::connect(address)?;
+    let nick = format!("justinfan{}", rand::random::<u32>() % 100000);
+    write!(stream, "NICK {}\r\nUSER {} 0 * :snake\r\nJOIN #{}\r\n", nick, nick, channel)?;
+    Ok(stream)
+}
+
+// Returns false once the game isn't listening anymore
+fn read_chat(mut stream: TcpStream, sender: &Sender<Command>) -> bool {
+    let reader = match stream.try_clone() {
+        Ok(stream) => BufReader::new(stream),
+        Err(_) => return true,
+    };
+
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+
+        // Servers hang up on clients that don't answer these
+        if let Some(token) = line.strip_prefix("PING ") {
+            if write!(stream, "PONG {}\r\n", token).is_err() {
+                break;
+            }
+            continue;
+        }
+
+        // :nick!user@host PRIVMSG #channel :message
+        let mut parts = line.splitn(4, ' ');
+        let (source, command, message) = (parts.next(), parts.next(), parts.nth(1));
+        if command != Some("PRIVMSG") {
+            continue;
+        }
+
+        let voter = source.unwrap_or("").trim_start_matches(':').split('!').next().unwrap_or("").to_string();
+        if let Some(direction) = message.and_then(|message| parse_command(message.trim_start_matches(':'))) {
+            if sender.send(Command { voter, direction }).is_err() {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+// Returns false once the game isn't listening anymore
+fn read_commands(reader: impl BufRead, sender: &Sender<Command>) -> bool {
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+
+        if let Some(dir) = parse_command(&line) {
+            if sender.send(Command { voter: String::new(), direction: dir }).is_err() {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+fn parse_command(line: &str) -> Option<Direction> {
+    match line.trim().to_lowercase().as_str() {
+        "up" | "u" => Some(Up),
+        "down" | "d" => Some(Down),
+        "left" | "l" => Some(Left),
+        "right" | "r" => Some(Right),
+        _ => None,
+    }
+}
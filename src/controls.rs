@@ -0,0 +1,181 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::input::Action;
+use crate::snake::Direction::{self, *};
+
+// How key presses turn into directions for the snake
+#[derive(Copy, Clone, PartialEq)]
+pub enum ControlScheme {
+    Absolute, // Each key points one way, arrows or WASD
+    Relative, // Two keys turn left or right from wherever the snake is heading, like old phones
+}
+
+impl ControlScheme {
+    pub const NAMES: &'static [&'static str] = &["absolute", "relative"];
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "absolute" => Some(ControlScheme::Absolute),
+            "relative" => Some(ControlScheme::Relative),
+            _ => None,
+        }
+    }
+
+    pub fn help(&self, keys: KeyPreset) -> &'static str {
+        match (self, keys) {
+            (ControlScheme::Absolute, KeyPreset::Standard) => "Arrow keys or WASD to move",
+            (ControlScheme::Absolute, KeyPreset::Ijkl) => "Arrow keys or IJKL to move",
+            (ControlScheme::Absolute, KeyPreset::Numpad) => "Arrow keys or 8456 to move",
+            (ControlScheme::Absolute, KeyPreset::Arrows) => "Arrow keys to move",
+            (ControlScheme::Relative, KeyPreset::Standard) => "Left/right or A/D to turn",
+            (ControlScheme::Relative, KeyPreset::Ijkl) => "Left/right or J/L to turn",
+            (ControlScheme::Relative, KeyPreset::Numpad) => "Left/right or 4/6 to turn",
+            (ControlScheme::Relative, KeyPreset::Arrows) => "Left/right to turn",
+        }
+    }
+
+    pub fn direction(&self, keys: KeyPreset, code: KeyCode, heading: Direction) -> Option<Direction> {
+        let pressed = keys.direction(code)?;
+        match self {
+            ControlScheme::Absolute => Some(pressed),
+            ControlScheme::Relative => match pressed {
+                Left => Some(turn_left(heading)),
+                Right => Some(turn_left(heading).opposite()),
+                _ => None,
+            },
+        }
+    }
+}
+
+// Which keys point which way, besides the arrows, which always work.
+// Meant for left-handed and one-handed players.
+#[derive(Copy, Clone, PartialEq)]
+pub enum KeyPreset {
+    Standard, // WASD
+    Ijkl,     // The same shape, for the right hand
+    Numpad,   // 8 up, 4 left, 5 down and 6 right
+    Arrows,   // Nothing else, for the left hand on the arrows and the right one free
+}
+
+impl KeyPreset {
+    pub const NAMES: &'static [&'static str] = &["standard", "ijkl", "numpad", "arrows"];
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "standard" => Some(KeyPreset::Standard),
+            "ijkl" => Some(KeyPreset::Ijkl),
+            "numpad" => Some(KeyPreset::Numpad),
+            "arrows" => Some(KeyPreset::Arrows),
+            _ => None,
+        }
+    }
+
+    // Up, left, down and right
+    pub fn letters(&self) -> Option<[char; 4]> {
+        match self {
+            KeyPreset::Standard => Some(['w', 'a', 's', 'd']),
+            KeyPreset::Ijkl => Some(['i', 'j', 'k', 'l']),
+            KeyPreset::Numpad => Some(['8', '4', '5', '6']),
+            KeyPreset::Arrows => None,
+        }
+    }
+
+    // Letters count with shift held too
+    pub fn direction(&self, code: KeyCode) -> Option<Direction> {
+        match code {
+            KeyCode::Up => Some(Up),
+            KeyCode::Left => Some(Left),
+            KeyCode::Down => Some(Down),
+            KeyCode::Right => Some(Right),
+            KeyCode::Char(c) => {
+                let i = self.letters()?.iter().position(|key| *key == c.to_ascii_lowercase())?;
+                Some([Up, Left, Down, Right][i])
+            },
+            _ => None,
+        }
+    }
+
+    // Shift with an arrow, or a capital letter, which is the same thing for most terminals
+    pub fn is_sprint(&self, ev: &KeyEvent) -> bool {
+        match ev.code {
+            KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right => ev.modifiers.contains(KeyModifiers::SHIFT),
+            KeyCode::Char(c) => c.is_ascii_uppercase() && self.direction(ev.code).is_some(),
+            _ => false,
+        }
+    }
+
+    // Keys that already do something else during a game, and would never steer
+    pub fn conflicts(&self) -> Vec<char> {
+        self.letters().into_iter().flatten()
+            .flat_map(|c| [c, c.to_ascii_uppercase()])
+            .filter(|c| Action::of(&KeyEvent::new(KeyCode::Char(*c), KeyModifiers::NONE)).is_some())
+            .collect()
+    }
+}
+
+// WASD for player 1 and the arrows for player 2, whatever the preset
+pub fn two_player_direction(code: KeyCode) -> Option<(usize, Direction)> {
+    match code {
+        KeyCode::Char('w') | KeyCode::Char('W') => Some((0, Up)),
+        KeyCode::Char('s') | KeyCode::Char('S') => Some((0, Down)),
+        KeyCode::Char('a') | KeyCode::Char('A') => Some((0, Left)),
+        KeyCode::Char('d') | KeyCode::Char('D') => Some((0, Right)),
+        KeyCode::Up => Some((1, Up)),
+        KeyCode::Down => Some((1, Down)),
+        KeyCode::Left => Some((1, Left)),
+        KeyCode::Right => Some((1, Right)),
+        _ => None,
+    }
+}
+
+// The same keys split by axis for co-op, one snake with player 1 turning it
+// left and right and player 2 up and down, so neither can get far alone
+pub fn coop_direction(code: KeyCode) -> Option<(usize, Direction)> {
+    let (player, dir) = two_player_direction(code)?;
+    let horizontal = matches!(dir, Left | Right);
+    (horizontal == (player == 0)).then_some((player, dir))
+}
+
+fn turn_left(heading: Direction) -> Direction {
+    match heading {
+        Up => Left,
+        Left => Down,
+        Down => Right,
+        Right => Up,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARROWS: [(KeyCode, Direction); 4] = [(KeyCode::Up, Up), (KeyCode::Down, Down), (KeyCode::Left, Left), (KeyCode::Right, Right)];
+
+    #[test]
+    fn modified_arrows_still_steer() {
+        for modifiers in [KeyModifiers::NONE, KeyModifiers::SHIFT, KeyModifiers::CONTROL, KeyModifiers::ALT] {
+            for (code, dir) in ARROWS {
+                let ev = KeyEvent::new(code, modifiers);
+                for keys in [KeyPreset::Standard, KeyPreset::Ijkl, KeyPreset::Numpad, KeyPreset::Arrows] {
+                    assert!(keys.direction(ev.code) == Some(dir));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shift_with_an_arrow_sprints() {
+        let keys = KeyPreset::Standard;
+        assert!(keys.is_sprint(&KeyEvent::new(KeyCode::Up, KeyModifiers::SHIFT)));
+        assert!(!keys.is_sprint(&KeyEvent::new(KeyCode::Up, KeyModifiers::CONTROL)));
+        assert!(keys.is_sprint(&KeyEvent::new(KeyCode::Char('W'), KeyModifiers::SHIFT)));
+        assert!(!keys.is_sprint(&KeyEvent::new(KeyCode::Char('Q'), KeyModifiers::SHIFT)));
+    }
+
+    #[test]
+    fn letters_count_with_shift() {
+        assert!(KeyPreset::Standard.direction(KeyCode::Char('A')) == Some(Left));
+        assert!(KeyPreset::Ijkl.direction(KeyCode::Char('i')) == Some(Up));
+        assert!(KeyPreset::Arrows.direction(KeyCode::Char('w')).is_none());
+    }
+}
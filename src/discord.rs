@@ -0,0 +1,158 @@
+use std::{io::{Read, Write}, process, thread};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::json::Value;
+
+// Discord takes at most 5 updates every 20 seconds
+const UPDATE_INTERVAL: Duration = Duration::from_secs(4);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(15);
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+
+// Shows the game on the player's Discord profile. The desktop app is talked to
+// over its local IPC socket from a thread of its own, fed with the same events
+// as --json-events. Nothing is said if Discord isn't running, it's just tried
+// again every now and then.
+pub struct Presence {
+    events: Sender<String>,
+}
+
+trait Pipe: Read + Write + Send {}
+impl<T: Read + Write + Send> Pipe for T {}
+
+// What's on the profile right now
+#[derive(Clone, PartialEq)]
+struct Activity {
+    playing: bool,
+    score: u64,
+    length: u64,
+    started: u64, // Unix seconds, Discord counts the time up from it
+}
+
+impl Presence {
+    pub fn start(client_id: &str) -> Self {
+        let (events, receiver) = channel();
+        let client_id = client_id.to_string();
+        thread::spawn(move || presence_loop(&client_id, receiver));
+        Presence { events }
+    }
+
+    pub fn event(&self, json: String) {
+        self.events.send(json).ok();
+    }
+}
+
+fn presence_loop(client_id: &str, events: Receiver<String>) {
+    let mut pipe: Option<Box<dyn Pipe>> = None;
+    let mut last_attempt: Option<Instant> = None;
+    let mut activity: Option<Activity> = None;
+    let mut shown: Option<Activity> = None;
+    let mut last_update = Instant::now() - UPDATE_INTERVAL;
+
+    loop {
+        match events.recv_timeout(UPDATE_INTERVAL.saturating_sub(last_update.elapsed())) {
+            Ok(json) => if let Ok(event) = Value::parse(&json) {
+                apply(&mut activity, &event);
+            },
+            Err(RecvTimeoutError::Timeout) => {},
+            // The game is gone
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        if activity == shown || last_update.elapsed() < UPDATE_INTERVAL {
+            continue;
+        }
+
+        if pipe.is_none() && last_attempt.is_none_or(|at| at.elapsed() >= RECONNECT_INTERVAL) {
+            last_attempt = Some(Instant::now());
+            pipe = connect(client_id);
+        }
+
+        if let (Some(conn), Some(current)) = (pipe.as_mut(), &activity) {
+            last_update = Instant::now();
+            match send(conn, OP_FRAME, &set_activity(current)) {
+                Ok(()) => shown = activity.clone(),
+                // Discord was closed, maybe it comes back later
+                Err(_) => pipe = None,
+            }
+        }
+    }
+}
+
+fn apply(activity: &mut Option<Activity>, event: &Value) {
+    let num = |key: &str| event.get(key).and_then(Value::as_u64);
+    match event.get("event").and_then(Value::as_str) {
+        Some("start") => {
+            let started = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            let length = event.get("snake").and_then(Value::as_array).map_or(0, |body| body.len() as u64);
+            *activity = Some(Activity { playing: true, score: 0, length, started });
+        },
+        Some("step") => if let (Some(current), Some(length)) = (activity.as_mut(), num("length")) {
+            current.length = length;
+        },
+        Some("eat") => if let (Some(current), Some(score)) = (activity.as_mut(), num("score")) {
+            current.score = score;
+        },
+        Some("game_over") => if let Some(current) = activity.as_mut() {
+            current.playing = false;
+        },
+        _ => {},
+    }
+}
+
+fn set_activity(activity: &Activity) -> Value {
+    let state = if activity.playing {"Playing"} else {"Game over"};
+    let details = format!("Score {}, length {}", activity.score, activity.length);
+    let presence = Value::Object(vec![
+        ("details".into(), details.as_str().into()),
+        ("state".into(), state.into()),
+        ("timestamps".into(), Value::Object(vec![("start".into(), activity.started.into())])),
+    ]);
+
+    Value::Object(vec![
+        ("cmd".into(), "SET_ACTIVITY".into()),
+        ("args".into(), Value::Object(vec![("pid".into(), (process::id() as u64).into()), ("activity".into(), presence)])),
+        ("nonce".into(), format!("{}", rand::random::<u64>()).as_str().into()),
+    ])
+}
+
+// Discord listens on the first free one of ten sockets
+fn connect(client_id: &str) -> Option<Box<dyn Pipe>> {
+    let handshake = Value::Object(vec![("v".into(), 1u64.into()), ("client_id".into(), client_id.into())]);
+    (0..10).find_map(|i| {
+        let mut pipe = open_pipe(i)?;
+        send(&mut pipe, OP_HANDSHAKE, &handshake).ok()?;
+        Some(pipe)
+    })
+}
+
+#[cfg(unix)]
+fn open_pipe(i: u32) -> Option<Box<dyn Pipe>> {
+    let dir = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"].iter()
+        .find_map(|var| std::env::var(var).ok())
+        .unwrap_or_else(|| "/tmp".into());
+    let stream = std::os::unix::net::UnixStream::connect(format!("{}/discord-ipc-{}", dir, i)).ok()?;
+    stream.set_read_timeout(Some(Duration::from_secs(1))).ok()?;
+    Some(Box::new(stream))
+}
+
+#[cfg(windows)]
+fn open_pipe(i: u32) -> Option<Box<dyn Pipe>> {
+    let pipe = std::fs::OpenOptions::new().read(true).write(true).open(format!(r"\\.\pipe\discord-ipc-{}", i)).ok()?;
+    Some(Box::new(pipe))
+}
+
+// Every message gets an answer, which is read and dropped so the socket never fills up
+fn send(pipe: &mut Box<dyn Pipe>, op: u32, payload: &Value) -> std::io::Result<()> {
+    let payload = payload.to_string();
+    let mut frame = op.to_le_bytes().to_vec();
+    frame.extend((payload.len() as u32).to_le_bytes());
+    frame.extend(payload.as_bytes());
+    pipe.write_all(&frame)?;
+
+    let mut header = [0u8; 8];
+    pipe.read_exact(&mut header)?;
+    let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    pipe.read_exact(&mut vec![0u8; len])
+}
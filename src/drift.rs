@@ -0,0 +1,23 @@
+use crate::Coords;
+
+// One cell across and one down or up for every move, always diagonal
+pub type Velocity = (i16, i16);
+
+pub const DIAGONALS: [Velocity; 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+// Where something drifting that way is after one more cell, and which way it
+// goes on from there. Whatever it runs into turns it back along that axis,
+// like a ball off a wall, and it waits a turn where there's nowhere to go.
+pub fn next(pos: Coords, velocity: Velocity, is_free: impl Fn(Coords) -> bool) -> (Coords, Velocity) {
+    let (dx, dy) = velocity;
+    [(dx, dy), (-dx, dy), (dx, -dy), (-dx, -dy)].iter()
+        .filter_map(|v| Some((offset(pos, *v)?, *v)))
+        .find(|(pos, _)| is_free(*pos))
+        .unwrap_or((pos, (-dx, -dy)))
+}
+
+///////////////////////////////////////////////////////////////////////////
+
+fn offset(pos: Coords, velocity: Velocity) -> Option<Coords> {
+    Some((pos.0.checked_add_signed(velocity.0)?, pos.1.checked_add_signed(velocity.1)?))
+}
@@ -0,0 +1,128 @@
+use std::{path::Path, process::exit};
+
+use crate::{Coords, TermInt};
+use crate::level::{Level, MAX_APPLES};
+use crate::caps::Glyphs;
+use crate::term::{Cell, Renderer, TermManager};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::ContentStyle;
+
+const HELP: &str = "Arrows move, space wall, h head, n apple, del clear, ctrl+s save, esc quit";
+
+// Draws a level file as-is with a cursor on top, the bottom row is left for the status line
+pub struct Editor {
+    path: String,
+    level: Level,
+    cursor: Coords,
+    term: TermManager,
+}
+
+impl Editor {
+    pub fn new(path: String, renderer: Renderer, glyphs: Glyphs) -> Self {
+        let term = TermManager::new(false, renderer, glyphs);
+        let (width, height) = term.get_terminal_size();
+
+        let level = if Path::new(&path).exists() {
+            Level::load(&path).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                exit(1);
+            })
+        } else {
+            Level::empty(width, height - 1)
+        };
+
+        if level.width > width || level.height > height - 1 {
+            eprintln!("The level is {}x{}, it doesn't fit in the terminal", level.width, level.height);
+            exit(1);
+        }
+
+        let cursor = (level.width / 2, level.height / 2);
+        Editor { path, level, cursor, term }
+    }
+
+    pub fn run(&mut self) {
+        self.term.setup();
+        self.draw_level();
+        self.draw_status(HELP);
+
+        loop {
+            self.term.show_cursor_at(self.cursor);
+            self.term.flush();
+
+            let key = self.term.read_key_blocking();
+            let mut status = HELP.to_string();
+            match key {
+                KeyEvent { code: KeyCode::Char('c'), modifiers: KeyModifiers::CONTROL } | KeyEvent { code: KeyCode::Esc, .. } => break,
+                KeyEvent { code: KeyCode::Char('s'), modifiers: KeyModifiers::CONTROL } => {
+                    status = match self.level.save(&self.path) {
+                        Ok(()) => format!("Saved to {}", self.path),
+                        Err(e) => e,
+                    };
+                },
+                KeyEvent { code, .. } => match code {
+                    KeyCode::Up => self.move_cursor(0, -1),
+                    KeyCode::Down => self.move_cursor(0, 1),
+                    KeyCode::Left => self.move_cursor(-1, 0),
+                    KeyCode::Right => self.move_cursor(1, 0),
+                    KeyCode::Char(' ') => {
+                        let wall = !self.level.is_wall(self.cursor);
+                        self.level.set_wall(self.cursor, wall);
+                    },
+                    KeyCode::Char('h') => {
+                        // Only one head, the old one goes away
+                        if let Some(old) = self.level.spawn {
+                            self.level.clear(old);
+                            self.draw_cell(old);
+                        }
+                        self.level.clear(self.cursor);
+                        self.level.spawn = Some(self.cursor);
+                    },
+                    KeyCode::Char('n') if self.level.apples.len() < MAX_APPLES => {
+                        self.level.clear(self.cursor);
+                        self.level.apples.push(self.cursor);
+                        // Apples after a removed one got renumbered
+                        self.draw_level();
+                    },
+                    KeyCode::Char('n') => status = format!("No more than {} apples", MAX_APPLES),
+                    KeyCode::Delete | KeyCode::Backspace => {
+                        self.level.clear(self.cursor);
+                        self.draw_level();
+                    },
+                    _ => {}
+                }
+            }
+
+            self.draw_cell(self.cursor);
+            self.draw_status(&status);
+        }
+
+        self.term.restore();
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn move_cursor(&mut self, dx: i32, dy: i32) {
+        let x = (self.cursor.0 as i32 + dx).clamp(0, self.level.width as i32 - 1);
+        let y = (self.cursor.1 as i32 + dy).clamp(0, self.level.height as i32 - 1);
+        self.cursor = (x as TermInt, y as TermInt);
+    }
+
+    fn draw_level(&mut self) {
+        for y in 0..self.level.height {
+            for x in 0..self.level.width {
+                self.draw_cell((x, y));
+            }
+        }
+    }
+
+    fn draw_cell(&mut self, pos: Coords) {
+        self.term.print_at(pos, Cell::plain(self.level.char_at(pos)));
+    }
+
+    fn draw_status(&mut self, text: &str) {
+        let (width, height) = self.term.get_terminal_size();
+        let line = format!("{:<w$}", text, w = width as usize);
+        self.term.print_text_at((0, height - 1), &line, ContentStyle::new());
+    }
+}
@@ -0,0 +1,266 @@
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::{Coords, TermInt};
+use crate::board::{Board, Shape};
+use crate::goal::Goal;
+use crate::growth::Growth;
+use crate::hunger::Hunger;
+use crate::snake::{Snake, Direction::{self, Right}, MoveResult::*};
+
+pub type Action = Direction;
+
+const INITIAL_SNAKE_LENGTH: i16 = 4;
+const REWARD_APPLE: f64 = 1.0;
+const REWARD_CRASH: f64 = -1.0;
+const REWARD_STEP: f64 = 0.0;
+
+// Values of each cell in an observation grid
+pub const CELL_EMPTY: u8 = 0;
+pub const CELL_WALL: u8 = 1;
+pub const CELL_BODY: u8 = 2;
+pub const CELL_HEAD: u8 = 3;
+pub const CELL_APPLE: u8 = 4;
+
+/// A Gym-style environment around the game rules: `reset()` starts a new episode
+/// and `step()` plays a single move, returning what the agent needs to learn from.
+/// Episodes end on a crash, when the goal is reached, when a hungry snake starves,
+/// or when it goes on for as many steps as there are cells without eating, which
+/// is a loop.
+///
+/// A whole game with no terminal, the built-in AI playing:
+///
+/// ```
+/// use snake::{ai, SnakeEnv};
+///
+/// let mut env = SnakeEnv::seeded(20, 10, 42);
+/// let mut score = 0;
+/// while let (false, Some(apple)) = (env.is_done(), env.apple()) {
+///     let dir = ai::next_direction(env.snake(), apple, env.board());
+///     let (_, reward, _) = env.step(dir);
+///     if reward > 0.0 {
+///         score += 1;
+///     }
+/// }
+/// assert!(score > 0);
+/// assert_eq!(env.snake().body().len(), 4 + score);
+/// ```
+///
+/// A bot of your own only has to pick a direction every step. This one goes
+/// wherever leaves the most room, and towards the apple when that's a tie:
+///
+/// ```
+/// use snake::{analysis, Coords, Direction::{self, *}, SnakeEnv};
+///
+/// fn pick(env: &SnakeEnv, apple: Coords) -> Direction {
+///     let (snake, board) = (env.snake(), env.board());
+///     let distance = |dir: Direction| {
+///         let pos = dir.step_from(snake.head());
+///         (pos.0 as i32 - apple.0 as i32).abs() + (pos.1 as i32 - apple.1 as i32).abs()
+///     };
+///     [Up, Down, Left, Right].iter().copied()
+///         .max_by_key(|dir| (analysis::free_space_after(snake, *dir, board), -distance(*dir)))
+///         .unwrap()
+/// }
+///
+/// let mut env = SnakeEnv::seeded(20, 10, 7);
+/// let mut steps = 0;
+/// while let (false, Some(apple)) = (env.is_done(), env.apple()) {
+///     env.step(pick(&env, apple));
+///     steps += 1;
+/// }
+/// assert!(steps > 0);
+/// ```
+pub struct SnakeEnv {
+    board: Board,
+    snake: Snake,
+    apple: Option<Coords>,
+    rng: StdRng,
+    goal: Goal,
+    hunger: Option<Hunger>,
+    growth: Growth,
+    eaten: u64, // The score, as every apple is worth a point
+    steps_since_apple: usize,
+    done: bool,
+}
+
+// Everything an episode changes as it goes, to go back to later: rewinding,
+// trying moves ahead for a bot, or rolling back a wrong guess. The board and
+// the settings stay out of it, they don't change while playing.
+#[derive(Clone)]
+pub struct Snapshot {
+    snake: Snake,
+    apple: Option<Coords>,
+    rng: StdRng,
+    hunger: Option<Hunger>,
+    eaten: u64,
+    steps_since_apple: usize,
+    done: bool,
+}
+
+// The whole board, one byte per cell, row by row
+#[derive(Clone, PartialEq)]
+pub struct Observation {
+    pub width: TermInt,
+    pub height: TermInt,
+    pub cells: Vec<u8>,
+}
+
+impl SnakeEnv {
+    pub fn new(width: TermInt, height: TermInt) -> Self {
+        Self::with_board(Board::new(Shape::Rectangle, width, height), StdRng::from_entropy())
+    }
+
+    // Same seed, same apples, for reproducible runs
+    pub fn seeded(width: TermInt, height: TermInt, seed: u64) -> Self {
+        Self::with_board(Board::new(Shape::Rectangle, width, height), StdRng::seed_from_u64(seed))
+    }
+
+    pub fn with_board(board: Board, rng: StdRng) -> Self {
+        let mut env = SnakeEnv {
+            board, snake: Snake::new((0, 0), 1, Right), apple: None, rng, goal: Goal::NoApplesLeft, hunger: None, growth: Growth::CLASSIC, eaten: 0, steps_since_apple: 0,
+            done: true
+        };
+        env.reset();
+        env
+    }
+
+    pub fn reset(&mut self) -> Observation {
+        let spawn = self.board.spawn_point(INITIAL_SNAKE_LENGTH as TermInt).expect("Error spawning: the board is too small");
+        self.snake = Snake::new(spawn, INITIAL_SNAKE_LENGTH, Right);
+        self.steps_since_apple = 0;
+        self.eaten = 0;
+        if let Some(hunger) = self.hunger.as_mut() {
+            hunger.feed();
+        }
+        self.done = false;
+        self.spawn_apple();
+        self.observation()
+    }
+
+    pub fn step(&mut self, action: Action) -> (Observation, f64, bool) {
+        if self.done {
+            return (self.observation(), 0.0, true);
+        }
+
+        self.snake.set_direction(action);
+        let reward = match self.snake.move_step(&self.board) {
+            Crashed => {
+                self.done = true;
+                REWARD_CRASH
+            },
+            Moved { new_head, old_head: _, old_tail: _ } if Some(new_head) == self.apple => {
+                self.eaten += 1;
+                self.snake.grow(self.growth.segments(self.eaten));
+                self.steps_since_apple = 0;
+                if let Some(hunger) = self.hunger.as_mut() {
+                    hunger.feed();
+                }
+                self.spawn_apple();
+                self.done = self.goal.is_reached(&self.snake, self.apple, self.eaten);
+                REWARD_APPLE
+            },
+            Moved { .. } => {
+                self.steps_since_apple += 1;
+                let hungry = self.hunger.as_mut().is_some_and(Hunger::tick);
+                if hungry && self.snake.shrink().is_none() {
+                    self.done = true;
+                    REWARD_CRASH
+                } else {
+                    self.done = self.steps_since_apple >= self.board.positions().len();
+                    REWARD_STEP
+                }
+            },
+        };
+
+        (self.observation(), reward, self.done)
+    }
+
+    // Filling the board unless told otherwise, takes effect from the next apple
+    pub fn set_goal(&mut self, goal: Goal) {
+        self.goal = goal;
+    }
+
+    // Losing a segment every so many steps without eating, or never with None
+    pub fn set_hunger(&mut self, hunger: Option<Hunger>) {
+        self.hunger = hunger;
+    }
+
+    // One segment for every apple unless told otherwise
+    pub fn set_growth(&mut self, growth: Growth) {
+        self.growth = growth;
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            snake: self.snake.clone(), apple: self.apple, rng: self.rng.clone(), hunger: self.hunger,
+            eaten: self.eaten, steps_since_apple: self.steps_since_apple, done: self.done,
+        }
+    }
+
+    // Only meant for snapshots of this same env, the board isn't checked
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.snake = snapshot.snake.clone();
+        self.apple = snapshot.apple;
+        self.rng = snapshot.rng.clone();
+        self.hunger = snapshot.hunger;
+        self.eaten = snapshot.eaten;
+        self.steps_since_apple = snapshot.steps_since_apple;
+        self.done = snapshot.done;
+    }
+
+    pub fn snake(&self) -> &Snake {
+        &self.snake
+    }
+
+    pub fn apple(&self) -> Option<Coords> {
+        self.apple
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub fn score(&self) -> u64 {
+        self.eaten
+    }
+
+    pub fn observation(&self) -> Observation {
+        let (width, height) = self.board.size();
+        let mut cells = vec![CELL_WALL; width as usize * height as usize];
+        let index = |pos: Coords| width as usize * pos.1 as usize + pos.0 as usize;
+
+        for pos in self.board.positions() {
+            cells[index(*pos)] = CELL_EMPTY;
+        }
+        for pos in self.snake.body() {
+            cells[index(*pos)] = CELL_BODY;
+        }
+        if let Some(apple) = self.apple {
+            cells[index(apple)] = CELL_APPLE;
+        }
+        cells[index(self.snake.head())] = CELL_HEAD;
+
+        Observation { width, height, cells }
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn spawn_apple(&mut self) {
+        let body = self.snake.body();
+        let choices: Vec<&Coords> = self.board.positions().iter().filter(|pos| !body.contains(pos)).collect();
+        self.apple = choices.choose(&mut self.rng).copied().copied();
+    }
+}
+
+impl Observation {
+    pub fn at(&self, pos: Coords) -> u8 {
+        if pos.0 >= self.width || pos.1 >= self.height {
+            return CELL_WALL;
+        }
+        self.cells[self.width as usize * pos.1 as usize + pos.0 as usize]
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt::Display;
+use std::io::{stdout, Write};
+
+use crate::json::write_string;
+use crate::Coords;
+
+// Newline-delimited JSON on stdout, one object per game event, for whatever
+// program is on the other end of the pipe. Every object has an "event" key,
+// positions are [x, y] arrays.
+pub struct EventStream {
+    closed: bool,
+}
+
+pub struct JsonEvent {
+    buf: String,
+}
+
+impl EventStream {
+    pub fn new() -> Self {
+        EventStream { closed: false }
+    }
+
+    pub fn emit(&mut self, event: JsonEvent) {
+        if self.closed {
+            return;
+        }
+
+        let mut out = stdout().lock();
+        let res = writeln!(out, "{}", event.to_json()).and_then(|_| out.flush());
+
+        // Whoever was reading went away, no reason to stop playing though
+        if res.is_err() {
+            self.closed = true;
+        }
+    }
+}
+
+impl JsonEvent {
+    pub fn new(name: &str) -> Self {
+        let mut event = JsonEvent { buf: String::from("{") };
+        event.push_key("event");
+        // Writing to a String never fails
+        write_string(&mut event.buf, name).unwrap();
+        event
+    }
+
+    pub fn num(mut self, key: &str, value: impl Display) -> Self {
+        self.push_key(key);
+        self.buf.push_str(&value.to_string());
+        self
+    }
+
+    pub fn str(mut self, key: &str, value: &str) -> Self {
+        self.push_key(key);
+        write_string(&mut self.buf, value).unwrap();
+        self
+    }
+
+    pub fn pos(mut self, key: &str, pos: Coords) -> Self {
+        self.push_key(key);
+        self.buf.push_str(&format!("[{},{}]", pos.0, pos.1));
+        self
+    }
+
+    pub fn positions(mut self, key: &str, positions: &[Coords]) -> Self {
+        self.push_key(key);
+        let items: Vec<String> = positions.iter().map(|pos| format!("[{},{}]", pos.0, pos.1)).collect();
+        self.buf.push_str(&format!("[{}]", items.join(",")));
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        format!("{}}}", self.buf)
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn push_key(&mut self, key: &str) {
+        if self.buf.len() > 1 {
+            self.buf.push(',');
+        }
+        write_string(&mut self.buf, key).unwrap();
+        self.buf.push(':');
+    }
+}
@@ -1,220 +1,2360 @@
-use std::{process::exit, thread::sleep, time::Duration, cmp::max};
+use std::{collections::VecDeque, env, fs, io::{stdin, stdout}, path::PathBuf, process::exit, thread::sleep, time::{Duration, Instant}};
 
-use crate::{Coords, TermInt};
-use crate::term::TermManager;
+use crate::chaos::{Modifier, Ruleset};
+use crate::{analysis, cli, pack, paths, recording, sim, storage, tutorial, Coords, TermInt};
+use crate::board::{Board, Shape};
+use crate::config::{Config, Mirror, Mode};
+use crate::drift::{self, Velocity};
+use crate::hunger::Hunger;
+use crate::control::{Control, ControlSource};
+use crate::controls;
+use crate::events::{EventStream, JsonEvent};
+use crate::goal::{self, Goal};
+use crate::hamilton::Cycle;
+use crate::heatmap::Heatmap;
+use crate::input::Action;
+use crate::lan::{self, Lan};
+use crate::menu::{self, Pick};
+use crate::metrics::Metrics;
+use crate::pip::PictureInPicture;
+use crate::achievements::{self, Achievements};
+use crate::recent::Recent;
+use crate::replay::{Replay, Step};
+use crate::run_code::RunCode;
+use crate::scores::Scores;
+use crate::sim::Strategy;
+use crate::term::{self, Anchor, Cell, Fog, KeyHold, Layout, Region, TermManager};
+use crate::theme::BodyPaint;
+use crate::speed::{Acceleration, Speed};
+use crate::summary::Summary;
+use crate::viewer::{self, Exit};
 use crate::snake::{Snake, Direction::{*, self}, MoveResult::{*, self}};
 
-use crossterm::event::{KeyEvent, KeyModifiers, KeyCode};
-use rand::seq::SliceRandom;
+use crossterm::event::{Event, KeyEvent, KeyCode};
+use crossterm::style::ContentStyle;
+use crossterm::tty::IsTty;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 
 const TICK_INTERVAL_MS: u64 = 5;
-const TICKS_UNTIL_UPDATE: u64 = 10;
+// Tuned by hand on a font with cells twice as high as they are wide
+const DEFAULT_VERTICAL_FACTOR: f64 = 1.35;
+const DEFAULT_CELL_ASPECT: f64 = 2.0;
 const INITIAL_SNAKE_LENGTH: i16 = 6;
+const SCREENSAVER_RESTART_MS: u64 = 2000;
+const RESTART_COUNTDOWN_SECS: u64 = 10;
+const SIDE_PANEL_WIDTH: TermInt = 22;
+const LAN_PANEL_REFRESH_MS: u64 = 500;
+const DEBUG_PANEL_ROWS: TermInt = 14;
+const EVENT_LOG_LINES: usize = 8;
+const CHAT_ROWS: TermInt = 10; // With the header
+const PIP_ROWS: TermInt = 9; // Twice as many on its board
+const MAGNET_CHANCE: f64 = 0.25;
+const MAGNET_STEPS: u32 = 40;
+const EXTRA_APPLES: usize = 2;
+const SPRINT_FACTOR: f64 = 2.0;
+const MIRROR_PERIOD_SECS: u64 = 15;
+const MIRROR_WARNING_SECS: u64 = 3;
+const TIMED_MODE_SECS: u64 = 90;
+const OBSTACLE_DENSITY: f64 = 0.03;
+const PAUSE_LINES: [&str; 4] = ["Paused", "Press Esc to resume,", "R to restart", "or Q to quit"];
+const MAX_SAVED_REPLAYS: usize = 20;
+const SHRINK_EVERY: u64 = 25; // Points
+const SHRINK_CLEARANCE: i32 = 4; // Cells kept free around the head
+const MENU_ITEMS: [&str; 14] = [
+    "Classic", "Timed", "Obstacles", "Chaos", "Drifting", "Two-player", "Versus", "Co-op", "Levels", "Tutorial", "Run code", "Settings", "High scores", "Quit"
+];
+const START_COUNTDOWN_SECS: u64 = 3;
+const HINT_LOOKAHEAD: u32 = 3; // Moves
+const DRIFT_EVERY: u64 = 3; // Moves
+const ENERGY_BAR_WIDTH: usize = 10;
+const GOAL_BAR_WIDTH: usize = 20;
+const VERSUS_TARGET: u64 = 10; // Points
+const GARBAGE_CLEARANCE: i32 = 4; // Cells kept free around the head
+const RUN_CODE_INPUT: usize = 19; // With the dashes
 
-const SNAKE_BODY_CHAR: char = '█';
-const APPLE_CHAR: char = 'O';
-const DEAD_SNAKE_CHAR: char = 'X';
+enum Outcome {
+    Crashed,
+    Won,
+    OutOfMoves,
+    OutOfTime,
+    Trapped,
+    Starved,
+}
+
+struct Apple {
+    pos: Coords,
+    value: u64,
+    cell: Cell,
+    velocity: Option<Velocity>, // Only for drifting apples
+}
+
+struct Player {
+    snake: Snake,
+    ticks_until_step: f64,
+    dir_change: Option<Direction>,
+    score: u64,
+}
+
+// A magnet lying on the board, and how many more steps the one picked up keeps pulling
+#[derive(Default)]
+struct Magnet {
+    pos: Option<Coords>,
+    steps_left: u32,
+}
 
 pub struct SnakeGame {
     width: TermInt,
     height: TermInt,
     paused: bool,
+    boss_screen: bool,
+    help_shown: bool,
+    speed: Speed,
+    layout: Layout,
+    config: Config,
     term: TermManager,
-    game_positions: Vec<Coords>,
+    board: Board,
+    lan: Option<Lan>,
+    events: Option<EventStream>,
+    control: Option<Control>,
+    magnet: Magnet,
+    extra_apples: Vec<Apple>, // Besides the one the rest of the game goes by
+    replay: Replay,
+    scores: Scores,
+    recent: Recent,
+    achievements: Achievements,
+    seed: u64,
+    run_code: Option<RunCode>, // Entered by the player, for the next round
+    board_size: Option<Coords>, // Taken from a run code instead of the terminal
+    round_rules: u64, // The rules hash as the round started, before any chaos modifiers
+    rng: StdRng, // Everything random in a round comes from here, so it can be told apart by its seed
+    session_started: Instant, // Across rounds, since the last break reminder
+    title_score: String,
+    added_walls: Vec<Coords>, // Put up during this round by --shrink
+    event_log: VecDeque<String>,
+    chaos: Option<(Ruleset, Vec<Modifier>)>, // The settings before this round's modifiers, and the modifiers
+    pip: Option<PictureInPicture>,
+    summary: Option<Summary>,
+    metrics: Option<Metrics>,
+    round_started: Instant,
+    lan_players: Option<Vec<String>>, // Everyone else, as of the last panel update
+    picked_level: Option<usize>, // From the bundled ones in the menu, which then stands in for --puzzle
+    #[cfg(feature = "discord")]
+    presence: Option<crate::discord::Presence>,
 }
 
 impl SnakeGame {
-    pub fn new() -> Self {
-        SnakeGame { width: 0, height: 0, paused: false, term: TermManager::new(), game_positions: vec![] }
+    pub fn new(config: Config) -> Self {
+        // JSON on a terminal would just garble the game, it's meant for pipes
+        let events = if config.json_events && !stdout().is_tty() {Some(EventStream::new())} else {None};
+        let term = TermManager::new(events.is_some(), config.renderer, config.glyphs);
+        let config_profile = config.profile.clone();
+        let summary = config.summary.then(Summary::new);
+        let metrics = config.export_metrics.is_some().then(Metrics::new);
+        #[cfg(feature = "discord")]
+        let config_discord = config.discord.clone();
+        SnakeGame {
+            width: 0, height: 0, paused: false, boss_screen: false, help_shown: false, speed: Speed::new(Acceleration::Linear, DEFAULT_VERTICAL_FACTOR, (1.0, 1.0)),
+            layout: Layout::new((0, 0), 0),
+            config, term, board: Board::new(Shape::Rectangle, 0, 0), lan: None, events,
+            control: None, magnet: Magnet::default(), extra_apples: vec![], replay: Replay::new(&[(0, 0)], (0, 0)),
+            scores: Scores::load(config_profile.as_deref()), recent: Recent::load(config_profile.as_deref()),
+            achievements: Achievements::load(config_profile.as_deref()), seed: 0, run_code: None, board_size: None, round_rules: 0, rng: StdRng::seed_from_u64(0),
+            session_started: Instant::now(), title_score: String::new(), added_walls: vec![], event_log: VecDeque::new(), lan_players: None, picked_level: None, chaos: None, pip: None,
+            summary, metrics, round_started: Instant::now(),
+            #[cfg(feature = "discord")]
+            presence: config_discord.as_deref().map(crate::discord::Presence::start),
+        }
     }
 
     pub fn initialize(&mut self) {
-        self.term.setup();
+        // Done before taking over the terminal so the error can be seen
+        if self.config.lan {
+            match Lan::start(&self.config.player_name) {
+                Ok(lan) => self.lan = Some(lan),
+                Err(e) => {
+                    eprintln!("Error starting LAN mode: {}", e);
+                    exit(1);
+                }
+            }
+        }
 
-        let (w, h) = self.term.get_terminal_size();
-        self.width = w;
-        self.height = h;
+        if let Some(source) = &self.config.control {
+            // The keyboard would be fighting the bot for stdin otherwise
+            if matches!(source, ControlSource::Stdin) && stdin().is_tty() {
+                eprintln!("Error starting control: stdin is a terminal, pipe the commands in instead");
+                exit(1);
+            }
+
+            match Control::start(source) {
+                Ok(control) => self.control = Some(control),
+                Err(e) => {
+                    eprintln!("Error starting control: {}", e);
+                    exit(1);
+                }
+            }
+        }
 
-        for y in 1..self.height - 1 {
-            for x in 1..self.width - 1 {
-                self.game_positions.push((x, y));
+        if let Some(path) = &self.config.export_metrics {
+            if let Err(e) = Metrics::new().save(path) {
+                eprintln!("Error writing {}: {}", path, e);
+                exit(1);
             }
         }
+
+        // Nobody could ever press a key without a terminal
+        if self.term.is_headless() && !self.config.screensaver && self.control.is_none() {
+            eprintln!("There is no terminal to play on, use --screensaver or --control to play without one");
+            exit(1);
+        }
+
+        self.term.setup();
+        self.update_board_size();
     }
 
     pub fn show_intro(&mut self) {
+        if self.term.is_headless() {
+            return;
+        }
+
+        self.pick_profile();
+
+        // Puzzles and modes given on the command line go straight to the game
+        if self.config.mode.is_none() && self.config.puzzle.is_none() {
+            self.main_menu();
+            // Nobody knows the two-player keys yet
+            if !self.mode().needs_two() {
+                return;
+            }
+        }
+
         let lines = &[
-            "Arrow keys or WASD to move",
+            self.controls_help(),
             "Esc to pause",
-            "CTRL+C to quit",
+            "Q or CTRL+C to quit",
             "",
-            "Press any key to begin"
+            "Press any key to begin,",
+            "or C to enter a run code"
         ];
 
-        self.term.show_message(lines);
-
-        if is_ctrl_c(&self.term.read_key_blocking()) {
-            self.clean_exit()
+        loop {
+            self.term.show_message(lines);
+            let key = self.term.read_key_blocking();
+            if is_quit(&key) {
+                self.clean_exit()
+            }
+            if key.code != KeyCode::Char('c') || self.enter_run_code() {
+                break;
+            }
         }
 
         self.term.hide_message();
     }
 
     pub fn play(&mut self) {
+        let code = self.run_code.take();
+        self.seed = code.map_or_else(|| rand::random::<u32>() as u64, |code| code.seed as u64);
+        self.rng = StdRng::seed_from_u64(self.seed);
+        let board_size = code.map(|code| (code.width, code.height));
+        if board_size != self.board_size {
+            self.board_size = board_size;
+            self.update_board_size();
+        }
+        self.restore_rules();
+        self.round_rules = self.config.rules_hash();
+        self.round_started = Instant::now();
+
+        match self.mode() {
+            Mode::TwoPlayer => return self.play_two_player(),
+            Mode::Versus => return self.play_versus(),
+            Mode::Obstacles => self.place_obstacles(),
+            Mode::Chaos => self.roll_modifiers(),
+            // Last round's walls come down
+            _ if self.shrinks() => self.update_board_size(),
+            _ => {},
+        }
+        self.added_walls.clear();
+        if let Some(metrics) = &mut self.metrics {
+            metrics.clear();
+        }
+
         self.term.clear();
-        self.term.draw_borders(Some((self.width, self.height)));
+        self.draw_board();
         self.term.hide_message();
+        self.draw_lan_panel();
+        self.event_log.clear();
+        self.draw_event_log();
+        self.announce_modifiers();
+        self.announce_coop();
+        self.title_score = "Score: 0".into();
+        self.update_title("Playing");
 
-        let center = (self.width / 2, self.height / 2);
-        let (max_x, max_y) = (self.width - 2, self.height - 2);
-
-        let mut snake = Snake::new(center, INITIAL_SNAKE_LENGTH, Right);
-        let mut apple = self.spawn_apple(&snake).unwrap();
+        self.magnet = Magnet::default();
+        let (spawn, direction, initial_length) = self.spawn_params();
+        let mut snake = Snake::new(spawn, initial_length, direction);
+        self.emit(JsonEvent::new("start")
+            .num("width", self.width).num("height", self.height)
+            .positions("snake", snake.body()).str("direction", direction.name()));
+        let mut apple = self.spawn_apple(&snake, 0).unwrap();
+        self.extra_apples.clear();
+        if self.config.extra_apples {
+            for _ in 0..EXTRA_APPLES {
+                if let Some(extra) = self.spawn_extra_apple(&snake, apple.pos) {
+                    self.extra_apples.push(extra);
+                }
+            }
+        }
+        self.replay = Replay::new(snake.body(), apple.pos);
+        let mut score: u64 = 0;
+        let mut eaten: u64 = 0;
         let mut dir_change: Option<Direction> = None;
-        let mut ticks_until_step = TICKS_UNTIL_UPDATE;
+        let mut next_wall_at = SHRINK_EVERY;
+        // Only told once, and only when there's a best to beat that the game would count
+        let mut best_to_beat = if self.counts_for_scores() {self.best_score()} else {None};
+        let mut best_shown = best_to_beat;
+        let mut cycle = if self.config.strategy == Some(Strategy::Hamiltonian) {Cycle::new(&self.board)} else {None};
+        let mut ticks_until_step = self.speed.initial_ticks();
+        let mut last_panel_draw = Instant::now();
+        let mut step_requested = false;
+        let mut sprint = KeyHold::default();
+        let started = Instant::now();
+        let mut mirror_status = None;
+        let mut was_mirrored = false;
+        let mut ghost: Option<Coords> = None;
+        let mut drift_arrow: Option<(Coords, Cell)> = None;
+        let mut pending_turn: Option<Direction> = None;
+        let mut coop_turns: [Option<Direction>; 2] = [None; 2];
+        let mut turn_preview: Option<(Coords, Cell)> = None;
+        let mut hints: Vec<Coords> = vec![];
+        let time_limit = (self.mode() == Mode::Timed && self.config.puzzle.is_none()).then(|| Duration::from_secs(TIMED_MODE_SECS));
+        let mut played = Duration::ZERO;
+        let mut last_tick = Instant::now();
+        let mut shown_secs = None;
+        let mut hunger = self.config.hunger.map(Hunger::new);
+
+        let mut heatmap = Heatmap::new(self.width, self.height);
+        snake.body().iter().for_each(|pos| heatmap.record(*pos));
 
+        self.update_fog(&snake);
         self.print_snake(&snake);
+        self.update_hints(&snake, apple.pos, &mut hints);
+        self.draw_puzzle_status(&snake, eaten);
+        self.draw_target_length(&snake);
+        self.draw_energy(hunger.as_ref());
+        self.draw_best(best_shown);
+        self.draw_apples_goal(eaten);
+        self.draw_coop_status(score, coop_turns);
+        self.draw_debug_panel(&snake, &apple, score, eaten);
 
         loop {
             sleep(Duration::from_millis(TICK_INTERVAL_MS));
+            let since_last_tick = last_tick.elapsed();
+            last_tick = Instant::now();
+
+            self.term.update_overlays();
+            let (mirrored, status) = self.mirror_state(started.elapsed().as_secs());
+            if status != mirror_status {
+                self.draw_mirror_status(status.as_deref());
+                self.draw_best(best_shown);
+                self.draw_apples_goal(eaten);
+                self.draw_coop_status(score, coop_turns);
+                mirror_status = status;
+            }
+            if mirrored != was_mirrored {
+                self.log_event(if mirrored {"Controls mirrored"} else {"Controls back to normal"});
+                was_mirrored = mirrored;
+            }
 
             for key_ev in self.term.read_key_events_queue() {
-                match &key_ev {
-                    ev if is_ctrl_c(ev) => self.clean_exit(),
-                    KeyEvent { code, modifiers: _ } => match code {
-                        KeyCode::Char('w') | KeyCode::Up => dir_change = Some(Up),
-                        KeyCode::Char('a') | KeyCode::Left => dir_change = Some(Left),
-                        KeyCode::Char('s') | KeyCode::Down => dir_change = Some(Down),
-                        KeyCode::Char('d') | KeyCode::Right => dir_change = Some(Right),
-                        KeyCode::Esc => self.toggle_pause(),
-                        _ => {}
-                    }
+                if self.config.sprint && self.config.keys.is_sprint(&key_ev) {
+                    sprint.press();
+                }
+
+                match Action::of(&key_ev) {
+                    _ if self.config.screensaver => self.clean_exit(),
+                    Some(Action::Quit) => {
+                        self.confirm_quit();
+                        // The clock doesn't run while asking
+                        last_tick = Instant::now();
+                    },
+                    Some(Action::BossScreen) => self.toggle_boss_screen(),
+                    _ if self.boss_screen => {},
+                    Some(Action::Suspend) => {
+                        self.suspend();
+                        last_tick = Instant::now();
+                    },
+                    Some(Action::Redraw) => self.term.redraw_all(),
+                    Some(Action::Pause) => self.toggle_pause(),
+                    Some(Action::Help) => self.toggle_help(),
+                    Some(Action::Chat) if self.lan.is_some() => {
+                        self.chat();
+                        last_tick = Instant::now();
+                    },
+                    Some(Action::Step) if self.config.step_mode => step_requested = true,
+                    Some(Action::Confirm) if self.config.confirm_turns => dir_change = pending_turn.take(),
+                    Some(Action::Hints) => {
+                        self.config.hints = !self.config.hints;
+                        self.update_hints(&snake, apple.pos, &mut hints);
+                    },
+                    Some(Action::Restart) => {
+                        if self.confirm_restart() {
+                            return;
+                        }
+                        last_tick = Instant::now();
+                    },
+                    _ if self.mode() == Mode::Coop => if let Some((i, dir)) = controls::coop_direction(key_ev.code) {
+                        dir_change = Some(steer(dir, mirrored));
+                        coop_turns[i] = Some(dir);
+                        self.draw_coop_status(score, coop_turns);
+                    },
+                    _ => {
+                        if let Some(dir) = self.config.controls.direction(self.config.keys, key_ev.code, snake.get_direction()) {
+                            if self.config.confirm_turns {
+                                pending_turn = Some(steer(dir, mirrored));
+                            } else {
+                                dir_change = Some(steer(dir, mirrored));
+                            }
+                        }
+                    },
+                }
+            }
+
+            if self.config.confirm_turns {
+                self.update_turn_preview(&snake, pending_turn, &mut turn_preview);
+            }
+
+            if let Some(lan) = &self.lan {
+                lan.set_score(score);
+                if last_panel_draw.elapsed() >= Duration::from_millis(LAN_PANEL_REFRESH_MS) {
+                    self.draw_lan_panel();
+                    last_panel_draw = Instant::now();
                 }
             }
 
             if self.paused { continue; }
+            self.update_pip();
 
-            // Not paused, count down til the next game update
-            ticks_until_step -= 1;
-            if ticks_until_step == 0 {
-                let score = snake.body().len() as u64 - INITIAL_SNAKE_LENGTH as u64;
-                ticks_until_step = if let Some(x) = TICKS_UNTIL_UPDATE.checked_sub(score / 7) {
-                    max(x, 1)
-                } else {
-                    1
-                }; // Speed up with higher scores
+            if self.idle_due() {
+                self.idle_pause();
+                continue;
+            }
+
+            if self.break_due() {
+                self.remind_break();
+                last_tick = Instant::now();
+                continue;
+            }
+
+            // The clock stops while paused
+            if let Some(limit) = time_limit {
+                played += since_last_tick;
+                let secs_left = limit.saturating_sub(played).as_millis().div_ceil(1000) as u64;
+                if secs_left == 0 {
+                    self.game_over(&snake, Outcome::OutOfTime, score, &heatmap);
+                    break;
+                }
+                if shown_secs != Some(secs_left) {
+                    self.draw_time_left(secs_left);
+                    shown_secs = Some(secs_left);
+                }
+            }
 
-                if let Some(dir) = dir_change {
-                    dir_change = None;
-                    snake.set_direction(dir);
+            if self.config.ghost {
+                self.update_ghost(&snake, dir_change, &mut ghost);
+            }
+
+            // Not paused, count down til the next game update, or wait for it in step mode
+            let step_now = if self.config.step_mode {
+                std::mem::take(&mut step_requested)
+            } else {
+                let speed = if self.config.double_speed {2.0} else {1.0};
+                ticks_until_step -= speed * if sprint.is_held() {SPRINT_FACTOR} else {1.0};
+                ticks_until_step <= 0.0
+            };
+
+            if step_now {
+                // Taken once per step, so chat votes have the whole step to come in
+                if let Some(dir) = self.control.as_ref().and_then(Control::next_direction) {
+                    dir_change = Some(dir);
+                }
+
+                if self.config.screensaver {
+                    dir_change = Some(sim::bot_direction(cycle.as_ref(), &snake, apple.pos, &self.board));
                 }
 
-                // Make the snake move a bit slower when going vertically, since terminal
-                // characters have a higher height than width
-                if matches!(snake.get_direction(), Up | Down) {
-                    ticks_until_step = (ticks_until_step as f64 * 1.35).ceil() as u64;
+                if let Some(dir) = dir_change.take() {
+                    if self.refused_by_assist(&snake, dir) {
+                        self.term.show_overlay(dir.step_from(snake.head()), "!");
+                    } else {
+                        snake.set_direction(dir);
+                    }
                 }
 
-                let move_res = snake.move_step(max_x, max_y);
+                // Fractional ticks carry over, so the speed ratio holds even at top speed
+                ticks_until_step += self.speed.step_ticks(eaten, snake.get_direction());
+
+                let move_res = snake.move_step(&self.board);
 
                 match &move_res {
                     Crashed => {
-                        self.game_over(&snake, score, false);
+                        self.emit(JsonEvent::new("crash").pos("head", snake.head()).str("direction", snake.get_direction().name()));
+                        self.game_over(&snake, Outcome::Crashed, score, &heatmap);
                         break;
                     },
-                    Moved { new_head, old_head: _, old_tail: _ } => {
-                        if *new_head == apple {
-                            let opt = self.spawn_apple(&snake);
-                            if opt.is_none() { // No more apples to spawn
-                                self.game_over(&snake, score, true);
+                    Moved { new_head, old_head: _, old_tail } => {
+                        let time_ms = started.elapsed().as_millis() as u32;
+                        heatmap.record(*new_head);
+                        self.emit(JsonEvent::new("step")
+                            .num("move", snake.moves()).pos("head", *new_head)
+                            .str("direction", snake.get_direction().name()).num("length", snake.body().len()));
+
+                        let extra = self.extra_apples.iter().position(|extra| extra.pos == *new_head);
+                        let ate = *new_head == apple.pos || extra.is_some();
+                        let mut pulled = false;
+                        if ate {
+                            let eaten_apple = extra.map_or(&apple, |i| &self.extra_apples[i]);
+                            let (pos, value) = (eaten_apple.pos, eaten_apple.value);
+                            score += value;
+                            self.title_score = format!("Score: {}", score);
+                            self.update_title("Playing");
+                            eaten += 1;
+                            self.emit(JsonEvent::new("eat").pos("pos", pos).num("value", value).num("score", score));
+                            self.show_popup(pos, &format!("+{}", value));
+                            self.log_event(&format!("Ate an apple +{}", value));
+                            if self.speed.ticks_per_step(eaten) < self.speed.ticks_per_step(eaten - 1) {
+                                self.log_event("Speed up!");
+                                self.term.show_toast("Speed up!");
+                            }
+                            if best_to_beat.is_some_and(|best| score > best) {
+                                best_to_beat = None;
+                                self.term.show_toast("New best score!");
+                            }
+                            if best_shown.is_some_and(|best| score > best) {
+                                best_shown = Some(score);
+                                self.draw_best(best_shown);
+                            }
+                            let next = match extra {
+                                Some(i) => {
+                                    self.extra_apples.remove(i);
+                                    if let Some(more) = self.spawn_extra_apple(&snake, apple.pos) {
+                                        self.extra_apples.push(more);
+                                    }
+                                    None
+                                },
+                                None => Some(self.spawn_apple(&snake, eaten as usize)),
+                            };
+                            snake.grow(self.config.growth.segments(score));
+                            // Eating an extra one leaves the main apple where it is
+                            let next_pos = next.as_ref().map_or(Some(apple.pos), |opt| opt.as_ref().map(|next| next.pos));
+                            if self.goal().is_reached(&snake, next_pos, eaten) {
+                                self.replay.record(Step { head: *new_head, grew: old_tail.is_none(), shrank: false, apple: apple.pos, time_ms });
+                                self.record_metrics(&snake, time_ms, score, eaten, true, sprint.is_held());
+                                self.draw_apples_goal(eaten);
+                                self.game_over(&snake, Outcome::Won, score, &heatmap);
                                 break;
                             }
-                            apple = opt.unwrap();
-                            snake.grow();
+
+                            if let Some(opt) = next {
+                                apple = opt.unwrap();
+
+                                // Far apples can be worth several walls at once
+                                while self.shrinks() && score >= next_wall_at {
+                                    next_wall_at += SHRINK_EVERY;
+                                    self.add_wall_block(&snake, &apple);
+                                    // It doesn't go around the new wall
+                                    cycle = None;
+                                }
+
+                                if self.config.magnets && self.magnet.pos.is_none() && self.rng.gen::<f64>() < MAGNET_CHANCE {
+                                    self.spawn_magnet(&snake, &apple);
+                                }
+                            }
+                        }
+
+                        let hungry = match hunger.as_mut() {
+                            Some(hunger) if ate => {
+                                hunger.feed();
+                                false
+                            },
+                            Some(hunger) => hunger.tick(),
+                            None => false,
+                        };
+
+                        if self.magnet.pos == Some(*new_head) {
+                            self.magnet = Magnet { pos: None, steps_left: MAGNET_STEPS };
+                            self.emit(JsonEvent::new("magnet").pos("pos", *new_head).num("steps", MAGNET_STEPS));
+                            self.log_event(&format!("Magnet on for {} steps", MAGNET_STEPS));
+                        } else if self.magnet.steps_left > 0 {
+                            self.magnet.steps_left -= 1;
+                            if self.magnet.steps_left == 0 {
+                                self.log_event("The magnet wore off");
+                            }
+                            // A new apple sits still for its first step, so it's worth what its spot says
+                            if !ate {
+                                pulled = self.pull_apple(&snake, &mut apple);
+                            }
                         }
+
+                        // Being pulled takes the place of drifting for that step
+                        if apple.velocity.is_some() && !ate && !pulled && snake.moves().is_multiple_of(DRIFT_EVERY) {
+                            self.drift_apple(&snake, &mut apple);
+                        }
+                        self.update_drift_arrow(&snake, &apple, &mut drift_arrow);
+
+                        // Down to the head, there's nothing left to lose
+                        let shrank = hungry && snake.body().len() > 1;
+                        self.replay.record(Step { head: *new_head, grew: old_tail.is_none(), shrank, apple: apple.pos, time_ms });
+                        self.record_metrics(&snake, time_ms, score, eaten, ate, sprint.is_held());
+                        self.update_fog(&snake);
                         self.print_snake_update(&snake, &move_res);
+                        if hungry {
+                            match snake.shrink() {
+                                Some(tail) => self.drop_tail(&snake, tail),
+                                None => {
+                                    self.game_over(&snake, Outcome::Starved, score, &heatmap);
+                                    break;
+                                },
+                            }
+                        }
+                        self.update_hints(&snake, apple.pos, &mut hints);
+                        self.draw_puzzle_status(&snake, eaten);
+                        self.draw_target_length(&snake);
+                        self.draw_apples_goal(eaten);
+                        self.draw_coop_status(score, coop_turns);
+                        self.draw_energy(hunger.as_ref());
+                        self.draw_debug_panel(&snake, &apple, score, eaten);
+
+                        if self.move_limit().is_some_and(|limit| snake.moves() >= limit) {
+                            self.game_over(&snake, Outcome::OutOfMoves, score, &heatmap);
+                            break;
+                        }
+
+                        // Obstacles make it easy to box yourself in, no point in waiting for the crash
+                        if self.mode() == Mode::Obstacles && snake.is_trapped(&self.board, apple.pos) {
+                            self.game_over(&snake, Outcome::Trapped, score, &heatmap);
+                            break;
+                        }
                     },
                 } // match
             } // Game step
         } // Game loop
-
-        // Quit if the user CTRL+C's after the game
-        if is_ctrl_c(&self.term.read_key_blocking()) {
-            self.clean_exit()
-        }
     }
 
     ///////////////////////////////////////////////////////////////////////////
 
-    fn clean_exit(&mut self) {
+    fn clean_exit(&mut self) -> ! {
         self.term.restore();
+        // Past the alternate screen, where it stays in sight
+        if let Some(summary) = &self.summary {
+            println!("{}", summary.to_json());
+        }
         exit(0);
     }
 
-    fn game_over(&mut self, snake: &Snake, score: u64, win: bool) {
-        let s = if win {"You won!"} else {"Game over!"};
+    fn update_speed(&mut self) {
+        // Unless told otherwise, adjust the hand-tuned factor to the actual font's aspect ratio
+        let vertical_factor = self.config.vertical_speed_factor.unwrap_or_else(|| {
+            self.term.cell_aspect_ratio()
+                .map_or(DEFAULT_VERTICAL_FACTOR, |aspect| DEFAULT_VERTICAL_FACTOR * aspect / DEFAULT_CELL_ASPECT)
+        });
+        let scale = self.config.board_scaling.scale((self.width, self.height));
+        self.speed = Speed::new(self.config.acceleration, vertical_factor, scale);
+    }
 
-        if !win {
-            for pos in snake.body() {
-                self.term.print_at(*pos, DEAD_SNAKE_CHAR);
+    fn mode(&self) -> Mode {
+        self.config.mode.unwrap_or(Mode::Classic)
+    }
+
+    fn controls_help(&self) -> &'static str {
+        match self.mode() {
+            Mode::TwoPlayer | Mode::Versus => "WASD for player 1, arrow keys for player 2",
+            Mode::Coop => "A/D for player 1, up/down arrows for player 2",
+            _ => self.config.controls.help(self.config.keys),
+        }
+    }
+
+    // Only asked when there's someone to pick and nobody was given on the command line
+    fn pick_profile(&mut self) {
+        let profiles = paths::profiles();
+        if self.config.profile.is_some() || profiles.is_empty() {
+            return;
+        }
+
+        let mut choices = profiles.clone();
+        choices.push("Guest".into());
+        let mut selected = 0;
+        loop {
+            match menu::choose(&mut self.term, "Who's playing?", &choices, selected, &["Arrows to pick, Enter to go"]) {
+                Pick::Item(i, KeyCode::Enter) => selected = i,
+                Pick::Quit => self.clean_exit(),
+                _ => continue,
+            }
+
+            let name = match profiles.get(selected) {
+                Some(name) => name,
+                None => return,
+            };
+            // A mistake in their config file is theirs to fix, the others can still play
+            match cli::parse_for_profile(name) {
+                Ok(config) => {
+                    self.config = config;
+                    self.scores = Scores::load(Some(name));
+                    self.recent = Recent::load(Some(name));
+                    self.achievements = Achievements::load(Some(name));
+                    self.update_board_size();
+                    return;
+                },
+                Err(e) => {
+                    let title = format!("Can't play as {}", name);
+                    let lines = [title.as_str(), "", e.as_str(), "", "Press any key to pick again"];
+                    if is_quit(&self.term.ask_at(Anchor::Center, &lines)) {
+                        self.clean_exit();
+                    }
+                },
+            }
+        }
+    }
+
+    // Returns once a game mode has been picked, with the settings possibly changed on the way
+    fn main_menu(&mut self) {
+        self.term.set_title("Snake");
+        // The settings shown are the player's own, not what the last chaos round made of them
+        self.restore_rules();
+        let items: Vec<String> = MENU_ITEMS.iter().map(|item| item.to_string()).collect();
+        let levels_item = Mode::ALL.len();
+        // What was played last, or the first time round, whatever was picked last time the game ran
+        let mut selected = match (self.picked_level, self.config.mode, self.recent.get("mode")) {
+            (Some(_), _, _) | (None, None, Some("levels")) => levels_item,
+            (None, Some(mode), _) => Mode::ALL.iter().position(|m| *m == mode).unwrap(),
+            (None, None, last) => last.and_then(Mode::by_name).and_then(|mode| Mode::ALL.iter().position(|m| *m == mode)).unwrap_or(0),
+        };
+
+        loop {
+            let footer = [self.config.controls.help(self.config.keys), "Esc to pause, Q to quit"];
+            match menu::choose(&mut self.term, "Snake", &items, selected, &footer) {
+                Pick::Item(i, KeyCode::Enter) => selected = i,
+                Pick::Quit => self.clean_exit(),
+                _ => continue,
+            }
+
+            // The modes come first, then everything else
+            let back = match selected.checked_sub(Mode::ALL.len()) {
+                None => {
+                    self.config.mode = Some(Mode::ALL[selected]);
+                    // A level picked last time doesn't come along
+                    if self.picked_level.take().is_some() {
+                        self.config.puzzle = None;
+                    }
+                    self.remember("mode", Mode::ALL[selected].name());
+                    break;
+                },
+                Some(0) => {
+                    let last = self.recent.get("level").and_then(|name| pack::names().iter().position(|level| *level == name));
+                    match menu::pick_level(&mut self.term, &self.scores, self.picked_level.or(last).unwrap_or(0)) {
+                        Pick::Item(i, _) => {
+                            self.picked_level = Some(i);
+                            self.config.puzzle = pack::load(pack::LEVELS[i].0);
+                            self.config.mode = Some(Mode::Classic);
+                            self.remember("mode", "levels");
+                            self.remember("level", pack::LEVELS[i].0);
+                            break;
+                        },
+                        other => other,
+                    }
+                },
+                Some(1) => tutorial::run(&mut self.term, &self.config),
+                Some(2) => {
+                    if self.enter_run_code() {
+                        break;
+                    }
+                    Pick::Back
+                },
+                Some(3) => {
+                    let pick = menu::settings(&mut self.term, &mut self.config, &mut self.recent);
+                    // Not worth bothering anyone about, it only saves them some picking next time
+                    self.recent.save().ok();
+                    pick
+                },
+                Some(4) => menu::high_scores(&mut self.term, &self.scores, &self.achievements),
+                _ => self.clean_exit(),
+            };
+
+            if let Pick::Quit = back {
+                self.clean_exit();
             }
         }
 
-        self.term.show_message(&[
-            s,
-            &*format!("Score: {}", score),
+        self.term.hide_message();
+        self.update_board_size();
+    }
+
+    fn remember(&mut self, key: &str, value: &str) {
+        self.recent.set(key, value);
+        self.recent.save().ok();
+    }
+
+    // Back to the player's settings, then one or two things thrown in on top
+    fn roll_modifiers(&mut self) {
+        self.restore_rules();
+        let modifiers = Modifier::roll(&mut self.rng);
+        let ruleset = Ruleset::of(&self.config);
+        for modifier in &modifiers {
+            modifier.apply(&mut self.config);
+        }
+        self.chaos = Some((ruleset, modifiers));
+    }
+
+    fn restore_rules(&mut self) {
+        if let Some((ruleset, _)) = self.chaos.take() {
+            ruleset.restore(&mut self.config);
+        }
+        // Whatever fog is left from last round would stay up otherwise
+        if self.config.fog.is_none() {
+            self.term.set_fog(None);
+        }
+    }
+
+    // Over the board with a short countdown, so the player can take it in
+    fn announce_modifiers(&mut self) {
+        let names: Vec<&str> = match &self.chaos {
+            Some((_, modifiers)) if !self.term.is_headless() => modifiers.iter().map(Modifier::name).collect(),
+            _ => return,
+        };
+
+        let mut lines = vec!["This round:", ""];
+        lines.extend(names.iter());
+        self.start_countdown(&lines);
+    }
+
+    // Who steers which way, for both of them to agree on before it starts
+    fn announce_coop(&mut self) {
+        if self.mode() != Mode::Coop || self.term.is_headless() {
+            return;
+        }
+
+        self.start_countdown(&[
+            "One snake, two players:",
+            "",
+            "Player 1 turns left and right with A/D",
+            "Player 2 turns up and down with the arrows",
             "",
-            "Press any key to play again,",
-            "or CTRL+C to quit."
+            "Talk to each other!",
         ]);
     }
 
-    fn spawn_apple(&mut self, snake: &Snake) -> Option<Coords> {
-        let choices: Vec<&Coords> = self.game_positions.iter().filter(|pos| !snake.body().contains(pos)).collect();
-        let res = choices.choose(&mut rand::thread_rng()).copied().copied();
+    fn start_countdown(&mut self, lines: &[&str]) {
+        for secs_left in (1..=START_COUNTDOWN_SECS).rev() {
+            let countdown = format!("Starting in {}s", secs_left);
+            let mut lines = lines.to_vec();
+            lines.extend(["", &countdown]);
+            self.term.show_message(&lines);
+
+            let until = Instant::now() + Duration::from_secs(1);
+            // Anything but a key, like a resize, waits out the second too
+            while let Some(ev) = self.term.poll_event(until.saturating_duration_since(Instant::now())) {
+                match ev {
+                    Event::Key(key) if is_quit(&key) => self.confirm_quit(),
+                    _ => {},
+                }
+            }
+        }
+        self.term.hide_message();
+    }
+
+    // A new set every round, kept off the rows around the start so the snake has a chance
+    fn place_obstacles(&mut self) {
+        self.update_board_size();
+        if self.config.puzzle.is_some() {
+            return;
+        }
+
+        let (spawn, _, _) = self.spawn_params();
+        let candidates: Vec<Coords> = self.board.positions().iter()
+            .filter(|pos| (pos.1 as i32 - spawn.1 as i32).abs() > 1)
+            .copied()
+            .collect();
+        let count = (self.board.positions().len() as f64 * OBSTACLE_DENSITY) as usize;
+        let walls: Vec<Coords> = candidates.choose_multiple(&mut self.rng, count).copied().collect();
+        self.board.add_obstacles(&walls);
+    }
+
+    fn shrinks(&self) -> bool {
+        self.config.shrink && self.mode() == Mode::Classic && self.config.puzzle.is_none()
+    }
+
+    // A 2x2 block away from the head, somewhere it doesn't wall off any free
+    // cell, so the board gets tighter but the snake is never shut in. Nothing
+    // happens if there's no such spot left.
+    fn add_wall_block(&mut self, snake: &Snake, apple: &Apple) {
+        let mut corners = self.board.positions().to_vec();
+        corners.shuffle(&mut self.rng);
+
+        let head = snake.head();
+        let reachable = self.board.region_of(head, &[]).size();
+        let is_free = |pos: &Coords| self.board.is_playable(*pos) && !snake.body().contains(pos)
+            && *pos != apple.pos && self.magnet.pos != Some(*pos) && !self.is_extra_apple(*pos);
+        let is_clear = |pos: &Coords| (pos.0 as i32 - head.0 as i32).abs() + (pos.1 as i32 - head.1 as i32).abs() > SHRINK_CLEARANCE;
 
-        res.map(|apple| {
-            self.term.print_at(apple, APPLE_CHAR);
+        let block = corners.into_iter()
+            .map(|(x, y)| [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)])
+            .filter(|cells| cells.iter().all(|pos| is_free(pos) && is_clear(pos)))
+            .find(|cells| self.board.region_of(head, cells).size() + cells.len() == reachable);
+
+        if let Some(cells) = block {
+            self.board.add_obstacles(&cells);
+            self.added_walls.extend(cells);
+            for pos in cells {
+                self.term.print_at(pos, self.config.theme.wall);
+            }
             self.term.flush();
-            apple
-        })
+            self.emit(JsonEvent::new("wall").positions("cells", &cells));
+            self.log_event("A wall went up");
+        }
     }
 
-    fn print_snake(&mut self, snake: &Snake) {
-        let snake_len = snake.body().len();
+    // Each snake steps on its own clock, so turning vertically costs both the same
+    fn play_two_player(&mut self) {
+        self.term.clear();
+        self.draw_board();
+        self.term.hide_message();
 
-        for (i, pos) in snake.body().iter().enumerate() {
-            let ch = if i == snake_len - 1 {snake.head_char()} else {SNAKE_BODY_CHAR};
-            self.term.print_at(*pos, ch);
+        let length = INITIAL_SNAKE_LENGTH;
+        let starts = [((self.width / 4, self.height / 2), Right), ((self.width * 3 / 4, self.height / 2), Left)];
+        let spawns = starts.map(|(target, direction)| self.board.spawn_point_near(target, length as TermInt, direction));
+        if spawns.contains(&None) {
+            self.cant_spawn("there's no room for both snakes on the board, try a bigger terminal");
         }
+        let mut players = [0, 1].map(|i| {
+            let (spawn, direction) = (spawns[i].unwrap(), starts[i].1);
+            Player { snake: Snake::new(spawn, length, direction), ticks_until_step: self.speed.initial_ticks(), dir_change: None, score: 0 }
+        });
 
-        self.term.flush();
+        self.event_log.clear();
+        self.draw_event_log();
+        let mut apple = self.spawn_shared_apple(&players);
+        for (i, player) in players.iter().enumerate() {
+            self.print_player(i, &player.snake, None);
+        }
+        self.draw_two_player_scores(&players);
+
+        loop {
+            sleep(Duration::from_millis(TICK_INTERVAL_MS));
+            self.term.update_overlays();
+
+            if self.read_two_player_keys(&mut players) {
+                return;
+            }
+
+            if self.paused { continue; }
+
+            if self.idle_due() {
+                self.idle_pause();
+                continue;
+            }
+
+            if self.break_due() {
+                self.remind_break();
+                continue;
+            }
+
+            let moves = self.step_players(&mut players);
+
+            // Running into the other snake is as bad as running into a wall, head-on kills both
+            let crashed: Vec<usize> = moves.iter()
+                .filter(|(i, mov)| matches!(mov, Crashed) || players[1 - i].snake.body().contains(&players[*i].snake.head()))
+                .map(|(i, _)| *i)
+                .collect();
+
+            for (i, mov) in &moves {
+                self.print_player(*i, &players[*i].snake, Some(mov));
+            }
+
+            if !crashed.is_empty() {
+                self.two_player_over(&players, &crashed);
+                break;
+            }
+
+            let eater = moves.iter().map(|(i, _)| *i).find(|i| apple == Some(players[*i].snake.head()));
+            if let Some(i) = eater {
+                players[i].score += 1;
+                players[i].snake.grow(self.config.growth.segments(players[i].score));
+                self.show_popup(players[i].snake.head(), "+1");
+                self.log_event(&format!("Player {} ate an apple +1", i + 1));
+                self.draw_two_player_scores(&players);
+                apple = self.spawn_shared_apple(&players);
+                if apple.is_none() {
+                    self.two_player_over(&players, &[]);
+                    break;
+                }
+            }
+        }
     }
 
-    fn print_snake_update(&mut self, snake: &Snake, mov: &MoveResult) {
-        if let Moved{new_head, old_head, old_tail} = mov {
-            self.term.print_at(*new_head, snake.head_char());
-            self.term.print_at(*old_head, SNAKE_BODY_CHAR);
+    // Each player gets a board of their own, one half of the screen, and the
+    // first one to VERSUS_TARGET points wins. Crashing loses right away.
+    fn play_versus(&mut self) {
+        // Last round's garbage comes down
+        self.update_board_size();
+        let mid = self.width / 2;
+        let mut walls: Vec<Coords> = (0..self.height).map(|y| (mid, y)).collect();
+        // The halves can only be as wide as each other with an odd width
+        if self.width.is_multiple_of(2) {
+            walls.extend((0..self.height).map(|y| (1, y)));
+        }
+        self.board.add_obstacles(&walls);
+        let offset = (self.width - 1) / 2; // From a cell on the left board to the same one on the right
 
-            if let Some(old_tail_pos) = old_tail {
-                self.term.print_at(*old_tail_pos, ' ');
+        self.term.clear();
+        self.draw_board();
+        self.term.hide_message();
+
+        let length = INITIAL_SNAKE_LENGTH;
+        let target = (mid.div_ceil(2), self.height / 2);
+        let spawns = [target, (target.0 + offset, target.1)].map(|target| self.board.spawn_point_near(target, length as TermInt, Right));
+        if spawns.contains(&None) {
+            self.cant_spawn("there's no room for both snakes on the board, try a bigger terminal");
+        }
+        let mut players = spawns.map(|spawn| {
+            Player { snake: Snake::new(spawn.unwrap(), length, Right), ticks_until_step: self.speed.initial_ticks(), dir_change: None, score: 0 }
+        });
+
+        self.event_log.clear();
+        self.draw_event_log();
+        let mut apples = [0, 1].map(|i| self.spawn_versus_apple(i, &players[i].snake, mid));
+        for (i, player) in players.iter().enumerate() {
+            self.print_player(i, &player.snake, None);
+        }
+        self.draw_two_player_scores(&players);
+        self.draw_versus_target();
+
+        loop {
+            sleep(Duration::from_millis(TICK_INTERVAL_MS));
+            self.term.update_overlays();
+
+            if self.read_two_player_keys(&mut players) {
+                return;
+            }
+
+            if self.paused { continue; }
+
+            if self.idle_due() {
+                self.idle_pause();
+                continue;
+            }
+
+            if self.break_due() {
+                self.remind_break();
+                continue;
+            }
+
+            let moves = self.step_players(&mut players);
+            for (i, mov) in &moves {
+                self.print_player(*i, &players[*i].snake, Some(mov));
+            }
+
+            let crashed: Vec<usize> = moves.iter().filter(|(_, mov)| matches!(mov, Crashed)).map(|(i, _)| *i).collect();
+            if !crashed.is_empty() {
+                self.two_player_over(&players, &crashed);
+                break;
+            }
+
+            for (i, _) in &moves {
+                let i = *i;
+                if apples[i] != Some(players[i].snake.head()) {
+                    continue;
+                }
+
+                players[i].score += 1;
+                players[i].snake.grow(self.config.growth.segments(players[i].score));
+                self.show_popup(players[i].snake.head(), "+1");
+                self.log_event(&format!("Player {} ate an apple +1", i + 1));
+                self.draw_two_player_scores(&players);
+                self.draw_versus_target();
+                apples[i] = self.spawn_versus_apple(i, &players[i].snake, mid);
+                if self.config.garbage {
+                    self.send_garbage(1 - i, &players[1 - i].snake, apples[1 - i], mid);
+                }
+            }
+
+            // Whoever ran out of room has nothing left to eat, and the score decides
+            if players.iter().any(|player| player.score >= VERSUS_TARGET) || apples.contains(&None) {
+                self.two_player_over(&players, &[]);
+                break;
+            }
+        }
+    }
+
+    // WASD for player 1 and the arrows for player 2, true if they asked for a
+    // restart and the round is over
+    fn read_two_player_keys(&mut self, players: &mut [Player; 2]) -> bool {
+        for key_ev in self.term.read_key_events_queue() {
+            match Action::of(&key_ev) {
+                Some(Action::Quit) => self.confirm_quit(),
+                Some(Action::BossScreen) => self.toggle_boss_screen(),
+                _ if self.boss_screen => {},
+                Some(Action::Suspend) => self.suspend(),
+                Some(Action::Redraw) => self.term.redraw_all(),
+                Some(Action::Pause) => self.toggle_pause(),
+                Some(Action::Help) => self.toggle_help(),
+                Some(Action::Restart) => if self.confirm_restart() {
+                    return true;
+                },
+                _ => if let Some((i, dir)) = controls::two_player_direction(key_ev.code) {
+                    players[i].dir_change = Some(dir);
+                },
+            }
+        }
+        false
+    }
+
+    // Only the players whose clock ran out this tick move
+    fn step_players(&self, players: &mut [Player; 2]) -> Vec<(usize, MoveResult)> {
+        let mut moves = vec![];
+        for (i, player) in players.iter_mut().enumerate() {
+            player.ticks_until_step -= 1.0;
+            if player.ticks_until_step > 0.0 {
+                continue;
+            }
+
+            if let Some(dir) = player.dir_change.take() {
+                player.snake.set_direction(dir);
             }
+            player.ticks_until_step += self.speed.step_ticks(player.score, player.snake.get_direction());
+            moves.push((i, player.snake.move_step(&self.board)));
+        }
+        moves
+    }
+
+    fn spawn_versus_apple(&mut self, player: usize, snake: &Snake, mid: TermInt) -> Option<Coords> {
+        let free: Vec<&Coords> = self.board.positions().iter()
+            .filter(|pos| (pos.0 < mid) == (player == 0) && !snake.body().contains(pos))
+            .collect();
+        let fair = self.fair_apple_spots(&free, &[snake]);
+        let pos = fair.choose(&mut self.rng).copied().copied()?;
+        self.term.print_at(pos, self.config.theme.apple);
+        self.term.flush();
+        Some(pos)
+    }
+
+    // A single block of wall on that player's board, away from the head and
+    // somewhere it doesn't wall off any free cell, like shrinking does
+    fn send_garbage(&mut self, player: usize, snake: &Snake, apple: Option<Coords>, mid: TermInt) {
+        let head = snake.head();
+        let reachable = self.board.region_of(head, &[]).size();
+        let mut spots: Vec<Coords> = self.board.positions().iter()
+            .filter(|pos| (pos.0 < mid) == (player == 0) && !snake.body().contains(pos) && Some(**pos) != apple)
+            .filter(|pos| (pos.0 as i32 - head.0 as i32).abs() + (pos.1 as i32 - head.1 as i32).abs() > GARBAGE_CLEARANCE)
+            .copied()
+            .collect();
+        spots.shuffle(&mut self.rng);
 
+        if let Some(pos) = spots.into_iter().find(|pos| self.board.region_of(head, &[*pos]).size() + 1 == reachable) {
+            self.board.add_obstacles(&[pos]);
+            self.term.print_at(pos, self.config.theme.wall);
             self.term.flush();
+            self.log_event(&format!("Player {} got a wall", player + 1));
         }
     }
 
-    fn toggle_pause(&mut self) {
-        if !self.paused {
-            self.term.show_message(&["Paused", "Press Esc to resume", "or Ctrl+C to quit"]);
-        } else {
-            self.term.hide_message();
+    // On the right of the top wall, the scores take the left
+    fn draw_versus_target(&mut self) {
+        let text = format!(" First to {} ", VERSUS_TARGET);
+        let bar = self.layout.top_bar;
+        let x = bar.width.saturating_sub(text.len() as TermInt);
+        self.term.print_text_in(bar, (x, 0), &text, ContentStyle::new());
+        self.term.flush();
+    }
+
+    // With nobody crashed or both of them, the score decides
+    fn two_player_over(&mut self, players: &[Player; 2], crashed: &[usize]) {
+        self.term.clear_overlays();
+        self.update_title("Game over");
+        for i in crashed {
+            for pos in players[*i].snake.body() {
+                self.term.print_at(*pos, self.config.theme.dead);
+            }
         }
 
-        self.paused = !self.paused;
+        let (left, right) = (players[0].score, players[1].score);
+        let winner = match crashed {
+            [1] => Some(0),
+            [0] => Some(1),
+            _ if left > right => Some(0),
+            _ if right > left => Some(1),
+            _ => None,
+        };
+        let (title, outcome) = match winner {
+            Some(0) => ("Player 1 wins!", "player_1"),
+            Some(_) => ("Player 2 wins!", "player_2"),
+            None => ("It's a draw!", "draw"),
+        };
+        let (mode, duration) = (self.mode().name(), self.round_started.elapsed());
+        if let Some(summary) = &mut self.summary {
+            summary.add(mode, outcome, &[left, right], duration);
+        }
+
+        self.restart_countdown(title, &[&format!("Player 1: {}  Player 2: {}", left, right)], false);
     }
-}
 
-fn is_ctrl_c(ev: &KeyEvent) -> bool {
-    matches!(ev, KeyEvent { code: KeyCode::Char('c'), modifiers: KeyModifiers::CONTROL })
+    fn spawn_shared_apple(&mut self, players: &[Player; 2]) -> Option<Coords> {
+        let free: Vec<&Coords> = self.board.positions().iter()
+            .filter(|pos| players.iter().all(|player| !player.snake.body().contains(pos)))
+            .collect();
+        let fair = self.fair_apple_spots(&free, &[&players[0].snake, &players[1].snake]);
+        let pos = fair.choose(&mut self.rng).copied().copied()?;
+        self.term.print_at(pos, self.config.theme.apple);
+        self.term.flush();
+        Some(pos)
+    }
+
+    // Player 1 looks like the single player snake, player 2 gets the theme's rival body
+    fn print_player(&mut self, i: usize, snake: &Snake, mov: Option<&MoveResult>) {
+        if i == 0 {
+            return match mov {
+                Some(mov) => self.print_snake_update(snake, mov),
+                None => self.print_snake(snake),
+            };
+        }
+
+        match mov {
+            Some(Moved { new_head, old_head, old_tail }) => {
+                self.term.print_at(*new_head, self.head_cell(snake));
+                self.term.print_at(*old_head, self.config.theme.rival);
+                if let Some(old_tail) = old_tail {
+                    self.term.print_at(*old_tail, Cell::plain(' '));
+                }
+                self.term.flush();
+            },
+            Some(Crashed) => {},
+            None => {
+                let (head, body) = snake.body().split_last().unwrap();
+                for pos in body {
+                    self.term.print_at(*pos, self.config.theme.rival);
+                }
+                self.term.print_at(*head, self.head_cell(snake));
+                self.term.flush();
+            },
+        }
+    }
+
+    fn draw_two_player_scores(&mut self, players: &[Player; 2]) {
+        let text = format!(" Player 1: {}  Player 2: {} ", players[0].score, players[1].score);
+        self.term.print_text_in(self.layout.top_bar, (0, 0), &text, ContentStyle::new());
+        self.term.flush();
+        self.title_score = format!("{} to {}", players[0].score, players[1].score);
+        self.update_title("Playing");
+    }
+
+    // Over the top wall, clear of the puzzle and mirror statuses at the bottom
+    fn draw_time_left(&mut self, secs: u64) {
+        let text = format!(" Time left: {}:{:02} ", secs / 60, secs % 60);
+        self.term.print_text_in(self.layout.top_bar, (0, 0), &text, ContentStyle::new());
+        self.term.flush();
+    }
+
+    // On the right of the top wall, the time left takes the left
+    fn draw_target_length(&mut self, snake: &Snake) {
+        if let Some(target) = self.goal().target_length() {
+            let text = format!(" Length: {}/{} ", goal::length_after_growing(snake), target);
+            let bar = self.layout.top_bar;
+            let x = bar.width.saturating_sub(text.len() as TermInt);
+            self.term.print_text_in(bar, (x, 0), &text, ContentStyle::new());
+            self.term.flush();
+        }
+    }
+
+    // In the middle of the bottom wall, between the best score and the mirror status
+    fn draw_apples_goal(&mut self, eaten: u64) {
+        if let Some(target) = self.goal().target_apples() {
+            let full = (eaten.min(target) * GOAL_BAR_WIDTH as u64 / target) as usize;
+            let text = format!(" Apples {}{} {}/{} ", "█".repeat(full), "░".repeat(GOAL_BAR_WIDTH - full), eaten, target);
+            let bar = self.layout.bottom_bar;
+            let x = (bar.width / 2).saturating_sub(text.chars().count() as TermInt / 2);
+            self.term.print_text_in(bar, (x, 0), &text, ContentStyle::new());
+            self.term.flush();
+        }
+    }
+
+    // On the left of the top wall, where the time left would go, with the last
+    // turn each player asked for so they can tell who did what
+    fn draw_coop_status(&mut self, score: u64, turns: [Option<Direction>; 2]) {
+        if self.mode() == Mode::Coop {
+            let turn = |i: usize| turns[i].map_or('·', turn_arrow);
+            let text = format!(" Team score: {}  P1 {}  P2 {} ", score, turn(0), turn(1));
+            self.term.print_text_in(self.layout.top_bar, (0, 0), &text, ContentStyle::new());
+            self.term.flush();
+        }
+    }
+
+    // In the middle of the top wall, between the time left and the length
+    fn draw_energy(&mut self, hunger: Option<&Hunger>) {
+        if let Some(hunger) = hunger {
+            let full = (hunger.energy() * ENERGY_BAR_WIDTH as f64).ceil() as usize;
+            let text = format!(" Energy {}{} ", "█".repeat(full), "░".repeat(ENERGY_BAR_WIDTH - full));
+            let bar = self.layout.top_bar;
+            let x = (bar.width / 2).saturating_sub(text.chars().count() as TermInt / 2);
+            self.term.print_text_in(bar, (x, 0), &text, ContentStyle::new());
+            self.term.flush();
+        }
+    }
+
+    // A turn into certain death, when going straight on wouldn't be. Turning
+    // back is left alone, the snake ignores that anyway.
+    fn refused_by_assist(&self, snake: &Snake, dir: Direction) -> bool {
+        let heading = snake.get_direction();
+        self.config.assist && dir != heading.opposite()
+            && snake.would_crash(dir, &self.board) && !snake.would_crash(heading, &self.board)
+    }
+
+    // Right above where it happened, the head is there and should stay in sight
+    fn show_popup(&mut self, pos: Coords, text: &str) {
+        let y = if pos.1 > 1 {pos.1 - 1} else {pos.1 + 1};
+        self.term.show_overlay((pos.0, y), text);
+    }
+
+    fn update_board_size(&mut self) {
+        let (w, h) = self.term.get_terminal_size();
+        let panel_width = self.panel_width();
+        // A run code's board if there's room for it, in the top left corner
+        let screen = match self.board_size {
+            Some((bw, bh)) if bw + panel_width <= w && bh <= h => (bw + panel_width, bh),
+            _ => (w, h),
+        };
+        self.layout = Layout::new(screen, panel_width);
+        self.width = self.layout.board.width;
+        self.height = self.layout.board.height;
+
+        self.board = match &self.config.puzzle {
+            Some(level) => Board::from_level(level, self.width, self.height),
+            None => Board::new(self.config.shape, self.width, self.height),
+        };
+        self.update_speed();
+    }
+
+    fn panel_width(&self) -> TermInt {
+        if self.lan.is_some() || self.config.step_mode || self.config.event_log || self.config.pip {SIDE_PANEL_WIDTH} else {0}
+    }
+
+    // Sets up the next round to start just like the one the code came from,
+    // true if it will. The code's mode is picked on the way.
+    fn enter_run_code(&mut self) -> bool {
+        let footer = ["From the game over screen of the round to play", "Enter to play it, Esc to go back"];
+        let text = match menu::enter_text(&mut self.term, "Run code", RUN_CODE_INPUT, &footer) {
+            Some(text) => text,
+            None => return false,
+        };
+
+        match self.check_run_code(&text) {
+            Ok(code) => {
+                self.config.mode = Some(Mode::ALL[code.mode as usize]);
+                self.run_code = Some(code);
+                true
+            },
+            Err(e) => {
+                self.term.ask_at(Anchor::Center, &["Can't play that run code:", &e, "", "Press any key to go back"]);
+                false
+            },
+        }
+    }
+
+    fn check_run_code(&mut self, text: &str) -> Result<RunCode, String> {
+        let code = RunCode::decode(text)?;
+        let mode = *Mode::ALL.get(code.mode as usize).ok_or("it's for a mode this game doesn't have")?;
+
+        let (w, h) = self.term.get_terminal_size();
+        if code.width + self.panel_width() > w || code.height > h {
+            return Err(format!("it was played on a {}x{} board, which doesn't fit in this terminal", code.width, code.height));
+        }
+
+        // The settings the round is about to be played with, in the code's mode
+        self.restore_rules();
+        let previous = self.config.mode.replace(mode);
+        let same_rules = code.same_rules(self.config.rules_hash());
+        self.config.mode = previous;
+        if !same_rules {
+            return Err("it was played with other settings, they all have to match".into());
+        }
+
+        Ok(code)
+    }
+
+    fn resize(&mut self, width: TermInt, height: TermInt) {
+        // The new size is picked up by the next round, the current board is gone anyway
+        self.term.resize(width, height);
+        self.update_board_size();
+    }
+
+    // On the puzzle's own spot if it has one, otherwise the free one closest
+    // to the middle that the whole body fits behind
+    fn spawn_params(&mut self) -> (Coords, Direction, i16) {
+        let (level_spawn, direction, length) = match &self.config.puzzle {
+            Some(level) => (level.spawn, level.direction, level.length),
+            None => (None, Right, INITIAL_SNAKE_LENGTH),
+        };
+
+        let origin = self.board.origin();
+        let spawn = match level_spawn.map(|pos| (pos.0 + origin.0, pos.1 + origin.1)) {
+            Some(pos) if self.board.fits_snake(pos, length as TermInt, direction) => Some(pos),
+            Some(_) => self.cant_spawn(&format!("the snake doesn't fit behind the puzzle's @, heading {}", direction.name())),
+            None => self.board.spawn_point_near((self.width / 2, self.height / 2), length as TermInt, direction),
+        };
+
+        match spawn {
+            Some(spawn) => (spawn, direction, length),
+            None => self.cant_spawn("there's no room for the snake on the board, try a bigger terminal"),
+        }
+    }
+
+    fn cant_spawn(&mut self, why: &str) -> ! {
+        self.term.restore();
+        eprintln!("Error spawning: {}", why);
+        exit(1);
+    }
+
+    fn emit(&mut self, event: JsonEvent) {
+        #[cfg(feature = "discord")]
+        if let Some(presence) = &self.presence {
+            presence.event(event.to_json());
+        }
+
+        if let Some(events) = &mut self.events {
+            events.emit(event);
+        }
+    }
+
+    // A puzzle's own target wins over the one given for every game
+    fn goal(&self) -> Goal {
+        let level_target = self.config.puzzle.as_ref().and_then(|level| level.target_length);
+        match (level_target.or(self.config.target_length), self.config.target_apples) {
+            (Some(target), _) => Goal::Length(target),
+            (None, Some(target)) => Goal::Apples(target),
+            (None, None) => Goal::NoApplesLeft,
+        }
+    }
+
+    fn move_limit(&self) -> Option<u64> {
+        self.config.puzzle.as_ref().and_then(|level| level.move_limit)
+    }
+
+    fn game_over(&mut self, snake: &Snake, outcome: Outcome, score: u64, heatmap: &Heatmap) {
+        // Show what was lurking out there
+        self.term.set_fog(None);
+        self.term.clear_overlays();
+        self.update_title("Game over");
+        let puzzle = self.config.puzzle.as_ref().is_some_and(|level| !level.apples.is_empty());
+        let s = match outcome {
+            Outcome::Crashed => "Game over!",
+            Outcome::Won if puzzle => "Puzzle solved!",
+            Outcome::Won => "You won!",
+            Outcome::OutOfMoves => "Out of moves!",
+            Outcome::OutOfTime => "Time's up!",
+            Outcome::Trapped => "Trapped!",
+            Outcome::Starved => "Starved!",
+        };
+        let win = matches!(outcome, Outcome::Won);
+        let outcome_name = match outcome {
+            Outcome::Crashed => "crashed",
+            Outcome::Won => "won",
+            Outcome::OutOfMoves => "out_of_moves",
+            Outcome::OutOfTime => "out_of_time",
+            Outcome::Trapped => "trapped",
+            Outcome::Starved => "starved",
+        };
+        self.emit(JsonEvent::new("game_over")
+            .str("outcome", outcome_name).num("score", score).num("moves", snake.moves()));
+        let (mode, duration) = (self.scores_mode(), self.round_started.elapsed());
+        if let Some(summary) = &mut self.summary {
+            summary.add(mode, outcome_name, &[score], duration);
+        }
+        // Checked it could be written at the start, there's nowhere to say so now
+        if let (Some(metrics), Some(path)) = (&self.metrics, &self.config.export_metrics) {
+            metrics.save(path).ok();
+        }
+
+        let mode = self.scores_mode();
+        let previous_best = self.best_score();
+        let mut unlocked = Vec::new();
+        if self.counts_for_scores() {
+            let level = self.config.puzzle.as_ref().and_then(|level| level.name.clone());
+            self.scores.add(mode, score, snake.body().len() as u64, snake.moves(), self.round_rules, level.as_deref());
+            // Nowhere to show an error while playing, the next game will try again
+            self.scores.save().ok();
+            self.save_recording(score);
+            let round = achievements::Round {
+                score, length: snake.body().len(), won: win, puzzle: self.config.puzzle.is_some(),
+            };
+            unlocked = self.achievements.unlock(&round);
+            self.achievements.save().ok();
+        }
+
+        let summary = match self.move_limit() {
+            Some(limit) => format!("Moves: {}/{}", snake.moves(), limit),
+            None if puzzle => format!("Moves: {}", snake.moves()),
+            None => match previous_best {
+                Some(best) if score <= best => format!("Score: {}  Best: {}", score, best),
+                Some(_) => format!("Score: {}  New best!", score),
+                None => format!("Score: {}", score),
+            },
+        };
+
+        if self.config.heatmap && !self.config.screensaver {
+            for i in 0..self.board.positions().len() {
+                let pos = self.board.positions()[i];
+                self.term.print_at(pos, self.config.theme.heat_cell(heatmap.shade(pos), heatmap.heat(pos)));
+            }
+        } else if !win {
+            for pos in snake.body() {
+                self.term.print_at(*pos, self.config.theme.dead);
+            }
+        }
+
+        if self.config.screensaver || self.term.is_headless() {
+            // No prompt, just let the dead snake sit there for a bit and start over
+            self.term.flush();
+            sleep(Duration::from_millis(SCREENSAVER_RESTART_MS));
+            if !self.term.read_key_events_queue().is_empty() {
+                self.clean_exit();
+            }
+        } else {
+            let mode = Mode::ALL.iter().position(|mode| *mode == self.mode()).unwrap() as u8;
+            let code = RunCode::new(self.seed as u32, (self.width, self.height), mode, self.round_rules);
+            let code_line = format!("Run code: {}", code.encode());
+            let unlocked_lines: Vec<String> = unlocked.iter().map(|a| format!("Achievement: {}", a.name)).collect();
+            let mut lines = vec![summary.as_str(), code_line.as_str()];
+            lines.extend(unlocked_lines.iter().map(String::as_str));
+            self.restart_countdown(s, &lines, true);
+        }
+    }
+
+    // Named after when the game ended, only the latest few are kept
+    fn save_recording(&mut self, score: u64) {
+        // Recordings only have room for the one apple, these would never add up
+        if self.config.extra_apples {
+            return;
+        }
+        let header = recording::Header {
+            seed: self.seed, config_hash: self.config.rules_hash(), score, far_apples: self.config.distance_scoring,
+            drifting_apples: self.mode() == Mode::Drifting, hunger: self.config.hunger, growth: self.config.growth,
+        };
+        // The round as it started, walls that came later just never get in the way
+        let (width, height) = self.board.size();
+        let mask: Vec<bool> = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|pos| self.board.is_playable(pos) || self.added_walls.contains(&pos))
+            .collect();
+        let bytes = recording::encode(&header, &Board::from_mask(width, height, &mask), &self.replay);
+        let dir = paths::replays_dir(self.config.profile.as_deref());
+        let timestamp_ms = self.scores.records().last().map_or(0, |r| r.timestamp_ms);
+        if fs::create_dir_all(&dir).is_err() || storage::write_atomic(&dir.join(format!("{}.snkr", timestamp_ms)), bytes).is_err() {
+            return;
+        }
+
+        let mut saved: Vec<PathBuf> = fs::read_dir(&dir).into_iter().flatten().flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "snkr"))
+            .collect();
+        saved.sort();
+        for path in saved.iter().rev().skip(MAX_SAVED_REPLAYS) {
+            fs::remove_file(path).ok();
+            fs::remove_file(path.with_extension("snkr.bak")).ok();
+        }
+    }
+
+    fn restart_countdown(&mut self, title: &str, summary: &[&str], can_replay: bool) {
+        // Starts over after watching the replay
+        'countdown: loop {
+            for secs_left in (1..=RESTART_COUNTDOWN_SECS).rev() {
+                let countdown_line = format!("Restarting in {}s", secs_left);
+                let mut lines = vec![title];
+                lines.extend(summary);
+                lines.extend(["", &countdown_line, "Press any key to play again,"]);
+                if can_replay {
+                    lines.push("V to watch the replay,");
+                }
+                lines.extend(["M for the menu,", "or Q to quit."]);
+                self.term.show_message(&lines);
+
+                let deadline = Instant::now() + Duration::from_secs(1);
+                while let Some(ev) = self.term.poll_event(deadline.saturating_duration_since(Instant::now())) {
+                    match ev {
+                        Event::Key(key_ev) => match Action::of(&key_ev) {
+                            Some(Action::Quit) => self.clean_exit(),
+                            Some(Action::Suspend) => {
+                                self.term.suspend();
+                                continue 'countdown;
+                            },
+                            Some(Action::Redraw) => self.term.redraw_all(),
+                            Some(Action::Replay) if can_replay => {
+                                self.watch_replay();
+                                continue 'countdown;
+                            },
+                            Some(Action::Menu) => {
+                                self.main_menu();
+                                return;
+                            },
+                            _ => return,
+                        },
+                        Event::Resize(w, h) => {
+                            self.resize(w, h);
+                            self.term.show_message(&lines);
+                        },
+                        _ => {}
+                    }
+                }
+            }
+
+            return;
+        }
+    }
+
+    fn watch_replay(&mut self) {
+        self.term.hide_message();
+        self.term.set_fog(None);
+        self.term.clear();
+        self.draw_board();
+
+        let status_row = self.height - 1;
+        if let Exit::Quit = viewer::watch(&mut self.term, &self.config.theme, &self.replay, status_row) {
+            self.clean_exit();
+        }
+
+        // Put the wall back where the status was
+        self.draw_board();
+    }
+
+    fn spawn_apple(&mut self, snake: &Snake, eaten: usize) -> Option<Apple> {
+        let pos = match &self.config.puzzle {
+            // Levels that are only walls get their apples anywhere
+            Some(level) if !level.apples.is_empty() => {
+                // Puzzles have a fixed sequence of apples
+                let origin = self.board.origin();
+                level.apples.get(eaten).map(|pos| (pos.0 + origin.0, pos.1 + origin.1))?
+            },
+            _ => {
+                let free: Vec<&Coords> = self.board.positions().iter()
+                    .filter(|pos| !snake.body().contains(pos) && self.magnet.pos != Some(**pos) && !self.is_extra_apple(**pos))
+                    .collect();
+                let fair = self.fair_apple_spots(&free, &[snake]);
+                fair.choose(&mut self.rng).copied().copied()?
+            },
+        };
+
+        let velocity = (self.mode() == Mode::Drifting).then(|| *drift::DIAGONALS.choose(&mut self.rng).unwrap());
+        Some(self.new_apple(snake, pos, velocity))
+    }
+
+    // Anywhere fair, apart from the main apple. Puzzles with apples of their
+    // own never get any, and they sit still even when the main one drifts.
+    fn spawn_extra_apple(&mut self, snake: &Snake, main: Coords) -> Option<Apple> {
+        if self.config.puzzle.as_ref().is_some_and(|level| !level.apples.is_empty()) {
+            return None;
+        }
+
+        let free: Vec<&Coords> = self.board.positions().iter()
+            .filter(|pos| !snake.body().contains(pos) && **pos != main && self.magnet.pos != Some(**pos) && !self.is_extra_apple(**pos))
+            .collect();
+        let fair = self.fair_apple_spots(&free, &[snake]);
+        let pos = fair.choose(&mut self.rng).copied().copied()?;
+        Some(self.new_apple(snake, pos, None))
+    }
+
+    fn is_extra_apple(&self, pos: Coords) -> bool {
+        self.extra_apples.iter().any(|extra| extra.pos == pos)
+    }
+
+    fn new_apple(&mut self, snake: &Snake, pos: Coords, velocity: Option<Velocity>) -> Apple {
+        let mut cell = self.config.theme.apple;
+        let value = if self.config.distance_scoring {
+            // Apples far away from the head are riskier to go for, and thus worth more
+            let value = self.board.distance_value(snake.head(), pos);
+            cell.ch = std::char::from_digit(value as u32, 10).unwrap();
+            value
+        } else {
+            1
+        };
+
+        self.term.print_at(pos, cell);
+        self.term.flush();
+        self.emit(JsonEvent::new("apple").pos("pos", pos).num("value", value));
+        Apple { pos, value, cell, velocity }
+    }
+
+    // Not right in front of a head, which would be a free point, nor walled off
+    // where no snake can get to it. Only walls count, bodies move out of the way.
+    // When that rules out everything left, any free spot will do.
+    fn fair_apple_spots<'a>(&self, free: &[&'a Coords], snakes: &[&Snake]) -> Vec<&'a Coords> {
+        let regions: Vec<_> = snakes.iter().map(|snake| self.board.region_of(snake.head(), &[])).collect();
+        let fair: Vec<&Coords> = free.iter().copied()
+            .filter(|pos| snakes.iter().all(|snake| snake.get_direction().step_from(snake.head()) != **pos))
+            .filter(|pos| regions.iter().any(|region| region.contains(**pos)))
+            .collect();
+
+        if fair.is_empty() {free.to_vec()} else {fair}
+    }
+
+    fn update_fog(&mut self, snake: &Snake) {
+        let radius = match self.config.fog {
+            Some(radius) => radius,
+            None => return,
+        };
+
+        // Just the inside of the arena, walls and anything around it stay in sight
+        let (top_left, size) = match &self.config.puzzle {
+            Some(level) => (self.board.origin(), (level.width, level.height)),
+            None => ((0, 0), (self.width, self.height)),
+        };
+        let top_left_inside = (top_left.0 + 1, top_left.1 + 1);
+        let bottom_right = ((top_left.0 + size.0).saturating_sub(1), (top_left.1 + size.1).saturating_sub(1));
+
+        self.term.set_fog(Some(Fog { top_left: top_left_inside, bottom_right, center: snake.head(), radius }));
+    }
+
+    fn spawn_magnet(&mut self, snake: &Snake, apple: &Apple) {
+        let choices: Vec<&Coords> = self.board.positions().iter()
+            .filter(|pos| !snake.body().contains(pos) && **pos != apple.pos && !self.is_extra_apple(**pos))
+            .collect();
+
+        if let Some(pos) = choices.choose(&mut self.rng) {
+            self.magnet.pos = Some(**pos);
+            self.term.print_at(**pos, self.config.theme.magnet);
+            self.log_event("A magnet showed up");
+        }
+    }
+
+    // Drags the apple one cell closer to the head, along whichever axis it's furthest on
+    // Whether it moved at all
+    fn pull_apple(&mut self, snake: &Snake, apple: &mut Apple) -> bool {
+        let head = snake.head();
+        let (dx, dy) = (head.0 as i32 - apple.pos.0 as i32, head.1 as i32 - apple.pos.1 as i32);
+        let dir = if dx.abs() >= dy.abs() {
+            if dx > 0 {Right} else {Left}
+        } else if dy > 0 {Down} else {Up};
+
+        let target = dir.step_from(apple.pos);
+        if target == head || !self.board.is_playable(target) || snake.body().contains(&target) || self.magnet.pos == Some(target) || self.is_extra_apple(target) {
+            return false;
+        }
+
+        self.term.print_at(apple.pos, Cell::plain(' '));
+        self.term.print_at(target, apple.cell);
+        apple.pos = target;
+        true
+    }
+
+    fn drift_apple(&mut self, snake: &Snake, apple: &mut Apple) {
+        let (pos, velocity) = self.next_drift(snake, apple);
+        apple.velocity = Some(velocity);
+        if pos != apple.pos {
+            self.term.print_at(apple.pos, Cell::plain(' '));
+            self.term.print_at(pos, apple.cell);
+            apple.pos = pos;
+        }
+    }
+
+    fn next_drift(&self, snake: &Snake, apple: &Apple) -> (Coords, Velocity) {
+        let velocity = apple.velocity.expect("Error drifting: the apple isn't a drifting one");
+        let is_free = |pos| self.board.is_playable(pos) && !snake.body().contains(&pos) && self.magnet.pos != Some(pos) && !self.is_extra_apple(pos);
+        drift::next(apple.pos, velocity, is_free)
+    }
+
+    fn print_snake(&mut self, snake: &Snake) {
+        self.term.print_at(snake.head(), self.head_cell(snake));
+        self.repaint_body(snake);
+        self.term.flush();
+    }
+
+    fn print_snake_update(&mut self, snake: &Snake, mov: &MoveResult) {
+        if let Moved{new_head, old_head, old_tail} = mov {
+            self.term.print_at(*new_head, self.head_cell(snake));
+
+            if self.config.theme.body_paint == BodyPaint::Solid {
+                self.term.print_at(*old_head, self.config.theme.body);
+                // A starving snake can be down to just its head
+                if let [tail, _, ..] = snake.body() {
+                    self.term.print_at(*tail, self.config.theme.tail);
+                }
+            } else {
+                // Every segment's color depends on its position along the body
+                self.repaint_body(snake);
+            }
+
+            if let Some(old_tail_pos) = old_tail {
+                self.term.print_at(*old_tail_pos, Cell::plain(' '));
+            }
+
+            self.term.flush();
+        }
+    }
+
+    // Gone to hunger, after the snake was already drawn
+    fn drop_tail(&mut self, snake: &Snake, tail: Coords) {
+        self.term.print_at(tail, Cell::plain(' '));
+        self.repaint_body(snake);
+        self.log_event("Hungry, lost a segment");
+        self.term.flush();
+    }
+
+    fn repaint_body(&mut self, snake: &Snake) {
+        let body = &snake.body()[..snake.body().len() - 1];
+
+        for (i, pos) in body.iter().rev().enumerate() {
+            let cell = self.config.theme.body_cell(i, body.len(), snake.moves());
+            if self.term.cell_at(*pos) != cell {
+                self.term.print_at(*pos, cell);
+            }
+        }
+    }
+
+    fn draw_lan_panel(&mut self) {
+        let entries = match &self.lan {
+            Some(lan) => lan.leaderboard(),
+            None => return,
+        };
+
+        // Whoever was already around when the game started doesn't count as joining
+        let players: Vec<String> = entries.iter().filter(|e| !e.is_local).map(|e| e.name.clone()).collect();
+        if let Some(before) = self.lan_players.replace(players.clone()) {
+            for name in players.iter().filter(|name| !before.contains(name)) {
+                self.term.show_toast(&format!("{} joined", name));
+            }
+            for name in before.iter().filter(|name| !players.contains(name)) {
+                self.term.show_toast(&format!("{} left", name));
+            }
+        }
+
+        let [_, _, _, panel, _] = self.side_panels();
+        let header = format!("{:<11}{:>5}{:>5}", "LAN", "now", "best");
+        self.print_panel_line(panel, 0, &header);
+
+        for row in 0..panel.height.saturating_sub(2) {
+            let line = match entries.get(row as usize) {
+                Some(e) => format!("{}{:<10}{:>5}{:>5}", if e.is_local {'>'} else {' '}, e.name, e.score, e.best),
+                None => String::new(),
+            };
+            self.print_panel_line(panel, row + 2, &line);
+        }
+
+        self.draw_chat_panel();
+        self.term.flush();
+    }
+
+    // The latest lines that fit, wrapped to the panel and newest at the bottom
+    fn draw_chat_panel(&mut self) {
+        let chat = match &self.lan {
+            Some(lan) => lan.chat(),
+            None => return,
+        };
+
+        let [_, _, _, _, panel] = self.side_panels();
+        if panel.height == 0 {
+            return;
+        }
+        self.print_panel_line(panel, 0, "CHAT (T to talk)");
+
+        let rows = panel.height.saturating_sub(2) as usize;
+        let lines: Vec<String> = chat.iter()
+            .flat_map(|line| term::wrap(&format!("{}: {}", line.name, line.text), panel.width as usize))
+            .collect();
+        let shown = &lines[lines.len().saturating_sub(rows)..];
+        for row in 0..rows {
+            let line = shown.get(row).map_or("", String::as_str);
+            self.print_panel_line(panel, row as TermInt + 2, line);
+        }
+    }
+
+    // The game waits while typing, nobody else's does
+    fn chat(&mut self) {
+        let text = menu::enter_text(&mut self.term, "Say to the LAN", lan::CHAT_LENGTH, &["Enter to send, Esc to go back"]);
+        if self.paused {
+            self.term.show_message(&PAUSE_LINES);
+        } else {
+            self.term.hide_message();
+        }
+
+        if let (Some(lan), Some(text)) = (&self.lan, text) {
+            if !text.trim().is_empty() {
+                lan.say(&text);
+            }
+        }
+        self.draw_lan_panel();
+    }
+
+    fn draw_debug_panel(&mut self, snake: &Snake, apple: &Apple, score: u64, eaten: u64) {
+        if !self.config.step_mode {
+            return;
+        }
+
+        let head = snake.head();
+        let tail = snake.body()[0];
+        let lines = [
+            "STEP MODE".to_string(),
+            "Space to step".to_string(),
+            String::new(),
+            format!("move      {}", snake.moves()),
+            format!("direction {:?}", snake.get_direction()),
+            format!("head      ({}, {})", head.0, head.1),
+            format!("tail      ({}, {})", tail.0, tail.1),
+            format!("length    {}", snake.body().len()),
+            format!("growing   {}", snake.growth_left()),
+            format!("apple     ({}, {})", apple.pos.0, apple.pos.1),
+            format!("apple val {}", apple.value),
+            format!("score     {}", score),
+            format!("eaten     {}", eaten),
+        ];
+
+        let [panel, _, _, _, _] = self.side_panels();
+        for (row, line) in lines.iter().enumerate() {
+            self.print_panel_line(panel, row as TermInt, line);
+        }
+
+        self.term.flush();
+    }
+
+    // Newest at the bottom, only kept with --event-log
+    fn log_event(&mut self, text: &str) {
+        if !self.config.event_log {
+            return;
+        }
+
+        if self.event_log.len() == EVENT_LOG_LINES {
+            self.event_log.pop_front();
+        }
+        self.event_log.push_back(text.to_string());
+        self.draw_event_log();
+    }
+
+    fn draw_event_log(&mut self) {
+        if !self.config.event_log {
+            return;
+        }
+
+        let [_, panel, _, _, _] = self.side_panels();
+        self.print_panel_line(panel, 0, "EVENTS");
+        for row in 0..EVENT_LOG_LINES {
+            let line = self.event_log.get(row).cloned().unwrap_or_default();
+            self.print_panel_line(panel, row as TermInt + 2, &line);
+        }
+        self.term.flush();
+    }
+
+    // Games played by the computer, a bot or with help say nothing about the player
+    fn counts_for_scores(&self) -> bool {
+        !self.config.screensaver && self.control.is_none() && !self.config.assist
+    }
+
+    fn scores_mode(&self) -> &'static str {
+        if self.config.puzzle.is_some() {"puzzle"} else {self.mode().name()}
+    }
+
+    // Only from games with the same rules, which covers the puzzle level too
+    fn best_score(&self) -> Option<u64> {
+        self.scores.best_with(self.scores_mode(), self.round_rules)
+    }
+
+    // Stacked down the side panel: step mode, the event log, then the LAN leaderboard
+    fn side_panels(&self) -> [Region; 5] {
+        let (debug, rest) = self.layout.panel.split_rows(if self.config.step_mode {DEBUG_PANEL_ROWS} else {0});
+        let (events, rest) = rest.split_rows(if self.config.event_log {EVENT_LOG_LINES as TermInt + 2} else {0});
+        let (pip, rest) = rest.split_rows(if self.config.pip {PIP_ROWS + 3} else {0});
+        // The chat takes the bottom of what the leaderboard would have had
+        let chat_rows = if self.lan.is_some() {CHAT_ROWS.min(rest.height / 2)} else {0};
+        let (lan, chat) = rest.split_rows(rest.height - chat_rows);
+        [debug, events, pip, lan, chat]
+    }
+
+    // At the speed the snake is set to go, not however fast the ticks came
+    fn record_metrics(&mut self, snake: &Snake, time_ms: u32, score: u64, eaten: u64, ate: bool, sprinting: bool) {
+        let boost = if self.config.double_speed {2.0} else {1.0} * if sprinting {SPRINT_FACTOR} else {1.0};
+        let speed = boost * 1000.0 / (self.speed.step_ticks(eaten, snake.get_direction()) * TICK_INTERVAL_MS as f64);
+        if let Some(metrics) = &mut self.metrics {
+            metrics.record(snake, time_ms, score, speed, ate);
+        }
+    }
+
+    // The computer's game keeps its own pace, it's just checked on every tick
+    fn update_pip(&mut self) {
+        if !self.config.pip {
+            return;
+        }
+
+        let [_, _, panel, _, _] = self.side_panels();
+        let (title, rest) = panel.split_rows(2);
+        let (board, _) = rest.split_rows(PIP_ROWS);
+        let pip = self.pip.get_or_insert_with(|| PictureInPicture::new(board.width, PIP_ROWS));
+        pip.update(&mut self.term, title, board);
+    }
+
+    // Padded to the whole width, so a shorter line doesn't leave leftovers behind
+    fn print_panel_line(&mut self, panel: Region, y: TermInt, text: &str) {
+        let line = format!("{:<w$.w$}", text, w = panel.width as usize);
+        self.term.print_text_in(panel, (0, y), &line, ContentStyle::new());
+    }
+
+    // Marks the free cells around the head that only lead to crashing, and
+    // takes down the old marks, all of them when hints are off
+    fn update_hints(&mut self, snake: &Snake, apple: Coords, hints: &mut Vec<Coords>) {
+        for old in hints.drain(..) {
+            if self.term.cell_at(old) == self.config.theme.danger {
+                self.term.print_at(old, Cell::plain(' '));
+            }
+        }
+
+        if self.config.hints {
+            for dir in [Up, Down, Left, Right] {
+                let next = dir.step_from(snake.head());
+                if dir != snake.get_direction().opposite() && self.term.cell_at(next) == Cell::plain(' ')
+                    && analysis::is_doomed(snake, dir, Some(apple), &self.board, HINT_LOOKAHEAD) {
+                    self.term.print_at(next, self.config.theme.danger);
+                    hints.push(next);
+                }
+            }
+        }
+
+        self.term.flush();
+    }
+
+    // With the ghost on, a faint arrow on the cell a drifting apple goes to next
+    fn update_drift_arrow(&mut self, snake: &Snake, apple: &Apple, arrow: &mut Option<(Coords, Cell)>) {
+        let (next, velocity) = match apple.velocity {
+            Some(_) if self.config.ghost => self.next_drift(snake, apple),
+            _ => return,
+        };
+        let cell = Cell::new(drift_arrow(velocity), self.config.theme.ghost.style);
+
+        if let Some((old, old_cell)) = arrow.take() {
+            // Unless something else already took its place
+            if self.term.cell_at(old) == old_cell {
+                self.term.print_at(old, Cell::plain(' '));
+            }
+        }
+
+        if next != apple.pos && self.term.cell_at(next) == Cell::plain(' ') {
+            self.term.print_at(next, cell);
+            *arrow = Some((next, cell));
+        }
+    }
+
+    // An arrow next to the head for the turn that's waiting on Enter, which
+    // follows the head around until then
+    fn update_turn_preview(&mut self, snake: &Snake, turn: Option<Direction>, preview: &mut Option<(Coords, Cell)>) {
+        let next = turn.filter(|dir| *dir != snake.get_direction().opposite())
+            .map(|dir| (dir.step_from(snake.head()), Cell::new(turn_arrow(dir), self.config.theme.ghost.style)));
+        if *preview == next {
+            return;
+        }
+
+        if let Some((old, old_cell)) = preview.take() {
+            // Unless something else already took its place
+            if self.term.cell_at(old) == old_cell {
+                self.term.print_at(old, Cell::plain(' '));
+            }
+        }
+
+        if let Some((pos, cell)) = next {
+            if self.term.cell_at(pos) == Cell::plain(' ') || self.term.cell_at(pos) == self.config.theme.ghost {
+                self.term.print_at(pos, cell);
+                *preview = next;
+            }
+        }
+
+        self.term.flush();
+    }
+
+    fn update_ghost(&mut self, snake: &Snake, dir_change: Option<Direction>, ghost: &mut Option<Coords>) {
+        // Where the head goes next, taking into account the turn that's about to happen
+        let dir = match dir_change {
+            Some(dir) if dir != snake.get_direction().opposite() => dir,
+            _ => snake.get_direction(),
+        };
+        let next = dir.step_from(snake.head());
+
+        if *ghost == Some(next) {
+            return;
+        }
+
+        if let Some(old) = ghost.take() {
+            // Unless something else already took its place
+            if self.term.cell_at(old) == self.config.theme.ghost {
+                self.term.print_at(old, Cell::plain(' '));
+            }
+        }
+
+        if self.term.cell_at(next) == Cell::plain(' ') {
+            self.term.print_at(next, self.config.theme.ghost);
+            *ghost = Some(next);
+        }
+
+        self.term.flush();
+    }
+
+    fn draw_puzzle_status(&mut self, snake: &Snake, eaten: u64) {
+        let level = match &self.config.puzzle {
+            Some(level) => level,
+            None => return,
+        };
+
+        // Padded so a shorter text doesn't leave leftovers behind
+        let moves = match level.move_limit {
+            Some(limit) => format!("{:>w$}/{}", snake.moves(), limit, w = limit.to_string().len()),
+            None => format!("{:<4}", snake.moves()),
+        };
+        let status = match level.apples.len() {
+            0 => format!(" Moves: {}  Apples: {} ", moves, eaten),
+            apples => format!(" Moves: {}  Apples: {}/{} ", moves, eaten, apples),
+        };
+        let origin = self.board.origin();
+
+        // Right below the level, or over its top wall if there's no room left
+        let below = origin.1 + level.height;
+        let y = if below < self.height {below} else {origin.1};
+        self.term.print_text_at((origin.0, y), &status, ContentStyle::new());
+        self.term.flush();
+    }
+
+    // Whether the controls are mirrored right now, and what to tell the player about it
+    fn mirror_state(&self, elapsed_secs: u64) -> (bool, Option<String>) {
+        match self.config.mirror {
+            Mirror::Off => (false, None),
+            Mirror::Always => (true, Some("Mirrored".into())),
+            Mirror::Toggle => {
+                let mirrored = (elapsed_secs / MIRROR_PERIOD_SECS) % 2 == 1;
+                let secs_left = MIRROR_PERIOD_SECS - elapsed_secs % MIRROR_PERIOD_SECS;
+                let status = if secs_left <= MIRROR_WARNING_SECS {
+                    Some(format!("{} in {}s", if mirrored {"Normal"} else {"Mirrored"}, secs_left))
+                } else if mirrored {
+                    Some("Mirrored".into())
+                } else {
+                    None
+                };
+                (mirrored, status)
+            },
+        }
+    }
+
+    // Over the bottom wall on the left, the mirror status takes the right
+    fn draw_best(&mut self, best: Option<u64>) {
+        if let Some(best) = best {
+            self.term.print_text_in(self.layout.bottom_bar, (0, 0), &format!(" Best: {} ", best), ContentStyle::new());
+            self.term.flush();
+        }
+    }
+
+    // Over the bottom wall, on the right so it stays clear of the puzzle status
+    fn draw_mirror_status(&mut self, status: Option<&str>) {
+        self.draw_board();
+        if let Some(status) = status {
+            let text = format!(" {} ", status);
+            let bar = self.layout.bottom_bar;
+            let x = bar.width.saturating_sub(text.len() as TermInt);
+            let style = ContentStyle::new().attribute(crossterm::style::Attribute::Reverse);
+            self.term.print_text_in(bar, (x, 0), &text, style);
+            self.term.flush();
+        }
+    }
+
+    fn draw_board(&mut self) {
+        if self.board.shape() == Shape::Rectangle {
+            self.term.draw_borders(Some((self.width, self.height)));
+            for pos in self.board.obstacles().to_vec() {
+                self.term.print_at(pos, self.config.theme.wall);
+            }
+            self.term.flush();
+        } else {
+            for pos in self.board.outline() {
+                self.term.print_at(pos, self.config.theme.wall);
+            }
+            self.term.flush();
+        }
+    }
+
+    fn head_cell(&self, snake: &Snake) -> Cell {
+        self.config.theme.head_cell(snake.get_direction())
+    }
+
+    fn toggle_boss_screen(&mut self) {
+        if !self.boss_screen {
+            // The game stays paused when coming back, so nobody gets caught off guard
+            if !self.paused {
+                self.toggle_pause();
+            }
+
+            let host = env::var("HOSTNAME").unwrap_or_else(|_| "localhost".into());
+            let prompt = format!("{}@{}:~", self.config.player_name, host);
+            self.term.show_boss_screen(&format!("{}$ ", prompt));
+            // Shells put the same in the title
+            self.term.set_title(&prompt);
+        } else {
+            self.term.hide_boss_screen();
+            self.update_title("Paused");
+        }
+
+        self.boss_screen = !self.boss_screen;
+    }
+
+    // Asked first, a stray key shouldn't throw a good run away. Below the pause box, which stays up.
+    fn confirm_restart(&mut self) -> bool {
+        let key = self.term.ask_at(Anchor::Bottom, &["Restart this game?", "", "Y to restart, any other key to go on"]);
+        if is_quit(&key) {
+            self.confirm_quit();
+            return false;
+        }
+
+        let restart = Action::of(&key) == Some(Action::Yes);
+        if restart {
+            self.paused = false;
+            self.term.hide_message();
+        }
+
+        restart
+    }
+
+    // Only returns if the player changed their mind
+    fn confirm_quit(&mut self) {
+        if !self.config.confirm_quit {
+            self.clean_exit();
+        }
+
+        let key = self.term.ask_at(Anchor::Bottom, &["Quit the game?", "", "Y, or the quit key again to quit,", "any other key to go on"]);
+        if matches!(Action::of(&key), Some(Action::Quit) | Some(Action::Yes)) {
+            self.clean_exit();
+        }
+    }
+
+    // Comes back paused, so nobody gets caught off guard
+    fn suspend(&mut self) {
+        if !self.paused {
+            self.toggle_pause();
+        }
+        self.term.suspend();
+    }
+
+    // Nobody's there to remind when the computer plays
+    fn break_due(&self) -> bool {
+        let playing = !self.config.screensaver && self.control.is_none() && !self.term.is_headless();
+        playing && self.config.break_reminder.is_some_and(|minutes| self.session_started.elapsed() >= Duration::from_secs(minutes * 60))
+    }
+
+    // Step mode waits for a key anyway, and so does nobody's game
+    fn idle_due(&self) -> bool {
+        let playing = !self.config.screensaver && self.control.is_none() && !self.term.is_headless() && !self.config.step_mode;
+        playing && self.config.idle_pause.is_some_and(|secs| self.term.idle_for() >= Duration::from_secs(secs))
+    }
+
+    // Not the usual pause screen, so whoever comes back knows why
+    fn idle_pause(&mut self) {
+        let secs = self.config.idle_pause.unwrap_or_default();
+        let line = format!("No key was pressed for {} second{}.", secs, if secs == 1 {""} else {"s"});
+        self.term.show_message(&["Paused on its own", &line, "", "Press Esc to resume,", "R to restart", "or Q to quit"]);
+        self.paused = true;
+        self.update_title("Paused");
+    }
+
+    // The game is left paused, so it's still waiting whenever they come back
+    fn remind_break(&mut self) {
+        self.toggle_pause();
+        let minutes = self.session_started.elapsed().as_secs() / 60;
+        let line = format!("You've been playing for {} minute{}.", minutes, if minutes == 1 {""} else {"s"});
+        self.term.ask_at(Anchor::Top, &["Take a break!", &line, "", "Press any key, the game stays paused"]);
+        self.session_started = Instant::now();
+    }
+
+    fn toggle_pause(&mut self) {
+        if !self.paused {
+            self.term.show_message(&PAUSE_LINES);
+        } else {
+            self.term.hide_message();
+        }
+
+        self.paused = !self.paused;
+        // When the help pauses, it's the pause box, and goes with it
+        if self.config.help_pauses {
+            self.help_shown = false;
+        }
+        self.update_title(if self.paused {"Paused"} else {"Playing"});
+    }
+
+    // Over the top of the board while playing on, or instead of the pause box
+    fn toggle_help(&mut self) {
+        let lines = self.key_help();
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        if !self.config.help_pauses {
+            if self.help_shown {
+                self.term.hide_message_at(Anchor::Top);
+            } else {
+                self.term.show_message_at(Anchor::Top, &lines);
+            }
+            self.help_shown = !self.help_shown;
+        } else if self.paused && self.help_shown {
+            self.toggle_pause();
+        } else {
+            if !self.paused {
+                self.toggle_pause();
+            }
+            self.term.show_message(&lines);
+            self.help_shown = true;
+        }
+    }
+
+    // Straight from the key bindings, only the ones this game would act on
+    fn key_help(&self) -> Vec<String> {
+        let two_player = self.mode().is_two_player();
+        let mut actions = vec![Action::Pause, Action::Restart, Action::Quit, Action::BossScreen];
+        if !two_player {
+            actions.push(Action::Hints);
+        }
+        if self.config.step_mode && !two_player {
+            actions.push(Action::Step);
+        }
+        // Co-op turns go straight through
+        if self.config.confirm_turns && !self.mode().needs_two() {
+            actions.push(Action::Confirm);
+        }
+        if self.lan.is_some() && !two_player {
+            actions.push(Action::Chat);
+        }
+        actions.extend([Action::Suspend, Action::Redraw, Action::Help]);
+
+        let width = actions.iter().map(|action| action.keys().len()).max().unwrap_or(0);
+        let mut lines = vec!["Keys".to_string(), self.controls_help().to_string()];
+        if self.config.sprint && !two_player {
+            lines.push("Shift with a direction to sprint".into());
+        }
+        lines.push(String::new());
+        // Messages center every line, so these are all padded to one width to keep the columns
+        let rows: Vec<String> = actions.iter().map(|action| format!("{:<w$}  {}", action.keys(), action.help(), w = width)).collect();
+        let row_width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        lines.extend(rows.iter().map(|row| format!("{:<w$}", row, w = row_width)));
+        lines
+    }
+
+    // For a game left running in another tab or window
+    fn update_title(&mut self, state: &str) {
+        let title = format!("Snake | {} | {}", state, self.title_score);
+        self.term.set_title(&title);
+    }
+}
+
+fn steer(dir: Direction, mirrored: bool) -> Direction {
+    if mirrored {dir.opposite()} else {dir}
+}
+
+fn is_quit(ev: &KeyEvent) -> bool {
+    Action::of(ev) == Some(Action::Quit)
+}
+
+fn turn_arrow(dir: Direction) -> char {
+    match dir {
+        Up => '↑',
+        Down => '↓',
+        Left => '←',
+        Right => '→',
+    }
+}
+
+// Rows go down the screen, so a positive y is heading down
+fn drift_arrow(velocity: Velocity) -> char {
+    match velocity {
+        (1, 1) => '↘',
+        (1, _) => '↗',
+        (_, 1) => '↙',
+        _ => '↖',
+    }
 }
@@ -0,0 +1,41 @@
+use crate::Coords;
+use crate::snake::Snake;
+
+// What it takes to win a round, checked every time an apple is eaten
+#[derive(Copy, Clone, PartialEq)]
+pub enum Goal {
+    NoApplesLeft,  // The board is full, or a puzzle's apples are all gone
+    Length(usize), // The snake gets this long, or runs out of apples trying
+    Apples(u64),   // That many apples eaten, or none left to eat
+}
+
+impl Goal {
+    // Right after eating, with the apple that comes next if there's one and
+    // that one counted in the apples eaten
+    pub fn is_reached(&self, snake: &Snake, next_apple: Option<Coords>, eaten: u64) -> bool {
+        match self {
+            Goal::NoApplesLeft => next_apple.is_none(),
+            Goal::Length(target) => next_apple.is_none() || length_after_growing(snake) >= *target,
+            Goal::Apples(target) => next_apple.is_none() || eaten >= *target,
+        }
+    }
+
+    pub fn target_length(&self) -> Option<usize> {
+        match self {
+            Goal::Length(target) => Some(*target),
+            _ => None,
+        }
+    }
+
+    pub fn target_apples(&self) -> Option<u64> {
+        match self {
+            Goal::Apples(target) => Some(*target),
+            _ => None,
+        }
+    }
+}
+
+// An apple only makes the snake longer over its next steps
+pub fn length_after_growing(snake: &Snake) -> usize {
+    snake.body().len() + snake.growth_left() as usize
+}
@@ -0,0 +1,23 @@
+// How many segments an apple is worth: a fixed number each, classic snake
+// being 1, plus one more for every so many points scored if it ramps up
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Growth {
+    pub per_apple: u16,
+    pub ramp: Option<u16>, // Points for every extra segment
+}
+
+impl Growth {
+    pub const CLASSIC: Growth = Growth { per_apple: 1, ramp: None };
+
+    // With the score as it is once the apple is counted
+    pub fn segments(&self, score: u64) -> u32 {
+        let extra = self.ramp.map_or(0, |points| score / points as u64);
+        (self.per_apple as u64 + extra).min(u32::MAX as u64) as u32
+    }
+}
+
+impl Default for Growth {
+    fn default() -> Self {
+        Growth::CLASSIC
+    }
+}
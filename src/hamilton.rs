@@ -0,0 +1,122 @@
+use crate::{ai, Coords, TermInt};
+use crate::board::Board;
+use crate::snake::{Snake, Direction::{self, *}};
+
+const DIRECTIONS: [Direction; 4] = [Up, Down, Left, Right];
+
+// A closed path through every playable cell. A snake that keeps to it can
+// never crash, and going around it fills the whole board sooner or later.
+// Moves that skip ahead along it are taken while the snake is short, as long
+// as they land before the tail and not past the apple.
+pub struct Cycle {
+    width: TermInt,
+    order: Vec<Option<u32>>, // Where each cell comes along the cycle, row by row
+    len: u32,
+}
+
+impl Cycle {
+    // Only for fully playable rectangles with an even number of cells, any
+    // other shape may well not have a cycle at all
+    pub fn new(board: &Board) -> Option<Self> {
+        let positions = board.positions();
+        let min_x = positions.iter().map(|pos| pos.0).min()?;
+        let max_x = positions.iter().map(|pos| pos.0).max()?;
+        let min_y = positions.iter().map(|pos| pos.1).min()?;
+        let max_y = positions.iter().map(|pos| pos.1).max()?;
+        let (w, h) = (max_x - min_x + 1, max_y - min_y + 1);
+        if positions.len() != w as usize * h as usize || !positions.len().is_multiple_of(2) || w < 2 || h < 2 {
+            return None;
+        }
+
+        // Laid out with an even number of rows, turned sideways if that takes it
+        let path = if h.is_multiple_of(2) {
+            rows_path(w, h)
+        } else {
+            rows_path(h, w).into_iter().map(|(x, y)| (y, x)).collect()
+        };
+
+        let (width, height) = board.size();
+        let mut order = vec![None; width as usize * height as usize];
+        for (i, (x, y)) in path.iter().enumerate() {
+            order[(min_y + y) as usize * width as usize + (min_x + x) as usize] = Some(i as u32);
+        }
+
+        Some(Cycle { width, order, len: path.len() as u32 })
+    }
+
+    pub fn next_direction(&self, snake: &Snake, apple: Coords, board: &Board) -> Direction {
+        let head = snake.head();
+        let along = match self.successor(head) {
+            Some(dir) => dir,
+            None => return ai::next_direction(snake, apple, board),
+        };
+
+        // Freshly spawned snakes aren't lined up along the cycle yet, that takes
+        // following it for as many steps as the snake is long
+        if !self.is_in_order(snake.body()) {
+            return if snake.would_crash(along, board) {ai::next_direction(snake, apple, board)} else {along};
+        }
+
+        // Past half the board there's no room to spare for shortcuts
+        if snake.body().len() * 2 >= self.len as usize {
+            return along;
+        }
+
+        let (head_i, tail_i) = (self.at(head).unwrap(), self.at(snake.body()[0]).unwrap());
+        let to_tail = self.distance(head_i, tail_i);
+        let to_apple = self.at(apple).map_or(to_tail, |apple_i| self.distance(head_i, apple_i));
+
+        DIRECTIONS.iter()
+            .filter(|dir| **dir != snake.get_direction().opposite() && !snake.would_crash(**dir, board))
+            .filter_map(|dir| Some((*dir, self.distance(head_i, self.at(dir.step_from(head))?))))
+            .filter(|(_, ahead)| *ahead < to_tail && *ahead <= to_apple)
+            .max_by_key(|(_, ahead)| *ahead)
+            .map_or(along, |(dir, _)| dir)
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn at(&self, pos: Coords) -> Option<u32> {
+        if pos.0 >= self.width {
+            return None;
+        }
+        self.order.get(pos.1 as usize * self.width as usize + pos.0 as usize).copied().flatten()
+    }
+
+    // Steps forward along the cycle to get from one cell to the other
+    fn distance(&self, from: u32, to: u32) -> u32 {
+        (to + self.len - from) % self.len
+    }
+
+    fn successor(&self, pos: Coords) -> Option<Direction> {
+        let next = (self.at(pos)? + 1) % self.len;
+        DIRECTIONS.iter().copied().find(|dir| self.at(dir.step_from(pos)) == Some(next))
+    }
+
+    // Tail to head, the body only ever goes forward and never wraps around the cycle
+    fn is_in_order(&self, body: &[Coords]) -> bool {
+        let order: Option<Vec<u32>> = body.iter().map(|pos| self.at(*pos)).collect();
+        match order {
+            Some(order) => {
+                let span: u32 = order.windows(2).map(|pair| self.distance(pair[0], pair[1])).sum();
+                span == self.distance(order[0], *order.last().unwrap())
+            },
+            None => false,
+        }
+    }
+}
+
+// Right along the top row, back and forth over the rest of the columns row by
+// row, then up the first column to the start. Needs an even number of rows.
+fn rows_path(w: TermInt, h: TermInt) -> Vec<Coords> {
+    let mut path: Vec<Coords> = (0..w).map(|x| (x, 0)).collect();
+    for y in 1..h {
+        if y % 2 == 1 {
+            path.extend((1..w).rev().map(|x| (x, y)));
+        } else {
+            path.extend((1..w).map(|x| (x, y)));
+        }
+    }
+    path.extend((1..h).rev().map(|y| (0, y)));
+    path
+}
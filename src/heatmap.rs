@@ -0,0 +1,45 @@
+use crate::{Coords, TermInt};
+
+const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+// How many times the snake's head went through every cell of the board
+pub struct Heatmap {
+    width: TermInt,
+    visits: Vec<u32>,
+}
+
+impl Heatmap {
+    pub fn new(width: TermInt, height: TermInt) -> Self {
+        Heatmap { width, visits: vec![0; width as usize * height as usize] }
+    }
+
+    pub fn record(&mut self, pos: Coords) {
+        let i = self.index(pos);
+        self.visits[i] += 1;
+    }
+
+    // Shaded glyph depending on how often a cell was visited compared to the busiest one
+    pub fn shade(&self, pos: Coords) -> char {
+        let max = *self.visits.iter().max().unwrap_or(&0);
+        let count = self.visits[self.index(pos)];
+
+        if count == 0 {
+            return SHADES[0];
+        }
+
+        let levels = (SHADES.len() - 1) as u32;
+        SHADES[(count * levels).div_ceil(max) as usize]
+    }
+
+    // From 0 for never to 1 for the busiest cell
+    pub fn heat(&self, pos: Coords) -> f64 {
+        let max = *self.visits.iter().max().unwrap_or(&0);
+        if max == 0 {0.0} else {self.visits[self.index(pos)] as f64 / max as f64}
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn index(&self, pos: Coords) -> usize {
+        self.width as usize * pos.1 as usize + pos.0 as usize
+    }
+}
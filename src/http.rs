@@ -0,0 +1,22 @@
+use std::process::Command;
+use std::time::Duration;
+
+// There's no TLS in our dependencies and pulling a whole stack in for the odd
+// request isn't worth it, so HTTPS goes through curl, which about every system
+// has. Anything going wrong comes back as an error to show, never a hang.
+pub fn get(url: &str, timeout: Duration) -> Result<String, String> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location"])
+        .args(["--max-time", &timeout.as_secs().max(1).to_string()])
+        .args(["--user-agent", concat!("snake/", env!("CARGO_PKG_VERSION"))])
+        .arg(url)
+        .output()
+        .map_err(|e| format!("couldn't run curl: {}", e))?;
+
+    if !output.status.success() {
+        let msg = String::from_utf8_lossy(&output.stderr).trim().trim_start_matches("curl: ").to_string();
+        return Err(if msg.is_empty() {format!("curl failed with {}", output.status)} else {msg});
+    }
+
+    String::from_utf8(output.stdout).map_err(|_| "the response isn't valid text".into())
+}
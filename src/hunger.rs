@@ -0,0 +1,37 @@
+// Going hungry: a snake that hasn't eaten in a while loses a segment off its
+// tail every so many steps, until it eats again or there's nothing left of it
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Hunger {
+    every: u16,
+    steps_left: u16,
+}
+
+impl Hunger {
+    pub fn new(every: u16) -> Self {
+        assert!(every > 0, "Error: hunger needs at least one step between segments");
+        Hunger { every, steps_left: every }
+    }
+
+    pub fn every(&self) -> u16 {
+        self.every
+    }
+
+    pub fn feed(&mut self) {
+        self.steps_left = self.every;
+    }
+
+    // Once for every step without eating, true when a segment is due
+    pub fn tick(&mut self) -> bool {
+        self.steps_left -= 1;
+        if self.steps_left == 0 {
+            self.steps_left = self.every;
+            return true;
+        }
+        false
+    }
+
+    // From 1 right after eating down to almost 0 just before losing a segment
+    pub fn energy(&self) -> f64 {
+        self.steps_left as f64 / self.every as f64
+    }
+}
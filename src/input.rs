@@ -0,0 +1,181 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+// More characters than anyone types between two reads of the keys
+const PASTE_BURST: usize = 4;
+
+// What a key means besides steering, so that every screen agrees on it.
+// Each screen only acts on the ones that make sense there.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Action {
+    Quit, // CTRL+C, or Q for terminals where that's awkward
+    Suspend,
+    Redraw,
+    Pause,
+    Restart,
+    BossScreen,
+    Step,
+    Replay,
+    Hints,
+    Menu,
+    Yes,
+    Confirm,
+    Help,
+    Chat,
+}
+
+// Every key that means something, in the order the help lists them. Held
+// with CTRL these come first, and anything else with CTRL counts as the plain key.
+const CTRL_KEYS: [(char, Action); 3] = [('c', Action::Quit), ('z', Action::Suspend), ('l', Action::Redraw)];
+const KEYS: [(KeyCode, Action); 14] = [
+    (KeyCode::Char('q'), Action::Quit), (KeyCode::Esc, Action::Pause), (KeyCode::Char('r'), Action::Restart),
+    (KeyCode::Char('b'), Action::BossScreen), (KeyCode::Char(' '), Action::Step), (KeyCode::Char('v'), Action::Replay),
+    (KeyCode::Char('h'), Action::Hints), (KeyCode::Char('m'), Action::Menu), (KeyCode::Char('y'), Action::Yes),
+    (KeyCode::Char('Y'), Action::Yes), (KeyCode::Enter, Action::Confirm), (KeyCode::Char('?'), Action::Help),
+    (KeyCode::F(1), Action::Help), (KeyCode::Char('t'), Action::Chat),
+];
+
+impl Action {
+    pub fn of(key: &KeyEvent) -> Option<Self> {
+        if key.modifiers == KeyModifiers::CONTROL {
+            let ctrl = CTRL_KEYS.iter().find(|(c, _)| key.code == KeyCode::Char(*c));
+            if let Some((_, action)) = ctrl {
+                return Some(*action);
+            }
+        }
+
+        KEYS.iter().find(|(code, _)| *code == key.code).map(|(_, action)| *action)
+    }
+
+    // What to press for it, as the help shows it
+    pub fn keys(&self) -> String {
+        let ctrl = CTRL_KEYS.iter().filter(|(_, action)| action == self).map(|(c, _)| format!("Ctrl+{}", c.to_ascii_uppercase()));
+        let plain = KEYS.iter().filter(|(_, action)| action == self).map(|(code, _)| key_name(*code));
+        let mut names: Vec<String> = ctrl.chain(plain).collect();
+        names.dedup();
+        names.join("/")
+    }
+
+    pub fn help(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Suspend => "Back to the shell for a while",
+            Action::Redraw => "Redraw the screen",
+            Action::Pause => "Pause or resume",
+            Action::Restart => "Restart",
+            Action::BossScreen => "Boss screen",
+            Action::Step => "Take a step",
+            Action::Replay => "Watch the replay",
+            Action::Hints => "Hints on or off",
+            Action::Menu => "Back to the menu",
+            Action::Yes => "Yes",
+            Action::Confirm => "Make the chosen turn",
+            Action::Help => "This help",
+            Action::Chat => "Say something to the LAN",
+        }
+    }
+}
+
+// Drops the keys nobody pressed: pasted text, which arrives all at once, and
+// what's left of escape sequences that reached the terminal in pieces, which
+// come out as Esc followed by plain characters. That Esc isn't a pause.
+pub fn without_noise(keys: Vec<KeyEvent>) -> Vec<KeyEvent> {
+    let mut kept = Vec::with_capacity(keys.len());
+    let mut rest = keys.into_iter().peekable();
+    while let Some(key) = rest.next() {
+        let opener = match (key.code, rest.peek().map(|next| next.code)) {
+            (KeyCode::Esc, Some(KeyCode::Char(c @ '['))) | (KeyCode::Esc, Some(KeyCode::Char(c @ 'O'))) => c,
+            _ => {
+                kept.push(key);
+                continue;
+            },
+        };
+
+        rest.next();
+        // CSI parameters and intermediates, SS3 goes straight to the final character
+        if opener == '[' {
+            while rest.next_if(|next| matches!(next.code, KeyCode::Char(' '..='?'))).is_some() {}
+        }
+        rest.next_if(|next| matches!(next.code, KeyCode::Char('@'..='~')));
+    }
+
+    if kept.iter().filter(|key| matches!(key.code, KeyCode::Char(_))).count() > PASTE_BURST {
+        kept.retain(|key| !matches!(key.code, KeyCode::Char(_)));
+    }
+    kept
+}
+
+fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".into(),
+        KeyCode::Char(c) => c.to_ascii_uppercase().to_string(),
+        KeyCode::Esc => "Esc".into(),
+        KeyCode::Enter => "Enter".into(),
+        KeyCode::F(n) => format!("F{}", n),
+        _ => "?".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(codes: &[KeyCode]) -> Vec<KeyEvent> {
+        codes.iter().map(|code| KeyEvent::new(*code, KeyModifiers::NONE)).collect()
+    }
+
+    fn chars(text: &str) -> Vec<KeyCode> {
+        text.chars().map(KeyCode::Char).collect()
+    }
+
+    #[test]
+    fn paste_over_the_burst_is_dropped() {
+        let pasted = keys(&chars("wasdw"));
+        assert!(without_noise(pasted).is_empty());
+    }
+
+    #[test]
+    fn burst_at_the_threshold_is_kept() {
+        let typed = keys(&chars("wasd"));
+        assert_eq!(without_noise(typed.clone()), typed);
+    }
+
+    #[test]
+    fn paste_keeps_the_keys_that_arent_characters() {
+        let mut codes = chars("hello");
+        codes.extend([KeyCode::Up, KeyCode::Enter]);
+        assert_eq!(without_noise(keys(&codes)), keys(&[KeyCode::Up, KeyCode::Enter]));
+    }
+
+    #[test]
+    fn split_escape_sequence_is_not_esc() {
+        // Up as CSI, shift+right with parameters, and F1 as SS3
+        let mut codes = vec![KeyCode::Esc];
+        codes.extend(chars("[A"));
+        codes.push(KeyCode::Esc);
+        codes.extend(chars("[1;2C"));
+        codes.push(KeyCode::Esc);
+        codes.extend(chars("OP"));
+        assert!(without_noise(keys(&codes)).is_empty());
+    }
+
+    #[test]
+    fn split_escape_sequence_keeps_what_comes_after() {
+        let mut codes = vec![KeyCode::Esc];
+        codes.extend(chars("[Bw"));
+        assert_eq!(without_noise(keys(&codes)), keys(&chars("w")));
+    }
+
+    #[test]
+    fn plain_esc_is_kept() {
+        assert_eq!(without_noise(keys(&[KeyCode::Esc])), keys(&[KeyCode::Esc]));
+        let codes = [KeyCode::Esc, KeyCode::Char('w')];
+        assert_eq!(without_noise(keys(&codes)), keys(&codes));
+    }
+
+    #[test]
+    fn modified_arrows_are_no_action() {
+        for modifiers in [KeyModifiers::SHIFT, KeyModifiers::CONTROL, KeyModifiers::ALT] {
+            assert!(Action::of(&KeyEvent::new(KeyCode::Up, modifiers)).is_none());
+        }
+    }
+}
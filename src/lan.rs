@@ -0,0 +1,177 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::UdpSocket;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Every player broadcasts its own score once per second, and everyone listening
+// on the port builds the leaderboard from that. Players that go silent for a
+// while are dropped, so there's no need for an explicit join/leave handshake.
+// Chat lines go out the same way, once, whenever someone says something.
+const PORT: u16 = 47474;
+const PROTOCOL_TAG: &str = "SNAKE1";
+const CHAT_TAG: &str = "SNAKECHAT1";
+const CHAT_KEPT: usize = 20;
+pub const CHAT_LENGTH: usize = 60;
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Lan {
+    state: Arc<Mutex<LanState>>,
+    socket: UdpSocket, // The same one the network thread listens on
+}
+
+pub struct Entry {
+    pub name: String,
+    pub score: u64,
+    pub best: u64,
+    pub is_local: bool,
+}
+
+#[derive(Clone)]
+pub struct ChatLine {
+    pub name: String,
+    pub text: String,
+}
+
+struct LanState {
+    id: u64,
+    name: String,
+    score: u64,
+    best: u64,
+    peers: HashMap<u64, Peer>,
+    chat: VecDeque<ChatLine>, // Oldest first, including our own
+}
+
+enum Message {
+    Announce(u64, Peer),
+    Chat(u64, ChatLine),
+}
+
+struct Peer {
+    name: String,
+    score: u64,
+    best: u64,
+    last_seen: Instant,
+}
+
+impl Lan {
+    pub fn start(name: &str) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", PORT))?;
+        socket.set_broadcast(true)?;
+        socket.set_read_timeout(Some(Duration::from_millis(250)))?;
+
+        let state = LanState { id: rand::random(), name: sanitize(name), score: 0, best: 0, peers: HashMap::new(), chat: VecDeque::new() };
+        let state = Arc::new(Mutex::new(state));
+        let thread_state = Arc::clone(&state);
+        let thread_socket = socket.try_clone()?;
+        thread::spawn(move || network_loop(thread_socket, thread_state));
+
+        Ok(Lan { state, socket })
+    }
+
+    // Whoever's listening right now gets it, there's no catching up later
+    pub fn say(&self, text: &str) {
+        let text = printable(text, CHAT_LENGTH);
+        let msg = {
+            let mut state = self.state.lock().unwrap();
+            let line = ChatLine { name: state.name.clone(), text: text.clone() };
+            state.add_chat(line);
+            format!("{} {} {}\t{}", CHAT_TAG, state.id, state.name, text)
+        };
+        self.socket.send_to(msg.as_bytes(), ("255.255.255.255", PORT)).ok();
+    }
+
+    pub fn chat(&self) -> Vec<ChatLine> {
+        self.state.lock().unwrap().chat.iter().cloned().collect()
+    }
+
+    pub fn set_score(&self, score: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.score = score;
+        state.best = state.best.max(score);
+    }
+
+    pub fn leaderboard(&self) -> Vec<Entry> {
+        let state = self.state.lock().unwrap();
+        let mut entries: Vec<Entry> = state.peers.values()
+            .filter(|peer| peer.last_seen.elapsed() < PEER_TIMEOUT)
+            .map(|peer| Entry { name: peer.name.clone(), score: peer.score, best: peer.best, is_local: false })
+            .collect();
+
+        entries.push(Entry { name: state.name.clone(), score: state.score, best: state.best, is_local: true });
+        entries.sort_by(|a, b| b.best.cmp(&a.best).then(b.score.cmp(&a.score)));
+        entries
+    }
+}
+
+fn network_loop(socket: UdpSocket, state: Arc<Mutex<LanState>>) {
+    let mut buf = [0; 512];
+    let mut last_announce: Option<Instant> = None;
+
+    loop {
+        if last_announce.is_none_or(|t| t.elapsed() >= ANNOUNCE_INTERVAL) {
+            let msg = {
+                let state = state.lock().unwrap();
+                format!("{} {} {} {} {}", PROTOCOL_TAG, state.id, state.score, state.best, state.name)
+            };
+
+            // Networking is best-effort, nothing we can do about a dropped packet
+            socket.send_to(msg.as_bytes(), ("255.255.255.255", PORT)).ok();
+            last_announce = Some(Instant::now());
+        }
+
+        // Times out regularly so we get to announce ourselves even if nobody else talks
+        if let Ok((len, _)) = socket.recv_from(&mut buf) {
+            let mut state = state.lock().unwrap();
+            match parse_message(&buf[..len]) {
+                Some(Message::Announce(id, peer)) if id != state.id => {
+                    state.peers.insert(id, peer);
+                },
+                Some(Message::Chat(id, line)) if id != state.id => state.add_chat(line),
+                _ => {},
+            }
+        }
+    }
+}
+
+impl LanState {
+    fn add_chat(&mut self, line: ChatLine) {
+        self.chat.push_back(line);
+        if self.chat.len() > CHAT_KEPT {
+            self.chat.pop_front();
+        }
+    }
+}
+
+fn parse_message(data: &[u8]) -> Option<Message> {
+    let msg = std::str::from_utf8(data).ok()?;
+    let (tag, rest) = msg.split_once(' ')?;
+
+    match tag {
+        PROTOCOL_TAG => {
+            let mut parts = rest.splitn(4, ' ');
+            let id = parts.next()?.parse().ok()?;
+            let score = parts.next()?.parse().ok()?;
+            let best = parts.next()?.parse().ok()?;
+            let name = sanitize(parts.next()?);
+            Some(Message::Announce(id, Peer { name, score, best, last_seen: Instant::now() }))
+        },
+        // Names can't have tabs in them, so that's where the text starts
+        CHAT_TAG => {
+            let (id, rest) = rest.split_once(' ')?;
+            let (name, text) = rest.split_once('\t')?;
+            Some(Message::Chat(id.parse().ok()?, ChatLine { name: sanitize(name), text: printable(text, CHAT_LENGTH) }))
+        },
+        _ => None,
+    }
+}
+
+fn sanitize(name: &str) -> String {
+    // Names end up drawn on the terminal, keep them short and printable
+    printable(name, 10)
+}
+
+fn printable(text: &str, max_len: usize) -> String {
+    text.chars().filter(|c| !c.is_control()).take(max_len).collect()
+}
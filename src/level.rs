@@ -0,0 +1,214 @@
+use std::{fs, path::Path};
+
+use crate::{storage, Coords, TermInt};
+use crate::snake::Direction::{self, *};
+
+pub const MAX_APPLES: usize = 35;
+const DEFAULT_LENGTH: i16 = 4;
+
+// Levels are plain text files: a few optional `key: value` lines followed by
+// the map itself, drawn with the following characters:
+//
+//   # + - |   wall
+//   space .   free cell
+//   @         snake head
+//   1-9, a-z  apples, eaten in that order
+//
+// Supported keys are `moves` (move limit), `length` (initial snake length),
+// `target` (the length that wins, before the apples run out) and `direction`
+// (up, down, left or right). Anything past the drawn map is wall.
+pub struct Level {
+    pub width: TermInt,
+    pub height: TermInt,
+    pub spawn: Option<Coords>,
+    pub direction: Direction,
+    pub length: i16,
+    pub apples: Vec<Coords>,
+    pub move_limit: Option<u64>,
+    pub target_length: Option<usize>,
+    pub name: Option<String>, // The file's, without the extension
+    walls: Vec<bool>,
+}
+
+impl Level {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Error reading {}: {}", path, e))?;
+        let mut level = Self::parse(&text).map_err(|e| format!("Error in {}: {}", path, e))?;
+        level.name = Path::new(path).file_stem().map(|stem| stem.to_string_lossy().into_owned());
+        Ok(level)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().peekable();
+        let mut direction = Right;
+        let mut length = DEFAULT_LENGTH;
+        let mut move_limit = None;
+        let mut target_length = None;
+
+        while let Some((key, value)) = lines.peek().and_then(|line| header_entry(line)) {
+            match key {
+                "moves" => move_limit = Some(value.parse().map_err(|_| "invalid move limit")?),
+                "target" => target_length = Some(value.parse().map_err(|_| "invalid target length")?),
+                "length" => length = value.parse().ok().filter(|l| *l > 0).ok_or("invalid snake length")?,
+                "direction" => direction = match value {
+                    "up" => Up,
+                    "down" => Down,
+                    "left" => Left,
+                    "right" => Right,
+                    _ => return Err("invalid direction".into()),
+                },
+                _ => return Err(format!("unknown setting {}", key)),
+            }
+            lines.next();
+        }
+
+        let map: Vec<Vec<char>> = lines.map(|line| line.chars().collect()).collect();
+        let width = map.iter().map(|row| row.len()).max().unwrap_or(0);
+        let height = map.len();
+
+        if width == 0 || height == 0 {
+            return Err("the map is empty".into());
+        }
+
+        let mut walls = vec![true; width * height];
+        let mut spawn = None;
+        let mut numbered_apples = vec![];
+
+        for (y, row) in map.iter().enumerate() {
+            for (x, ch) in row.iter().enumerate() {
+                let pos = (x as TermInt, y as TermInt);
+                match ch {
+                    '#' | '+' | '-' | '|' => continue,
+                    ' ' | '.' => {},
+                    '@' => spawn = Some(pos),
+                    c if (c.is_ascii_digit() && *c != '0') || c.is_ascii_lowercase() => {
+                        numbered_apples.push((c.to_digit(36).unwrap(), pos));
+                    },
+                    c => return Err(format!("unexpected character '{}' in the map", c)),
+                }
+                walls[width * y + x] = false;
+            }
+        }
+
+        if target_length.is_some_and(|target| target <= length as usize) {
+            return Err("the target length has to be longer than the snake".into());
+        }
+
+        numbered_apples.sort_by_key(|(order, _)| *order);
+        let apples = numbered_apples.into_iter().map(|(_, pos)| pos).collect();
+
+        Ok(Level {
+            width: width as TermInt, height: height as TermInt, spawn, direction, length, apples, move_limit, target_length, name: None, walls
+        })
+    }
+
+    // A blank level with nothing but walls around it
+    pub fn empty(width: TermInt, height: TermInt) -> Self {
+        let walls = (0..height).flat_map(|y| (0..width).map(move |x| {
+            x == 0 || y == 0 || x == width - 1 || y == height - 1
+        })).collect();
+
+        Level { width, height, spawn: None, direction: Right, length: DEFAULT_LENGTH, apples: vec![], move_limit: None, target_length: None, name: None, walls }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        storage::write_replacing(Path::new(path), self.to_text()).map_err(|e| format!("Error writing {}: {}", path, e))
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        if let Some(limit) = self.move_limit {
+            text.push_str(&format!("moves: {}\n", limit));
+        }
+        if self.length != DEFAULT_LENGTH {
+            text.push_str(&format!("length: {}\n", self.length));
+        }
+        if let Some(target) = self.target_length {
+            text.push_str(&format!("target: {}\n", target));
+        }
+        if self.direction != Right {
+            text.push_str(&format!("direction: {}\n", self.direction.name()));
+        }
+
+        for y in 0..self.height {
+            let row: String = (0..self.width).map(|x| self.char_at((x, y))).collect();
+            text.push_str(row.trim_end());
+            text.push('\n');
+        }
+
+        text
+    }
+
+    // What the cell looks like in the level file
+    pub fn char_at(&self, pos: Coords) -> char {
+        if self.spawn == Some(pos) {
+            '@'
+        } else if let Some(i) = self.apples.iter().position(|apple| *apple == pos) {
+            std::char::from_digit(i as u32 + 1, 36).unwrap()
+        } else if self.is_wall(pos) {
+            '#'
+        } else {
+            ' '
+        }
+    }
+
+    // Shrunk to fit in so many characters, each one two cells high with a
+    // block for each half that's mostly wall
+    pub fn thumbnail(&self, width: usize, height: usize) -> Vec<String> {
+        let cols = (self.width as usize).div_ceil(width.max(1)).max(1);
+        let rows = (self.height as usize).div_ceil(height.max(1) * 2).max(1);
+        let mostly_wall = |x: usize, y: usize| {
+            let cells: Vec<bool> = (y..y + rows).flat_map(|y| (x..x + cols).map(move |x| (x, y)))
+                .filter(|(x, y)| *x < self.width as usize && *y < self.height as usize)
+                .map(|(x, y)| self.is_wall((x as TermInt, y as TermInt)))
+                .collect();
+            !cells.is_empty() && cells.iter().filter(|wall| **wall).count() * 2 >= cells.len()
+        };
+
+        (0..self.height as usize).step_by(rows * 2).map(|y| {
+            (0..self.width as usize).step_by(cols).map(|x| match (mostly_wall(x, y), mostly_wall(x, y + rows)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            }).collect()
+        }).collect()
+    }
+
+    pub fn is_wall(&self, pos: Coords) -> bool {
+        pos.0 >= self.width || pos.1 >= self.height || self.walls[self.index(pos)]
+    }
+
+    // Walls, the spawn point and apples all take up the whole cell
+    pub fn set_wall(&mut self, pos: Coords, wall: bool) {
+        self.clear(pos);
+        let i = self.index(pos);
+        self.walls[i] = wall;
+    }
+
+    pub fn clear(&mut self, pos: Coords) {
+        let i = self.index(pos);
+        self.walls[i] = false;
+        self.apples.retain(|apple| *apple != pos);
+        if self.spawn == Some(pos) {
+            self.spawn = None;
+        }
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn index(&self, pos: Coords) -> usize {
+        self.width as usize * pos.1 as usize + pos.0 as usize
+    }
+}
+
+fn header_entry(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(':')?;
+    let key = key.trim();
+
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_lowercase()) {
+        return None;
+    }
+
+    Some((key, value.trim()))
+}
@@ -0,0 +1,42 @@
+// The game engine, free of any terminal code, so that bots and training
+// loops can drive it directly. The binary builds the actual game on top.
+pub mod ai;
+pub mod analysis;
+pub mod board;
+pub mod drift;
+pub mod env;
+pub mod goal;
+pub mod growth;
+pub mod hamilton;
+pub mod hunger;
+pub mod level;
+pub mod pack;
+pub mod recording;
+pub mod replay;
+pub mod rollback;
+pub mod run_code;
+pub mod sim;
+pub mod snake;
+pub mod storage;
+pub mod verify;
+pub mod widget;
+
+pub type TermInt = u16;
+pub type Coords = (u16, u16);
+
+// What's kept working from one release to the next, for depending on the
+// engine without following every change. The rest of the modules are public
+// too, but may still change shape between 0.x versions.
+//
+// The engine is SnakeEnv, there's no Engine type besides it. What happens on
+// each step comes back from SnakeEnv::step as an Observation, a reward and
+// whether it's over, and from Snake::move_step as a MoveResult, so there's no
+// GameEvent either: the JSON events are the game's, not the engine's. Config
+// is the game's options, terminal and all, the engine takes its rules as a
+// Goal, a Growth and a Hunger with SnakeEnv's setters instead.
+pub use board::{Board, Shape};
+pub use env::{Observation, SnakeEnv, Snapshot};
+pub use goal::Goal;
+pub use growth::Growth;
+pub use hunger::Hunger;
+pub use snake::{Direction, MoveResult, Snake};
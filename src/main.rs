@@ -1,17 +1,110 @@
+mod achievements;
+mod caps;
+mod chaos;
+mod cli;
+mod config;
+mod control;
+mod controls;
+#[cfg(feature = "discord")]
+mod discord;
+mod editor;
+mod events;
 mod game;
+mod heatmap;
+mod http;
+mod input;
+mod json;
+mod lan;
+mod menu;
+mod metrics;
+mod paths;
+mod recent;
+mod pip;
+mod scores;
 mod term;
-mod snake;
+mod speed;
+mod summary;
+mod theme;
+mod tutorial;
+mod update;
+mod viewer;
 
-pub type TermInt = u16;
-pub type Coords = (u16, u16);
+use ::snake::{ai, analysis, board, drift, env, goal, growth, hamilton, hunger, level, pack, recording, replay, run_code, sim, snake, storage, verify, Coords, TermInt};
+
+const SIMULATION_SIZE: Coords = (40, 20);
 
 fn main() {
-    let mut game = game::SnakeGame::new();
+    let config = cli::parse();
+    if let Some(command) = config.scores_command {
+        if let Err(e) = scores::run_command(command, config.profile.as_deref()) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if config.check_update {
+        match update::check() {
+            Ok(msg) => println!("{}", msg),
+            Err(e) => {
+                eprintln!("Error checking for updates: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(path) = config.verify {
+        match std::fs::read(&path).map_err(|e| e.to_string()).and_then(|bytes| verify::verify_bytes(&bytes)) {
+            Ok(run) => println!("{}: valid, {} points from {} apples in {} steps", path, run.score, run.apples, run.steps),
+            Err(e) => {
+                eprintln!("{}: invalid, {}", path, e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(path) = config.watch {
+        if let Err(e) = viewer::watch_file(&path, &config.theme, config.renderer, config.glyphs) {
+            eprintln!("Error watching {}: {}", path, e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(path) = config.edit {
+        editor::Editor::new(path, config.renderer, config.glyphs).run();
+        return;
+    }
+
+    if let Some(games) = config.simulate {
+        simulate(games, config.strategy, config.growth);
+        return;
+    }
+
+    let screensaver = config.screensaver;
+    let mut game = game::SnakeGame::new(config);
     game.initialize();
-    game.show_intro();
+
+    if !screensaver {
+        game.show_intro();
+    }
 
     loop {
         // The main game loop takes care of exiting cleanly on CTRL+C
         game.play();
     }
 }
+
+fn simulate(games: u64, strategy: Option<sim::Strategy>, growth: growth::Growth) {
+    let strategies = strategy.map_or(sim::Strategy::ALL.to_vec(), |s| vec![s]);
+    println!("{} games on a {}x{} board", games, SIMULATION_SIZE.0, SIMULATION_SIZE.1);
+    println!("{:<12}{:>10}{:>8}{:>12}{:>8}", "strategy", "avg score", "best", "avg steps", "wins");
+
+    for strategy in strategies {
+        let stats = sim::run(strategy, games, SIMULATION_SIZE.0, SIMULATION_SIZE.1, growth);
+        println!("{:<12}{:>10.1}{:>8}{:>12.1}{:>8}",
+            strategy.name(), stats.mean_score(), stats.best_score, stats.mean_steps(), stats.full_boards);
+    }
+}
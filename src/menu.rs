@@ -0,0 +1,229 @@
+use crate::achievements::{self, Achievements};
+use crate::board::Shape;
+use crate::config::{name_of, Config, Mirror, Mode};
+use crate::controls::{ControlScheme, KeyPreset};
+use crate::input::Action;
+use crate::pack;
+use crate::recent::Recent;
+use crate::scores::Scores;
+use crate::speed::Acceleration;
+use crate::term::TermManager;
+use crate::theme::Theme;
+
+use crossterm::event::{KeyCode, KeyModifiers};
+
+const THUMBNAIL_WIDTH: usize = 24;
+const THUMBNAIL_HEIGHT: usize = 9;
+// The options each of the settings goes by on the command line
+const SETTING_OPTIONS: [&str; 12] = [
+    "theme", "shape", "acceleration", "controls", "keys", "mirror", "ghost", "hints", "sprint", "magnets", "distance-scoring", "assist"
+];
+
+pub enum Pick {
+    Item(usize, KeyCode), // Enter, or left/right to change a value in place
+    Back,
+    Quit, // The whole game should go
+}
+
+// A list of items in a message box, with a marker on the selected one
+pub fn choose(term: &mut TermManager, title: &str, items: &[String], mut selected: usize, footer: &[&str]) -> Pick {
+    let width = items.iter().map(|item| item.len()).max().unwrap_or(0);
+
+    loop {
+        let mut lines = vec![title.to_string(), String::new()];
+        lines.extend(items.iter().enumerate().map(|(i, item)| {
+            format!("{} {:<w$}", if i == selected {'>'} else {' '}, item, w = width)
+        }));
+        if !footer.is_empty() {
+            lines.push(String::new());
+            lines.extend(footer.iter().map(|line| line.to_string()));
+        }
+        term.show_message(&lines.iter().map(String::as_str).collect::<Vec<_>>());
+
+        let key = term.read_key_blocking();
+        match (Action::of(&key), key.code) {
+            (Some(Action::Quit), _) => return Pick::Quit,
+            (Some(Action::Suspend), _) => term.suspend(),
+            (Some(Action::Redraw), _) => term.redraw_all(),
+            (_, KeyCode::Up) => selected = (selected + items.len() - 1) % items.len(),
+            (_, KeyCode::Down) => selected = (selected + 1) % items.len(),
+            (_, KeyCode::Enter | KeyCode::Left | KeyCode::Right) => return Pick::Item(selected, key.code),
+            (_, KeyCode::Esc) => return Pick::Back,
+            _ => {}
+        }
+    }
+}
+
+// A line typed into a message box, None if it was given up on with Esc
+pub fn enter_text(term: &mut TermManager, title: &str, max_len: usize, footer: &[&str]) -> Option<String> {
+    let mut text = String::new();
+
+    loop {
+        let field = format!("{:_<w$}", text, w = max_len);
+        let mut lines = vec![title, "", &field];
+        if !footer.is_empty() {
+            lines.push("");
+            lines.extend(footer);
+        }
+        term.show_message(&lines);
+
+        let key = term.read_key_blocking();
+        match (Action::of(&key), key.code) {
+            (Some(Action::Suspend), _) => term.suspend(),
+            (Some(Action::Redraw), _) => term.redraw_all(),
+            // Q is just a letter here, CTRL+C gives up like Esc
+            (Some(Action::Quit), _) if key.modifiers.contains(KeyModifiers::CONTROL) => return None,
+            (_, KeyCode::Esc) => return None,
+            (_, KeyCode::Enter) => return Some(text),
+            (_, KeyCode::Backspace) => {
+                text.pop();
+            },
+            (_, KeyCode::Char(c)) if !key.modifiers.contains(KeyModifiers::CONTROL) && text.chars().count() < max_len => text.push(c),
+            _ => {}
+        }
+    }
+}
+
+// The config file is left for the user to edit, changes are kept for next
+// time as what was last picked
+pub fn settings(term: &mut TermManager, config: &mut Config, recent: &mut Recent) -> Pick {
+    let mut selected = 0;
+
+    loop {
+        let values = setting_values(config);
+        let mut items: Vec<String> = values.iter().map(|(label, value)| format!("{:<12}{:>10}", label, value)).collect();
+        items.push("Back".into());
+
+        let (i, code) = match choose(term, "Settings", &items, selected, &["Left and right to change"]) {
+            Pick::Item(i, _) if i == values.len() => return Pick::Back,
+            Pick::Item(i, code) => (i, code),
+            other => return other,
+        };
+        selected = i;
+        let step = if code == KeyCode::Left {-1} else {1};
+
+        match i {
+            0 => config.theme = Theme::by_name(cycle(Theme::NAMES, config.theme.name, step)).unwrap(),
+            1 => config.shape = Shape::by_name(cycle(Shape::NAMES, values[i].1, step)).unwrap(),
+            2 => config.acceleration = Acceleration::by_name(cycle(Acceleration::NAMES, values[i].1, step)).unwrap(),
+            3 => config.controls = ControlScheme::by_name(cycle(ControlScheme::NAMES, values[i].1, step)).unwrap(),
+            4 => config.keys = KeyPreset::by_name(cycle(KeyPreset::NAMES, values[i].1, step)).unwrap(),
+            5 => config.mirror = Mirror::by_name(cycle(Mirror::NAMES, values[i].1, step)).unwrap(),
+            6 => config.ghost = !config.ghost,
+            7 => config.hints = !config.hints,
+            8 => config.sprint = !config.sprint,
+            9 => config.magnets = !config.magnets,
+            10 => config.distance_scoring = !config.distance_scoring,
+            _ => config.assist = !config.assist,
+        }
+
+        let value = match setting_values(config)[i].1 {
+            "on" => "true",
+            "off" => "false",
+            value => value,
+        };
+        recent.set(SETTING_OPTIONS[i], value);
+    }
+}
+
+pub fn high_scores(term: &mut TermManager, scores: &Scores, achievements: &Achievements) -> Pick {
+    let mut lines = vec!["High scores".to_string(), String::new()];
+    for mode in Mode::ALL.iter().map(|mode| mode.name()).filter(|name| !Mode::by_name(name).unwrap().is_two_player()).chain(["puzzle"]) {
+        let games = scores.records().iter().filter(|r| r.mode == mode).count();
+        let best = scores.best(mode).map_or("-".to_string(), |best| best.to_string());
+        lines.push(format!("{:<10}{:>6} games  best {:>5}", mode, games, best));
+    }
+    for level in scores.levels() {
+        let games = scores.records().iter().filter(|r| r.level.as_deref() == Some(level)).count();
+        let best = scores.best_on_level(level).map_or("-".to_string(), |best| best.to_string());
+        lines.push(format!("  {:<8.8}{:>6} games  best {:>5}", level, games, best));
+    }
+    lines.extend([
+        String::new(), format!("Achievements {}/{}", achievements.count(), achievements::ALL.len()), String::new(),
+        "A for the achievements,".to_string(), "any other key to go back".to_string(),
+    ]);
+    term.show_message(&lines.iter().map(String::as_str).collect::<Vec<_>>());
+
+    let key = term.read_key_blocking();
+    match (Action::of(&key), key.code) {
+        (Some(Action::Quit), _) => Pick::Quit,
+        (_, KeyCode::Char('a' | 'A')) => achievement_list(term, achievements),
+        _ => Pick::Back,
+    }
+}
+
+// The bundled levels one at a time, each with a small picture of it. Picks
+// the one it was on with Enter.
+pub fn pick_level(term: &mut TermManager, scores: &Scores, mut selected: usize) -> Pick {
+    let names = pack::names();
+
+    loop {
+        let name = names[selected];
+        let level = pack::load(name).unwrap();
+        let thumbnail = level.thumbnail(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT);
+        let best = scores.best_on_level(name).map_or("-".to_string(), |best| best.to_string());
+
+        let mut lines = vec![
+            "Levels".to_string(), String::new(),
+            format!("< {} >", name), format!("{} of {}, best {}", selected + 1, names.len(), best), String::new(),
+        ];
+        // Padded to one width, messages center each line on its own
+        let width = thumbnail.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+        lines.extend(thumbnail.iter().map(|row| format!("{:<w$}", row, w = width)));
+        lines.extend([String::new(), "Left and right to look around".to_string(), "Enter to play, Esc to go back".to_string()]);
+        term.show_message(&lines.iter().map(String::as_str).collect::<Vec<_>>());
+
+        let key = term.read_key_blocking();
+        match (Action::of(&key), key.code) {
+            (Some(Action::Quit), _) => return Pick::Quit,
+            (Some(Action::Suspend), _) => term.suspend(),
+            (Some(Action::Redraw), _) => term.redraw_all(),
+            (_, KeyCode::Left | KeyCode::Up) => selected = (selected + names.len() - 1) % names.len(),
+            (_, KeyCode::Right | KeyCode::Down) => selected = (selected + 1) % names.len(),
+            (_, KeyCode::Enter) => return Pick::Item(selected, key.code),
+            (_, KeyCode::Esc) => return Pick::Back,
+            _ => {}
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////
+
+// Locked ones are listed too, so there's something to go for
+fn achievement_list(term: &mut TermManager, achievements: &Achievements) -> Pick {
+    let mut lines = vec!["Achievements".to_string(), String::new()];
+    lines.extend(achievements::ALL.iter().map(|achievement| {
+        let mark = if achievements.is_unlocked(achievement.id) {"[x]"} else {"[ ]"};
+        format!("{} {:<12}{:<26}", mark, achievement.name, achievement.description)
+    }));
+    lines.extend([String::new(), "Press any key to go back".to_string()]);
+    term.show_message(&lines.iter().map(String::as_str).collect::<Vec<_>>());
+
+    match Action::of(&term.read_key_blocking()) {
+        Some(Action::Quit) => Pick::Quit,
+        _ => Pick::Back,
+    }
+}
+
+fn setting_values(config: &Config) -> [(&'static str, &'static str); 12] {
+    let on_off = |on: bool| if on {"on"} else {"off"};
+    [
+        ("Theme", config.theme.name),
+        ("Shape", name_of(Shape::NAMES, Shape::by_name, &config.shape)),
+        ("Speed up", name_of(Acceleration::NAMES, Acceleration::by_name, &config.acceleration)),
+        ("Controls", name_of(ControlScheme::NAMES, ControlScheme::by_name, &config.controls)),
+        ("Keys", name_of(KeyPreset::NAMES, KeyPreset::by_name, &config.keys)),
+        ("Mirror", name_of(Mirror::NAMES, Mirror::by_name, &config.mirror)),
+        ("Ghost", on_off(config.ghost)),
+        ("Hints", on_off(config.hints)),
+        ("Sprint", on_off(config.sprint)),
+        ("Magnets", on_off(config.magnets)),
+        ("Far apples", on_off(config.distance_scoring)),
+        ("Assist", on_off(config.assist)),
+    ]
+}
+
+fn cycle(names: &[&'static str], current: &str, step: isize) -> &'static str {
+    let i = names.iter().position(|name| *name == current).unwrap_or(0) as isize;
+    names[(i + step).rem_euclid(names.len() as isize) as usize]
+}
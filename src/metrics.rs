@@ -0,0 +1,56 @@
+use std::{fs, io};
+
+use crate::Coords;
+use crate::snake::Snake;
+
+// One step of a round, as it stood right after the move
+struct Row {
+    step: u64,
+    time_ms: u32,
+    score: u64,
+    length: usize,
+    speed: f64, // Steps per second, as set rather than as measured
+    head: Coords,
+    since_apple: (u64, u32), // Steps and milliseconds, from the start until the first one
+}
+
+// Every step of the round being played, for those who like graphing their
+// games. It's written out as CSV once the round is over, replacing whatever
+// round was there before.
+pub struct Metrics {
+    rows: Vec<Row>,
+    last_apple: (u64, u32),
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics { rows: vec![], last_apple: (0, 0) }
+    }
+
+    pub fn clear(&mut self) {
+        self.rows.clear();
+        self.last_apple = (0, 0);
+    }
+
+    pub fn record(&mut self, snake: &Snake, time_ms: u32, score: u64, speed: f64, ate: bool) {
+        let step = snake.moves();
+        if ate {
+            self.last_apple = (step, time_ms);
+        }
+        let since_apple = (step - self.last_apple.0, time_ms - self.last_apple.1);
+        self.rows.push(Row { step, time_ms, score, length: snake.body().len(), speed, head: snake.head(), since_apple });
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("step,time_ms,score,length,speed,head_x,head_y,steps_since_apple,ms_since_apple\n");
+        for row in &self.rows {
+            csv.push_str(&format!("{},{},{},{},{:.2},{},{},{},{}\n",
+                row.step, row.time_ms, row.score, row.length, row.speed, row.head.0, row.head.1, row.since_apple.0, row.since_apple.1));
+        }
+        csv
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+}
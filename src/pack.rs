@@ -0,0 +1,35 @@
+use crate::level::Level;
+
+// The levels that come with the game, built into it so they're there with no
+// files around. They're just walls, apples show up anywhere like in the
+// classic mode.
+pub const LEVELS: [(&str, &str); 16] = [
+    ("box", include_str!("../levels/box.txt")),
+    ("pillars", include_str!("../levels/pillars.txt")),
+    ("corners", include_str!("../levels/corners.txt")),
+    ("cross", include_str!("../levels/cross.txt")),
+    ("tunnel", include_str!("../levels/tunnel.txt")),
+    ("rooms", include_str!("../levels/rooms.txt")),
+    ("checkerboard", include_str!("../levels/checkerboard.txt")),
+    ("arena", include_str!("../levels/arena.txt")),
+    ("diamond", include_str!("../levels/diamond.txt")),
+    ("scattered", include_str!("../levels/scattered.txt")),
+    ("hourglass", include_str!("../levels/hourglass.txt")),
+    ("lanes", include_str!("../levels/lanes.txt")),
+    ("comb", include_str!("../levels/comb.txt")),
+    ("fortress", include_str!("../levels/fortress.txt")),
+    ("spiral", include_str!("../levels/spiral.txt")),
+    ("maze", include_str!("../levels/maze.txt")),
+];
+
+pub fn names() -> Vec<&'static str> {
+    LEVELS.iter().map(|(name, _)| *name).collect()
+}
+
+// Named after itself, so its scores are kept apart like any other level's
+pub fn load(name: &str) -> Option<Level> {
+    let (name, text) = LEVELS.iter().find(|(level, _)| *level == name)?;
+    let mut level = Level::parse(text).expect("Error parsing a bundled level");
+    level.name = Some(name.to_string());
+    Some(level)
+}
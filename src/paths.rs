@@ -0,0 +1,86 @@
+use std::{env, fs, path::PathBuf};
+
+// Where everything the game keeps between runs lives, following each platform's
+// conventions. Each directory can be moved with its own environment variable.
+pub fn config_dir() -> PathBuf {
+    resolve("SNAKE_CONFIG_DIR", "XDG_CONFIG_HOME", &[".config"], "Library/Application Support", "APPDATA")
+}
+
+pub fn data_dir() -> PathBuf {
+    resolve("SNAKE_DATA_DIR", "XDG_DATA_HOME", &[".local", "share"], "Library/Application Support", "APPDATA")
+}
+
+pub fn cache_dir() -> PathBuf {
+    resolve("SNAKE_CACHE_DIR", "XDG_CACHE_HOME", &[".cache"], "Library/Caches", "LOCALAPPDATA")
+}
+
+pub fn config_file() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+// Each profile keeps its data apart, playing without one uses the top directory
+pub fn profile_dir(profile: Option<&str>) -> PathBuf {
+    match profile {
+        Some(name) => data_dir().join("profiles").join(name),
+        None => data_dir(),
+    }
+}
+
+// Applied on top of the main config file
+pub fn profile_config_file(name: &str) -> PathBuf {
+    config_dir().join("profiles").join(format!("{}.toml", name))
+}
+
+pub fn scores_file(profile: Option<&str>) -> PathBuf {
+    profile_dir(profile).join("scores.json")
+}
+
+// What was last picked in the menus, it's not a setting so it's kept with the data
+pub fn recent_file(profile: Option<&str>) -> PathBuf {
+    profile_dir(profile).join("recent.toml")
+}
+
+// What the profile has unlocked, kept with its scores
+pub fn achievements_file(profile: Option<&str>) -> PathBuf {
+    profile_dir(profile).join("achievements.toml")
+}
+
+// One file per finished game, named after when it ended
+pub fn replays_dir(profile: Option<&str>) -> PathBuf {
+    profile_dir(profile).join("replays")
+}
+
+// Anyone with either data or settings of their own
+pub fn profiles() -> Vec<String> {
+    let names_in = |dir: PathBuf, suffix: &str| -> Vec<String> {
+        fs::read_dir(dir).into_iter().flatten().flatten()
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter_map(|name| name.strip_suffix(suffix).map(String::from))
+            .collect()
+    };
+
+    let mut names = names_in(data_dir().join("profiles"), "");
+    names.extend(names_in(config_dir().join("profiles"), ".toml"));
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn resolve(override_var: &str, xdg_var: &str, xdg_default: &[&str], macos_dir: &str, windows_var: &str) -> PathBuf {
+    if let Some(dir) = env::var_os(override_var).filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let base = if cfg!(windows) {
+        env::var_os(windows_var).map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home.map(|home| home.join(macos_dir))
+    } else {
+        // Relative XDG paths are invalid as per the spec, and should be ignored
+        env::var_os(xdg_var).map(PathBuf::from).filter(|dir| dir.is_absolute())
+            .or_else(|| home.map(|home| xdg_default.iter().fold(home, |path, part| path.join(part))))
+    };
+
+    base.unwrap_or_else(|| PathBuf::from(".")).join("snake")
+}
@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+
+use crate::ai;
+use crate::env::{SnakeEnv, CELL_APPLE, CELL_EMPTY};
+use crate::term::{Cell, Region, TermManager};
+use crate::TermInt;
+
+use crossterm::style::ContentStyle;
+
+const STEP_MS: u64 = 80;
+
+// A small game the computer plays on its own in a corner, to watch or to keep
+// pace with. It runs on an engine of its own and is drawn at half the height,
+// one character for every two rows.
+pub struct PictureInPicture {
+    env: SnakeEnv,
+    score: u64,
+    last_step: Instant,
+}
+
+impl PictureInPicture {
+    pub fn new(width: TermInt, rows: TermInt) -> Self {
+        PictureInPicture { env: SnakeEnv::new(width, rows * 2), score: 0, last_step: Instant::now() }
+    }
+
+    // Steps when it's due, starting over once the game is done
+    pub fn update(&mut self, term: &mut TermManager, title: Region, board: Region) {
+        if self.last_step.elapsed() < Duration::from_millis(STEP_MS) {
+            return;
+        }
+        self.last_step = Instant::now();
+
+        if self.env.is_done() {
+            self.env.reset();
+            self.score = 0;
+        } else if let Some(apple) = self.env.apple() {
+            let dir = ai::next_direction(self.env.snake(), apple, self.env.board());
+            let (_, reward, _) = self.env.step(dir);
+            if reward > 0.0 {
+                self.score += 1;
+            }
+        }
+
+        self.draw(term, title, board);
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn draw(&self, term: &mut TermManager, title: Region, board: Region) {
+        let line = format!("{:<16}{:>5}", "COMPUTER", self.score);
+        term.print_text_in(title, (0, 0), &format!("{:<w$.w$}", line, w = title.width as usize), ContentStyle::new());
+
+        let obs = self.env.observation();
+        for y in 0..obs.height.div_ceil(2) {
+            for x in 0..obs.width {
+                let (top, bottom) = (obs.at((x, y * 2)), obs.at((x, y * 2 + 1)));
+                let filled = |cell: u8| cell != CELL_EMPTY && cell != CELL_APPLE;
+                let ch = match (filled(top), filled(bottom)) {
+                    _ if top == CELL_APPLE || bottom == CELL_APPLE => 'o',
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                };
+                term.print_in(board, (x, y), Cell::plain(ch));
+            }
+        }
+        term.flush();
+    }
+}
@@ -0,0 +1,73 @@
+use std::{fs, path::PathBuf};
+
+use crate::board::Shape;
+use crate::config::{Config, Mirror};
+use crate::controls::{ControlScheme, KeyPreset};
+use crate::speed::Acceleration;
+use crate::theme::Theme;
+use crate::{paths, storage};
+
+// What was last picked in the menus, so the next launch starts from there.
+// Only what was actually picked is kept, anything never touched in the menus
+// still comes from the config file. It's kept like the config file too, one
+// `option = "value"` per line, so it's easy to look at and to delete.
+pub struct Recent {
+    path: PathBuf,
+    entries: Vec<(String, String)>,
+}
+
+impl Recent {
+    // Missing or unreadable just means nothing was picked yet
+    pub fn load(profile: Option<&str>) -> Self {
+        let path = paths::recent_file(profile);
+        let entries = fs::read_to_string(&path).unwrap_or_default().lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().trim_matches('"').to_string()))
+            .collect();
+
+        Recent { path, entries }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, value)| value.as_str())
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) {
+        match self.entries.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = value.to_string(),
+            None => self.entries.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let dir = self.path.parent().unwrap();
+        fs::create_dir_all(dir).map_err(|e| format!("Error creating {}: {}", dir.display(), e))?;
+        let text: String = self.entries.iter().map(|(key, value)| format!("{} = \"{}\"\n", key, value)).collect();
+        storage::write_atomic(&self.path, text).map_err(|e| format!("Error writing {}: {}", self.path.display(), e))
+    }
+
+    // The settings menu's picks, the mode and level are only for the menus to
+    // start on. Whatever doesn't make sense anymore is left alone.
+    pub fn apply(&self, config: &mut Config) {
+        for (key, value) in &self.entries {
+            let on = value == "true";
+            match key.as_str() {
+                "theme" => if let Some(theme) = Theme::by_name(value) {
+                    config.theme = theme;
+                },
+                "shape" => config.shape = Shape::by_name(value).unwrap_or(config.shape),
+                "acceleration" => config.acceleration = Acceleration::by_name(value).unwrap_or(config.acceleration),
+                "controls" => config.controls = ControlScheme::by_name(value).unwrap_or(config.controls),
+                "keys" => config.keys = KeyPreset::by_name(value).unwrap_or(config.keys),
+                "mirror" => config.mirror = Mirror::by_name(value).unwrap_or(config.mirror),
+                "ghost" => config.ghost = on,
+                "hints" => config.hints = on,
+                "sprint" => config.sprint = on,
+                "magnets" => config.magnets = on,
+                "distance-scoring" => config.distance_scoring = on,
+                "assist" => config.assist = on,
+                _ => {},
+            }
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::convert::TryInto;
+
+use crate::{Coords, TermInt};
+use crate::board::Board;
+use crate::growth::Growth;
+use crate::replay::{Replay, Step};
+use crate::snake::Direction::{self, *};
+
+const MAGIC: &[u8; 4] = b"SNKR";
+// Bumped whenever the layout changes, older versions must still be read
+pub const VERSION: u16 = 3;
+
+// A finished game in a file of its own: the board, the snake and apple it
+// started with, and every step after that with when it happened. All numbers
+// are little endian, the layout is:
+//
+//   "SNKR", version u16, seed u64, config hash u64, score u64, flags u8,
+//     then hunger u16 if the flag for it is set (since version 2),
+//     then segments per apple u16 and points per extra segment u16, 0 for
+//     none, if the flag for growth is set (since version 3)
+//   width u16, height u16, one bit per cell row by row, set if playable
+//   body length u16 and the body tail first, apple, both as x u16, y u16
+//   step count u32, then for each: milliseconds since the start u32,
+//     one byte with the direction in bits 0-1, bit 2 if the snake grew,
+//     bit 3 if the apple moved and bit 4 if the snake went hungry, followed
+//     by the new apple if it moved
+//   FNV-1a u64 of everything before it
+pub struct Recording {
+    pub version: u16,
+    pub seed: u64, // What the round's random numbers came from
+    pub config_hash: u64, // Tells apart games played with different settings
+    pub score: u64,
+    pub far_apples: bool, // Apples were worth more the further away they spawned
+    pub drifting_apples: bool, // Apples moved on their own, one cell diagonally at a time
+    pub hunger: Option<u16>, // Steps without eating it took to lose a segment
+    pub growth: Growth, // Classic unless the flag for it is set
+    pub board: Board,
+    pub replay: Replay,
+}
+
+// Everything at the top of a recording besides the board and the snake
+pub struct Header {
+    pub seed: u64,
+    pub config_hash: u64,
+    pub score: u64,
+    pub far_apples: bool,
+    pub drifting_apples: bool,
+    pub hunger: Option<u16>,
+    pub growth: Growth,
+}
+
+const FLAG_FAR_APPLES: u8 = 1;
+const FLAG_DRIFTING_APPLES: u8 = 1 << 1;
+const FLAG_HUNGER: u8 = 1 << 2;
+const FLAG_GROWTH: u8 = 1 << 3;
+const STEP_GREW: u8 = 1 << 2;
+const STEP_APPLE_MOVED: u8 = 1 << 3;
+const STEP_SHRANK: u8 = 1 << 4;
+
+pub fn encode(header: &Header, board: &Board, replay: &Replay) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.extend(VERSION.to_le_bytes());
+    out.extend(header.seed.to_le_bytes());
+    out.extend(header.config_hash.to_le_bytes());
+    out.extend(header.score.to_le_bytes());
+    out.push(if header.far_apples {FLAG_FAR_APPLES} else {0} | if header.drifting_apples {FLAG_DRIFTING_APPLES} else {0}
+        | if header.hunger.is_some() {FLAG_HUNGER} else {0} | if header.growth != Growth::CLASSIC {FLAG_GROWTH} else {0});
+    if let Some(every) = header.hunger {
+        out.extend(every.to_le_bytes());
+    }
+    if header.growth != Growth::CLASSIC {
+        out.extend(header.growth.per_apple.to_le_bytes());
+        out.extend(header.growth.ramp.unwrap_or(0).to_le_bytes());
+    }
+
+    let (width, height) = board.size();
+    out.extend(width.to_le_bytes());
+    out.extend(height.to_le_bytes());
+    let mut bits = vec![0u8; (width as usize * height as usize).div_ceil(8)];
+    for y in 0..height {
+        for x in 0..width {
+            if board.is_playable((x, y)) {
+                let i = y as usize * width as usize + x as usize;
+                bits[i / 8] |= 1 << (i % 8);
+            }
+        }
+    }
+    out.extend(bits);
+
+    let start = replay.frame(0);
+    out.extend((start.body.len() as u16).to_le_bytes());
+    for pos in &start.body {
+        push_coords(&mut out, *pos);
+    }
+    push_coords(&mut out, start.apple);
+
+    out.extend((replay.steps().len() as u32).to_le_bytes());
+    let (mut head, mut apple) = (start.head(), start.apple);
+    for step in replay.steps() {
+        let moved = step.apple != apple;
+        let dir_bits = match direction_between(head, step.head) {
+            Up => 0,
+            Down => 1,
+            Left => 2,
+            Right => 3,
+        };
+
+        out.extend(step.time_ms.to_le_bytes());
+        out.push(dir_bits | if step.grew {STEP_GREW} else {0} | if moved {STEP_APPLE_MOVED} else {0} | if step.shrank {STEP_SHRANK} else {0});
+        if moved {
+            push_coords(&mut out, step.apple);
+        }
+        head = step.head;
+        apple = step.apple;
+    }
+
+    out.extend(checksum(&out).to_le_bytes());
+    out
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Recording, String> {
+    if bytes.len() < MAGIC.len() + 8 || &bytes[..MAGIC.len()] != MAGIC {
+        return Err("not a snake recording".into());
+    }
+
+    let (data, sum) = bytes.split_at(bytes.len() - 8);
+    if checksum(data).to_le_bytes() != sum {
+        return Err("the recording is damaged, its checksum doesn't match".into());
+    }
+
+    let mut reader = Reader { data, pos: MAGIC.len() };
+    let version = reader.u16()?;
+    if version > VERSION {
+        return Err(format!("the recording is version {}, this game only knows up to {}", version, VERSION));
+    }
+
+    let seed = reader.u64()?;
+    let config_hash = reader.u64()?;
+    let score = reader.u64()?;
+    let flags = reader.u8()?;
+    let (far_apples, drifting_apples) = (flags & FLAG_FAR_APPLES != 0, flags & FLAG_DRIFTING_APPLES != 0);
+    let hunger = if flags & FLAG_HUNGER != 0 {Some(reader.u16()?)} else {None};
+    if hunger == Some(0) {
+        return Err("the snake went hungry every 0 steps".into());
+    }
+    let growth = if flags & FLAG_GROWTH != 0 {
+        let (per_apple, ramp) = (reader.u16()?, reader.u16()?);
+        Growth { per_apple, ramp: Some(ramp).filter(|points| *points > 0) }
+    } else {
+        Growth::CLASSIC
+    };
+
+    let (width, height) = (reader.u16()?, reader.u16()?);
+    let bits = reader.take((width as usize * height as usize).div_ceil(8))?;
+    let mask: Vec<bool> = (0..width as usize * height as usize).map(|i| bits[i / 8] & (1 << (i % 8)) != 0).collect();
+    let board = Board::from_mask(width, height, &mask);
+
+    let in_bounds = |pos: Coords| if pos.0 < width && pos.1 < height {Ok(pos)} else {Err("a position is off the board".to_string())};
+    let length = reader.u16()?;
+    if length == 0 {
+        return Err("the snake has no body".into());
+    }
+    let body = (0..length).map(|_| in_bounds(reader.coords()?)).collect::<Result<Vec<_>, String>>()?;
+    let mut apple = in_bounds(reader.coords()?)?;
+
+    let mut replay = Replay::new(&body, apple);
+    let mut head = *body.last().unwrap();
+    let mut length = body.len();
+    for _ in 0..reader.u32()? {
+        let time_ms = reader.u32()?;
+        let flags = reader.u8()?;
+        let dir = [Up, Down, Left, Right][(flags & 3) as usize];
+        head = step_within(dir, head, width, height).ok_or("a step goes off the board")?;
+        if flags & STEP_APPLE_MOVED != 0 {
+            apple = in_bounds(reader.coords()?)?;
+        }
+        let (grew, shrank) = (flags & STEP_GREW != 0, flags & STEP_SHRANK != 0);
+        // Going hungry never takes the head
+        length = (length + grew as usize).checked_sub(shrank as usize).filter(|length| *length > 0).ok_or("the snake has no body left")?;
+        replay.record(Step { head, grew, shrank, apple, time_ms });
+    }
+
+    if reader.pos != data.len() {
+        return Err("there's data after the last step".into());
+    }
+
+    Ok(Recording { version, seed, config_hash, score, far_apples, drifting_apples, hunger, growth, board, replay })
+}
+
+// FNV-1a, good enough to catch damage, and easy to get the same anywhere
+pub fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+///////////////////////////////////////////////////////////////////////////
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let bytes = self.data.get(self.pos..self.pos + n).ok_or("the recording ends too early")?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn coords(&mut self) -> Result<Coords, String> {
+        Ok((self.u16()?, self.u16()?))
+    }
+}
+
+fn push_coords(out: &mut Vec<u8>, pos: Coords) {
+    out.extend(pos.0.to_le_bytes());
+    out.extend(pos.1.to_le_bytes());
+}
+
+// Steps are always to a neighbouring cell
+fn direction_between(from: Coords, to: Coords) -> Direction {
+    match (to.0 as i32 - from.0 as i32, to.1 as i32 - from.1 as i32) {
+        (_, -1) => Up,
+        (_, 1) => Down,
+        (-1, _) => Left,
+        _ => Right,
+    }
+}
+
+fn step_within(dir: Direction, pos: Coords, width: TermInt, height: TermInt) -> Option<Coords> {
+    let next = match dir {
+        Up => (pos.0, pos.1.checked_sub(1)?),
+        Left => (pos.0.checked_sub(1)?, pos.1),
+        _ => dir.step_from(pos),
+    };
+    (next.0 < width && next.1 < height).then_some(next)
+}
@@ -0,0 +1,97 @@
+use crate::Coords;
+use crate::snake::Direction::{self, *};
+
+// Every this many steps the whole snake is stored, so any frame can be
+// rebuilt by replaying at most this many steps on top of a keyframe
+const KEYFRAME_INTERVAL: usize = 50;
+
+// What happened in a game, step by step
+pub struct Replay {
+    steps: Vec<Step>,
+    keyframes: Vec<Keyframe>,
+    body: Vec<Coords>,
+}
+
+#[derive(Copy, Clone)]
+pub struct Step {
+    pub head: Coords,
+    pub grew: bool,
+    pub shrank: bool, // Lost a segment off its tail to hunger, after the step
+    pub apple: Coords, // Where the apple is after the step
+    pub time_ms: u32, // Since the game started
+}
+
+struct Keyframe {
+    body: Vec<Coords>,
+    apple: Coords,
+}
+
+// The board after a number of steps, tail first like in the snake itself
+pub struct Frame {
+    pub body: Vec<Coords>,
+    pub apple: Coords,
+}
+
+impl Replay {
+    pub fn new(body: &[Coords], apple: Coords) -> Self {
+        Replay { steps: vec![], keyframes: vec![Keyframe { body: body.to_vec(), apple }], body: body.to_vec() }
+    }
+
+    pub fn record(&mut self, step: Step) {
+        apply(&mut self.body, &step);
+        self.steps.push(step);
+
+        if self.steps.len().is_multiple_of(KEYFRAME_INTERVAL) {
+            self.keyframes.push(Keyframe { body: self.body.clone(), apple: step.apple });
+        }
+    }
+
+    // Frames go from 0, before the first step, to the number of steps
+    pub fn frame_count(&self) -> usize {
+        self.steps.len() + 1
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn frame(&self, n: usize) -> Frame {
+        let n = n.min(self.steps.len());
+        let keyframe = &self.keyframes[n / KEYFRAME_INTERVAL];
+        let mut frame = Frame { body: keyframe.body.clone(), apple: keyframe.apple };
+
+        for step in &self.steps[n / KEYFRAME_INTERVAL * KEYFRAME_INTERVAL..n] {
+            apply(&mut frame.body, step);
+            frame.apple = step.apple;
+        }
+
+        frame
+    }
+}
+
+impl Frame {
+    pub fn head(&self) -> Coords {
+        *self.body.last().unwrap()
+    }
+
+    // Worked out from the neck, since that's the way the head came from
+    pub fn direction(&self) -> Direction {
+        let head = self.head();
+        match self.body.len().checked_sub(2).map(|i| self.body[i]) {
+            Some(neck) if neck.1 < head.1 => Down,
+            Some(neck) if neck.1 > head.1 => Up,
+            Some(neck) if neck.0 > head.0 => Left,
+            _ => Right,
+        }
+    }
+}
+
+fn apply(body: &mut Vec<Coords>, step: &Step) {
+    body.push(step.head);
+    if !step.grew {
+        body.remove(0);
+    }
+    if step.shrank {
+        body.remove(0);
+    }
+}
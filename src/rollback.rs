@@ -0,0 +1,353 @@
+use std::collections::VecDeque;
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::{recording, Coords, TermInt};
+use crate::board::Board;
+use crate::snake::{Snake, Direction::{self, Right}, MoveResult::*};
+
+pub const TARGET: u64 = 10;
+const INITIAL_LENGTH: i16 = 4;
+const MAX_ROLLBACK: u32 = 8; // Frames ahead of the last one the remote input is known for
+
+// A turn, or None to keep going the same way
+pub type Input = Option<Direction>;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Outcome {
+    Won(usize),
+    Draw,
+}
+
+// A versus round as both ends of a connection play it out: the same board
+// for each player, apples of their own and the first to the target wins,
+// while crashing loses. Every snake moves once per frame, with no timing
+// and no floating point involved, so the same seed and inputs come out the
+// same on any machine. It's cheap to clone, which is all a snapshot takes.
+#[derive(Clone)]
+pub struct VersusState {
+    frame: u32,
+    snakes: [Snake; 2],
+    apples: [Option<Coords>; 2],
+    scores: [u64; 2],
+    rng: StdRng,
+    outcome: Option<Outcome>,
+}
+
+// Rollback for one end of a connection: local inputs are played right away,
+// after an optional delay of a few frames, and the remote player is guessed
+// to keep going straight until their input for the frame arrives. A wrong
+// guess goes back to the last frame both inputs were known for and plays
+// everything after it again. Sending and receiving inputs is up to the caller.
+pub struct Session {
+    board: Board,
+    local: usize,
+    delay: u32,
+    confirmed: VersusState,       // Up to the last frame with the remote input known
+    inputs: VecDeque<FrameInputs>, // From the confirmed frame on
+    current: VersusState,
+}
+
+#[derive(Copy, Clone, Default)]
+struct FrameInputs {
+    local: Input,
+    remote: Option<Input>, // None while it's just a guess
+}
+
+impl VersusState {
+    pub fn new(board: &Board, seed: u64) -> Self {
+        let spawn = board.spawn_point(INITIAL_LENGTH as TermInt).expect("Error spawning: the board is too small");
+        let snake = Snake::new(spawn, INITIAL_LENGTH, Right);
+        let mut state = VersusState {
+            frame: 0, snakes: [snake.clone(), snake], apples: [None; 2], scores: [0; 2],
+            rng: StdRng::seed_from_u64(seed), outcome: None,
+        };
+        for i in 0..2 {
+            state.apples[i] = state.spawn_apple(board, i);
+        }
+        state
+    }
+
+    // Both players in order, the round ends the first frame someone crashes or
+    // gets to the target, and both doing it at once is a draw. Frames still
+    // go by once it's over, just with nothing happening.
+    pub fn step(&mut self, board: &Board, inputs: [Input; 2]) {
+        self.frame += 1;
+        if self.outcome.is_some() {
+            return;
+        }
+
+        let mut crashed = [false; 2];
+        for i in 0..2 {
+            if let Some(dir) = inputs[i] {
+                self.snakes[i].set_direction(dir);
+            }
+            match self.snakes[i].move_step(board) {
+                Crashed => crashed[i] = true,
+                Moved { new_head, .. } if Some(new_head) == self.apples[i] => {
+                    self.scores[i] += 1;
+                    self.snakes[i].grow(1);
+                    self.apples[i] = self.spawn_apple(board, i);
+                },
+                Moved { .. } => {},
+            }
+        }
+
+        let done = [0, 1].map(|i| crashed[1 - i] || self.scores[i] >= TARGET || self.apples[i].is_none());
+        self.outcome = match done {
+            [true, true] => Some(Outcome::Draw),
+            [true, false] => Some(Outcome::Won(0)),
+            [false, true] => Some(Outcome::Won(1)),
+            [false, false] => None,
+        };
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn snake(&self, player: usize) -> &Snake {
+        &self.snakes[player]
+    }
+
+    pub fn apple(&self, player: usize) -> Option<Coords> {
+        self.apples[player]
+    }
+
+    pub fn score(&self, player: usize) -> u64 {
+        self.scores[player]
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    // For both ends to compare every so often, a mismatch means they drifted apart
+    pub fn checksum(&self) -> u64 {
+        let mut bytes = self.frame.to_le_bytes().to_vec();
+        for i in 0..2 {
+            for pos in self.snakes[i].body().iter().chain(&self.apples[i]) {
+                bytes.extend(pos.0.to_le_bytes());
+                bytes.extend(pos.1.to_le_bytes());
+            }
+            bytes.extend(self.scores[i].to_le_bytes());
+        }
+        recording::checksum(&bytes)
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn spawn_apple(&mut self, board: &Board, player: usize) -> Option<Coords> {
+        let body = self.snakes[player].body();
+        let free: Vec<&Coords> = board.positions().iter().filter(|pos| !body.contains(pos)).collect();
+        free.choose(&mut self.rng).copied().copied()
+    }
+}
+
+impl Session {
+    // Both ends need the same board and seed, and a player each
+    pub fn new(board: Board, seed: u64, local: usize, delay: u32) -> Self {
+        assert!(local < 2, "Error: versus only has players 0 and 1");
+        let state = VersusState::new(&board, seed);
+        // Nobody gets to turn before the delay is up, so those frames are known already
+        let inputs = (0..delay).map(|_| FrameInputs { local: None, remote: Some(None) }).collect();
+        Session { board, local, delay, confirmed: state.clone(), inputs, current: state }
+    }
+
+    // Plays the next frame with the local input for it, and returns the frame
+    // that input is for, to be sent to the other end. Nothing happens, and
+    // None comes back, while too far ahead of what's come in from it.
+    pub fn advance(&mut self, input: Input) -> Option<u32> {
+        if self.current.frame - self.confirmed.frame >= MAX_ROLLBACK {
+            return None;
+        }
+
+        // Frame n's inputs play on the step from n to n + 1
+        let frame = self.current.frame + self.delay;
+        self.inputs_at(frame).local = input;
+        let inputs = self.inputs_for(self.current.frame);
+        self.current.step(&self.board, inputs);
+        Some(frame)
+    }
+
+    // Repeats and anything too far ahead to be real are ignored
+    pub fn receive(&mut self, frame: u32, input: Input) {
+        if frame < self.confirmed.frame || frame > self.current.frame + self.delay + MAX_ROLLBACK {
+            return;
+        }
+
+        let inputs = self.inputs_at(frame);
+        let guessed = inputs.remote.unwrap_or(None);
+        inputs.remote = Some(input);
+        if frame < self.current.frame && guessed != input {
+            self.roll_back();
+        }
+        self.confirm();
+    }
+
+    pub fn state(&self) -> &VersusState {
+        &self.current
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn local_player(&self) -> usize {
+        self.local
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn inputs_at(&mut self, frame: u32) -> &mut FrameInputs {
+        let i = (frame - self.confirmed.frame) as usize;
+        if self.inputs.len() <= i {
+            self.inputs.resize(i + 1, FrameInputs::default());
+        }
+        &mut self.inputs[i]
+    }
+
+    // In player order, with the remote one guessed if it's not in yet
+    fn inputs_for(&mut self, frame: u32) -> [Input; 2] {
+        let local = self.local;
+        let inputs = *self.inputs_at(frame);
+        let remote = inputs.remote.unwrap_or(None);
+        if local == 0 {[inputs.local, remote]} else {[remote, inputs.local]}
+    }
+
+    fn roll_back(&mut self) {
+        let until = self.current.frame;
+        self.current = self.confirmed.clone();
+        while self.current.frame < until {
+            let inputs = self.inputs_for(self.current.frame);
+            self.current.step(&self.board, inputs);
+        }
+    }
+
+    // Moves the snapshot up past every frame that's no longer a guess
+    fn confirm(&mut self) {
+        while self.confirmed.frame < self.current.frame && self.inputs.front().is_some_and(|inputs| inputs.remote.is_some()) {
+            let inputs = self.inputs_for(self.confirmed.frame);
+            self.confirmed.step(&self.board, inputs);
+            self.inputs.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Shape;
+    use crate::snake::Direction::*;
+
+    const SEED: u64 = 7;
+
+    fn board() -> Board {
+        Board::new(Shape::Rectangle, 30, 16)
+    }
+
+    // Turns every few frames, different for each player, so a guess that
+    // they kept going straight is wrong every now and then
+    fn script(player: usize, n: u32) -> Input {
+        match (n * 7 + player as u32 * 3) % 11 {
+            0 => Some(Up),
+            3 => Some(Left),
+            6 => Some(Down),
+            9 => Some(Right),
+            _ => None,
+        }
+    }
+
+    // Each end plays a frame per tick, and what it sends takes its latency
+    // in ticks to get to the other one
+    fn play(delay: u32, latency: [u32; 2], frames: u32) -> [Session; 2] {
+        let mut sessions = [0, 1].map(|player| Session::new(board(), SEED, player, delay));
+        let mut in_flight: Vec<(u32, usize, u32, Input)> = vec![]; // Arriving at, to, frame, input
+
+        for tick in 0..10_000 {
+            for player in 0..2 {
+                let frame = sessions[player].state().frame();
+                if frame < frames {
+                    let input = script(player, frame);
+                    if let Some(sent) = sessions[player].advance(input) {
+                        in_flight.push((tick + latency[player], 1 - player, sent, input));
+                    }
+                }
+            }
+            for &(_, to, frame, input) in in_flight.iter().filter(|(at, ..)| *at <= tick) {
+                sessions[to].receive(frame, input);
+            }
+            in_flight.retain(|(at, ..)| *at > tick);
+
+            if in_flight.is_empty() && sessions.iter().all(|session| session.state().frame() == frames) {
+                return sessions;
+            }
+        }
+        panic!("Never got to frame {}", frames);
+    }
+
+    // The same round with both inputs known right away
+    fn played_locally(delay: u32, frames: u32) -> VersusState {
+        let board = board();
+        let mut state = VersusState::new(&board, SEED);
+        let input = |player, n: u32| if n < delay {None} else {script(player, n - delay)};
+        for n in 0..frames {
+            state.step(&board, [input(0, n), input(1, n)]);
+        }
+        state
+    }
+
+    #[test]
+    fn both_ends_agree_without_delay() {
+        let sessions = play(0, [3, 5], 200);
+        let expected = played_locally(0, 200);
+        for session in &sessions {
+            assert_eq!(session.state().checksum(), expected.checksum());
+            assert_eq!(session.state().outcome(), expected.outcome());
+        }
+    }
+
+    #[test]
+    fn both_ends_agree_with_delay() {
+        let sessions = play(2, [6, 1], 200);
+        let expected = played_locally(2, 200);
+        for session in &sessions {
+            assert_eq!(session.state().checksum(), expected.checksum());
+            assert_eq!(session.state().outcome(), expected.outcome());
+        }
+    }
+
+    #[test]
+    fn stops_too_far_ahead() {
+        let mut session = Session::new(board(), SEED, 0, 0);
+        for frame in 0..MAX_ROLLBACK {
+            assert_eq!(session.advance(None), Some(frame));
+        }
+        assert_eq!(session.advance(None), None);
+        assert_eq!(session.state().frame(), MAX_ROLLBACK);
+
+        // One more frame known lets it go one further
+        session.receive(0, None);
+        assert_eq!(session.advance(None), Some(MAX_ROLLBACK));
+        assert_eq!(session.advance(None), None);
+    }
+
+    #[test]
+    fn repeated_and_stale_inputs_change_nothing() {
+        let mut session = Session::new(board(), SEED, 0, 0);
+        for _ in 0..5 {
+            session.advance(None);
+        }
+        session.receive(0, Some(Down));
+        session.receive(1, None);
+        let checksum = session.state().checksum();
+        let confirmed = session.confirmed.frame;
+
+        session.receive(1, None);
+        session.receive(0, Some(Down));
+        session.receive(0, Some(Up));
+        session.receive(MAX_ROLLBACK * 10, Some(Up));
+        assert_eq!(session.state().checksum(), checksum);
+        assert_eq!(session.confirmed.frame, confirmed);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::TermInt;
+
+// Crockford's, without I, L, O or U to be mixed up when typed in
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const LENGTH: usize = 16; // 80 bits, five to a character
+const GROUP: usize = 4;
+const RULES_BITS: u32 = 12;
+
+// What it takes to play a round again from the same start somewhere else:
+// where its random numbers came from, the board it was on, the mode, and a
+// few bits of the rules hash to tell when the rules aren't the same. Written
+// as 16 letters and digits in groups of four, like 0J4M-8ZQ1-...
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RunCode {
+    pub seed: u32,
+    pub width: TermInt,
+    pub height: TermInt,
+    pub mode: u8, // Up to 16, numbered however the game likes
+    rules: u16,
+}
+
+impl RunCode {
+    pub fn new(seed: u32, size: (TermInt, TermInt), mode: u8, rules_hash: u64) -> Self {
+        RunCode { seed, width: size.0, height: size.1, mode: mode & 0xF, rules: fingerprint(rules_hash) }
+    }
+
+    pub fn same_rules(&self, rules_hash: u64) -> bool {
+        self.rules == fingerprint(rules_hash)
+    }
+
+    pub fn encode(&self) -> String {
+        let bits = (self.seed as u128) << 48 | (self.width as u128) << 32 | (self.height as u128) << 16
+            | (self.mode as u128) << RULES_BITS | self.rules as u128;
+
+        let chars: Vec<char> = (0..LENGTH).rev().map(|i| ALPHABET[(bits >> (i * 5)) as usize & 0x1F] as char).collect();
+        chars.chunks(GROUP).map(|group| group.iter().collect::<String>()).collect::<Vec<_>>().join("-")
+    }
+
+    // Case, dashes and spaces don't matter, and the letters that look like digits count as those
+    pub fn decode(text: &str) -> Result<Self, String> {
+        let mut bits: u128 = 0;
+        let mut length = 0;
+
+        for c in text.chars().filter(|c| *c != '-' && !c.is_whitespace()) {
+            let c = match c.to_ascii_uppercase() {
+                'O' => '0',
+                'I' | 'L' => '1',
+                c => c,
+            };
+            let value = ALPHABET.iter().position(|a| *a as char == c).ok_or(format!("'{}' can't be in a run code", c))?;
+            bits = bits << 5 | value as u128;
+            length += 1;
+        }
+
+        if length != LENGTH {
+            return Err(format!("a run code has {} letters and digits", LENGTH));
+        }
+
+        Ok(RunCode {
+            seed: (bits >> 48) as u32,
+            width: (bits >> 32) as TermInt,
+            height: (bits >> 16) as TermInt,
+            mode: (bits >> RULES_BITS) as u8 & 0xF,
+            rules: bits as u16 & ((1 << RULES_BITS) - 1),
+        })
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////
+
+fn fingerprint(rules_hash: u64) -> u16 {
+    (rules_hash & ((1 << RULES_BITS) - 1)) as u16
+}
@@ -0,0 +1,174 @@
+use std::{fs, path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::json::Value;
+use crate::{paths, storage};
+
+// Version 1 had no rules or level, games from back then only count for whole modes
+const FORMAT_VERSION: u64 = 2;
+
+// Every finished game, kept around for high scores and so it can be moved
+// between machines. Records are told apart by the time the game ended. Games
+// played by different rules have bests of their own, as do puzzle levels.
+pub struct Scores {
+    path: PathBuf,
+    records: Vec<Record>,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Record {
+    pub timestamp_ms: u64,
+    pub mode: String,
+    pub score: u64,
+    pub length: u64,
+    pub moves: u64,
+    pub rules: Option<u64>, // The config's rules hash, saved as a string since it doesn't fit a JSON number
+    pub level: Option<String>, // The puzzle's file name, without the extension
+}
+
+pub enum ScoresCommand {
+    List,
+    Export(String),
+    Import(String),
+}
+
+impl Scores {
+    // A broken file goes back to the last good save, or to scratch if there's none
+    pub fn load(profile: Option<&str>) -> Self {
+        let path = paths::scores_file(profile);
+        let records = storage::read_recovering(&path, |text| records_from_json(&Value::parse(text)?))
+            .unwrap_or_default();
+
+        Scores { path, records }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let dir = self.path.parent().unwrap();
+        fs::create_dir_all(dir).map_err(|e| format!("Error creating {}: {}", dir.display(), e))?;
+        storage::write_atomic(&self.path, self.to_json().to_string()).map_err(|e| format!("Error writing {}: {}", self.path.display(), e))
+    }
+
+    pub fn add(&mut self, mode: &str, score: u64, length: u64, moves: u64, rules: u64, level: Option<&str>) {
+        let timestamp_ms = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+        self.records.push(Record { timestamp_ms, mode: mode.into(), score, length, moves, rules: Some(rules), level: level.map(String::from) });
+    }
+
+    // Across every set of rules the mode was played with
+    pub fn best(&self, mode: &str) -> Option<u64> {
+        self.records.iter().filter(|r| r.mode == mode).map(|r| r.score).max()
+    }
+
+    // The one to beat in a game with exactly these rules
+    pub fn best_with(&self, mode: &str, rules: u64) -> Option<u64> {
+        self.records.iter().filter(|r| r.mode == mode && r.rules == Some(rules)).map(|r| r.score).max()
+    }
+
+    pub fn best_on_level(&self, level: &str) -> Option<u64> {
+        self.records.iter().filter(|r| r.level.as_deref() == Some(level)).map(|r| r.score).max()
+    }
+
+    // Every puzzle level played, by name
+    pub fn levels(&self) -> Vec<&str> {
+        let mut levels: Vec<&str> = self.records.iter().filter_map(|r| r.level.as_deref()).collect();
+        levels.sort_unstable();
+        levels.dedup();
+        levels
+    }
+
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    // Returns how many of them were new
+    pub fn merge(&mut self, records: Vec<Record>) -> usize {
+        let before = self.records.len();
+        for record in records {
+            if !self.records.iter().any(|r| r.timestamp_ms == record.timestamp_ms) {
+                self.records.push(record);
+            }
+        }
+        self.records.sort_by_key(|r| r.timestamp_ms);
+        self.records.len() - before
+    }
+
+    pub fn to_json(&self) -> Value {
+        let records = self.records.iter().map(|r| {
+            let mut fields = vec![
+                ("timestamp_ms".into(), r.timestamp_ms.into()),
+                ("mode".into(), r.mode.as_str().into()),
+                ("score".into(), r.score.into()),
+                ("length".into(), r.length.into()),
+                ("moves".into(), r.moves.into()),
+            ];
+            if let Some(rules) = r.rules {
+                fields.push(("rules".into(), rules.to_string().as_str().into()));
+            }
+            if let Some(level) = &r.level {
+                fields.push(("level".into(), level.as_str().into()));
+            }
+            Value::Object(fields)
+        }).collect();
+
+        Value::Object(vec![("version".into(), FORMAT_VERSION.into()), ("scores".into(), Value::Array(records))])
+    }
+}
+
+pub fn run_command(command: ScoresCommand, profile: Option<&str>) -> Result<(), String> {
+    let mut scores = Scores::load(profile);
+
+    match command {
+        ScoresCommand::List => {
+            let mut modes: Vec<&str> = scores.records().iter().map(|r| r.mode.as_str()).collect();
+            modes.sort_unstable();
+            modes.dedup();
+
+            println!("{} games played", scores.records().len());
+            for mode in modes {
+                let games = scores.records().iter().filter(|r| r.mode == mode).count();
+                println!("{:<10} {:>6} games, best score {}", mode, games, scores.best(mode).unwrap_or(0));
+            }
+            for level in scores.levels() {
+                let games = scores.records().iter().filter(|r| r.level.as_deref() == Some(level)).count();
+                println!("  {:<8} {:>6} games, best score {}", level, games, scores.best_on_level(level).unwrap_or(0));
+            }
+        },
+        ScoresCommand::Export(path) => {
+            storage::write_replacing(Path::new(&path), scores.to_json().to_string()).map_err(|e| format!("Error writing {}: {}", path, e))?;
+            println!("Exported {} games to {}", scores.records().len(), path);
+        },
+        ScoresCommand::Import(path) => {
+            let text = fs::read_to_string(&path).map_err(|e| format!("Error reading {}: {}", path, e))?;
+            let json = Value::parse(&text).map_err(|e| format!("Error in {}: {}", path, e))?;
+            let records = records_from_json(&json).map_err(|e| format!("Error in {}: {}", path, e))?;
+            let added = scores.merge(records);
+            scores.save()?;
+            println!("Imported {} new games from {}", added, path);
+        },
+    }
+
+    Ok(())
+}
+
+fn records_from_json(json: &Value) -> Result<Vec<Record>, String> {
+    match json.get("version").and_then(Value::as_u64) {
+        Some(1) | Some(FORMAT_VERSION) => {},
+        Some(v) => return Err(format!("unsupported version {}", v)),
+        None => return Err("missing version".into()),
+    }
+
+    let entries = json.get("scores").and_then(Value::as_array).ok_or("missing scores")?;
+    entries.iter().enumerate().map(|(i, entry)| {
+        let field = |key: &str| entry.get(key).and_then(Value::as_u64).ok_or(format!("score {} has no valid {}", i + 1, key));
+        Ok(Record {
+            timestamp_ms: field("timestamp_ms")?,
+            mode: entry.get("mode").and_then(Value::as_str).ok_or(format!("score {} has no valid mode", i + 1))?.into(),
+            score: field("score")?,
+            length: field("length")?,
+            moves: field("moves")?,
+            rules: match entry.get("rules") {
+                Some(rules) => Some(rules.as_str().and_then(|r| r.parse().ok()).ok_or(format!("score {} has no valid rules", i + 1))?),
+                None => None,
+            },
+            level: entry.get("level").and_then(Value::as_str).map(String::from),
+        })
+    }).collect()
+}
@@ -0,0 +1,156 @@
+use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
+use std::thread;
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::{ai, Coords, TermInt};
+use crate::board::Board;
+use crate::env::{Observation, SnakeEnv, CELL_APPLE, CELL_EMPTY};
+use crate::growth::Growth;
+use crate::hamilton::Cycle;
+use crate::snake::{Snake, Direction::{self, *}};
+
+const DIRECTIONS: [Direction; 4] = [Up, Down, Left, Right];
+
+// The ways a simulated game can be played
+#[derive(Copy, Clone, PartialEq)]
+pub enum Strategy {
+    Pathfinding, // Same as the screensaver
+    Greedy,      // Straight towards the apple, avoiding only the very next cell
+    Random,      // Any move that doesn't kill it right away
+    Hamiltonian, // Around a cycle through every cell, with shortcuts, never loses
+}
+
+#[derive(Default)]
+pub struct Stats {
+    pub games: u64,
+    pub total_score: u64,
+    pub best_score: u64,
+    pub total_steps: u64,
+    pub full_boards: u64,
+}
+
+impl Strategy {
+    pub const NAMES: &'static [&'static str] = &["pathfinding", "greedy", "random", "hamiltonian"];
+    pub const ALL: [Strategy; 4] = [Strategy::Pathfinding, Strategy::Greedy, Strategy::Random, Strategy::Hamiltonian];
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "pathfinding" => Some(Strategy::Pathfinding),
+            "greedy" => Some(Strategy::Greedy),
+            "random" => Some(Strategy::Random),
+            "hamiltonian" => Some(Strategy::Hamiltonian),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Strategy::Pathfinding => "pathfinding",
+            Strategy::Greedy => "greedy",
+            Strategy::Random => "random",
+            Strategy::Hamiltonian => "hamiltonian",
+        }
+    }
+}
+
+impl Stats {
+    pub fn mean_score(&self) -> f64 {
+        self.total_score as f64 / self.games.max(1) as f64
+    }
+
+    pub fn mean_steps(&self) -> f64 {
+        self.total_steps as f64 / self.games.max(1) as f64
+    }
+
+    fn add(&mut self, other: Stats) {
+        self.games += other.games;
+        self.total_score += other.total_score;
+        self.best_score = self.best_score.max(other.best_score);
+        self.total_steps += other.total_steps;
+        self.full_boards += other.full_boards;
+    }
+}
+
+// Plays the given number of games spread over all cores. Game n always uses
+// seed n, so the same run gives the same numbers no matter the thread count.
+pub fn run(strategy: Strategy, games: u64, width: TermInt, height: TermInt, growth: Growth) -> Stats {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get()) as u64;
+    let next_game = Arc::new(AtomicU64::new(0));
+
+    let workers: Vec<_> = (0..threads.min(games.max(1))).map(|_| {
+        let next_game = Arc::clone(&next_game);
+        thread::spawn(move || {
+            let mut stats = Stats::default();
+            loop {
+                let seed = next_game.fetch_add(1, Ordering::Relaxed);
+                if seed >= games {
+                    break stats;
+                }
+                stats.add(play_game(strategy, seed, width, height, growth));
+            }
+        })
+    }).collect();
+
+    let mut stats = Stats::default();
+    for worker in workers {
+        stats.add(worker.join().expect("Error running simulation"));
+    }
+    stats
+}
+
+// The bots that can also play a real game. Boards with no cycle through
+// them get pathfinding instead of the Hamiltonian bot.
+pub fn bot_direction(cycle: Option<&Cycle>, snake: &Snake, apple: Coords, board: &Board) -> Direction {
+    match cycle {
+        Some(cycle) => cycle.next_direction(snake, apple, board),
+        None => ai::next_direction(snake, apple, board),
+    }
+}
+
+fn play_game(strategy: Strategy, seed: u64, width: TermInt, height: TermInt, growth: Growth) -> Stats {
+    let mut env = SnakeEnv::seeded(width, height, seed);
+    env.set_growth(growth);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut obs = env.observation();
+    let cycle = Cycle::new(env.board());
+    let mut score = 0;
+    let mut steps = 0;
+
+    while !env.is_done() {
+        let apple = match env.apple() {
+            Some(apple) => apple,
+            None => break,
+        };
+
+        let dir = match strategy {
+            Strategy::Pathfinding => ai::next_direction(env.snake(), apple, env.board()),
+            Strategy::Hamiltonian => bot_direction(cycle.as_ref(), env.snake(), apple, env.board()),
+            Strategy::Greedy => greedy_direction(&obs, env.snake().head(), apple),
+            Strategy::Random => *safe_directions(&obs, env.snake().head()).choose(&mut rng).unwrap_or(&Up),
+        };
+
+        let (next_obs, reward, _) = env.step(dir);
+        if reward > 0.0 {
+            score += 1;
+        }
+        obs = next_obs;
+        steps += 1;
+    }
+
+    Stats { games: 1, total_score: score, best_score: score, total_steps: steps, full_boards: env.apple().is_none() as u64 }
+}
+
+fn greedy_direction(obs: &Observation, head: Coords, apple: Coords) -> Direction {
+    let distance = |pos: Coords| (pos.0 as i32 - apple.0 as i32).abs() + (pos.1 as i32 - apple.1 as i32).abs();
+    safe_directions(obs, head).into_iter()
+        .min_by_key(|dir| distance(dir.step_from(head)))
+        .unwrap_or(Up)
+}
+
+fn safe_directions(obs: &Observation, head: Coords) -> Vec<Direction> {
+    DIRECTIONS.iter()
+        .filter(|dir| matches!(obs.at(dir.step_from(head)), CELL_EMPTY | CELL_APPLE))
+        .copied()
+        .collect()
+}
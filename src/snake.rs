@@ -1,8 +1,9 @@
 use crate::{Coords, TermInt};
+use crate::board::Board;
 use Direction::*;
 use MoveResult::*;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum Direction {
     Up,
     Down,
@@ -10,15 +11,64 @@ pub enum Direction {
     Right
 }
 
+impl Direction {
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Up => Down,
+            Down => Up,
+            Left => Right,
+            Right => Left,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Up => "up",
+            Down => "down",
+            Left => "left",
+            Right => "right",
+        }
+    }
+
+    pub fn step_from(&self, pos: Coords) -> Coords {
+        match self {
+            Up => (pos.0, pos.1 - 1),
+            Down => (pos.0, pos.1 + 1),
+            Left => (pos.0 - 1, pos.1),
+            Right => (pos.0 + 1, pos.1),
+        }
+    }
+}
+
 pub enum MoveResult {
     Moved { new_head: Coords, old_head: Coords, old_tail: Option<Coords> },
     Crashed
 }
 
+/// The body from the tail to the head, and where it's heading. Moving it
+/// checks for walls and its own body, eating is up to whoever plays it.
+///
+/// ```
+/// use snake::{Board, Direction, MoveResult, Shape, Snake};
+///
+/// let board = Board::new(Shape::Rectangle, 10, 6);
+/// let mut snake = Snake::new((4, 2), 3, Direction::Right);
+/// assert_eq!(snake.body(), &[(2, 2), (3, 2), (4, 2)]);
+///
+/// snake.grow(1);
+/// assert!(matches!(snake.move_step(&board), MoveResult::Moved { old_tail: None, .. }));
+/// assert_eq!(snake.body().len(), 4);
+///
+/// snake.set_direction(Direction::Up);
+/// snake.move_step(&board);
+/// assert!(matches!(snake.move_step(&board), MoveResult::Crashed));
+/// ```
+#[derive(Clone)]
 pub struct Snake {
     body: Vec<Coords>,
     direction: Direction,
-    grow_next_move: bool,
+    growth_left: u32, // Steps left that leave the tail where it is
+    moves: u64,
 }
 
 impl Snake {
@@ -34,32 +84,35 @@ impl Snake {
             .map(|i| (pos.0 as i16 - diff.0 * i, pos.1 as i16 - diff.1 * i))
             .map(|(x, y)| (x as TermInt, y as TermInt))
             .collect();
-        Snake { body, direction, grow_next_move: false }
+        Snake { body, direction, growth_left: 0, moves: 0 }
     }
 
     pub fn body(&self) -> &[Coords] {
         &self.body
     }
 
-    pub fn move_step(&mut self, max_x: TermInt, max_y: TermInt) -> MoveResult {
-        let old_head = *self.body.last().unwrap();
+    pub fn moves(&self) -> u64 {
+        self.moves
+    }
 
-        let new_head = match &self.direction {
-            Up => (old_head.0, old_head.1 - 1),
-            Down => (old_head.0, old_head.1 + 1),
-            Left => (old_head.0 - 1, old_head.1),
-            Right => (old_head.0 + 1, old_head.1),
-        };
+    pub fn head(&self) -> Coords {
+        *self.body.last().unwrap()
+    }
+
+    pub fn move_step(&mut self, board: &Board) -> MoveResult {
+        let old_head = self.head();
 
-        if new_head.0 == 0 || new_head.1 == 0 || new_head.0 > max_x || 
-           new_head.1 > max_y || self.body()[1..].contains(&new_head) {
-               return Crashed;
-           }
+        let new_head = self.direction.step_from(old_head);
+
+        if self.crashes_into(new_head, board) {
+            return Crashed;
+        }
 
         self.body.push(new_head);
+        self.moves += 1;
 
-        if self.grow_next_move {
-            self.grow_next_move = false;
+        if self.growth_left > 0 {
+            self.growth_left -= 1;
             Moved { new_head, old_head, old_tail: None }
         } else {
             let old_tail = self.body.drain(0..1).next().unwrap();
@@ -67,27 +120,69 @@ impl Snake {
         }
     }
 
+    // Whether turning that way would end the game on the very next step
+    pub fn would_crash(&self, direction: Direction, board: &Board) -> bool {
+        self.crashes_into(direction.step_from(self.head()), board)
+    }
+
     pub fn set_direction(&mut self, new_direction: Direction) {
-        match (&new_direction, &self.direction) {
-            (Up, Down) | (Down, Up) | (Right, Left) | (Left, Right) => {},
-            _ => self.direction = new_direction,
-        };
+        if new_direction != self.direction.opposite() {
+            self.direction = new_direction;
+        }
     }
 
     pub fn get_direction(&self) -> Direction {
         self.direction
     }
 
-    pub fn grow(&mut self) {
-        self.grow_next_move = true;
+    pub fn is_growing(&self) -> bool {
+        self.growth_left > 0
+    }
+
+    pub fn growth_left(&self) -> u32 {
+        self.growth_left
+    }
+
+    // One segment longer on each of the next that many steps, on top of any
+    // growing still left to do
+    pub fn grow(&mut self, segments: u32) {
+        self.growth_left = self.growth_left.saturating_add(segments);
     }
 
-    pub fn head_char(&self) -> char {
-        match self.direction {
-            Up => '^',
-            Down => 'v',
-            Left => '<',
-            Right => '>',
+    // Loses its tail right away, or None when only the head is left to lose
+    pub fn shrink(&mut self) -> Option<Coords> {
+        if self.body.len() < 2 {
+            return None;
         }
+        Some(self.body.remove(0))
+    }
+
+    // Shut in by walls and its own body away from the apple, with fewer free
+    // cells left than moves it takes for the tail to uncover a way out. Twisty
+    // spaces may run out even sooner, so a false here is no promise of escape.
+    pub fn is_trapped(&self, board: &Board, apple: Coords) -> bool {
+        let (head, body) = self.body.split_last().unwrap();
+        let region = board.region_of(*head, body);
+        if region.contains(apple) {
+            return false;
+        }
+
+        // The body cell at i is gone after i + 1 moves, more if still growing
+        let delay = self.growth_left as usize;
+        let opens_after = body.iter().enumerate()
+            .filter(|(_, pos)| [Up, Down, Left, Right].iter().any(|dir| region.contains(dir.step_from(**pos))))
+            .map(|(i, _)| i + delay)
+            .min();
+
+        opens_after.is_none_or(|moves| region.size() - 1 < moves)
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    // The tail is moving out of the way, so it doesn't count, unless the snake
+    // is growing and it stays
+    fn crashes_into(&self, pos: Coords, board: &Board) -> bool {
+        let staying = if self.is_growing() {&self.body[..]} else {&self.body[1..]};
+        !board.is_playable(pos) || staying.contains(&pos)
     }
 }
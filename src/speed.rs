@@ -0,0 +1,212 @@
+use std::cmp::max;
+
+use crate::snake::Direction::{self, *};
+use crate::TermInt;
+
+const BASE_TICKS: u64 = 10;
+const MIN_TICKS: u64 = 1;
+// The board speeds are tuned for, a classic terminal
+const REFERENCE_SIZE: (f64, f64) = (80.0, 24.0);
+// No slower than half the speed, and no faster than four times it
+const SCALE_RANGE: (f64, f64) = (0.25, 2.0);
+
+// How the game speeds up as the score grows
+#[derive(Copy, Clone, PartialEq)]
+pub enum Acceleration {
+    Linear,  // One tick faster every 7 points, the classic behavior
+    Stepped, // Two ticks faster every 20 points
+    Capped,  // Like linear, but never more than twice as fast as the start
+    None,    // Same speed all game long
+}
+
+// How the speed adapts to the size of the board, so that crossing it takes
+// about as long on any terminal
+#[derive(Copy, Clone, PartialEq)]
+pub enum BoardScaling {
+    None,  // Cells go by at the same pace on any board
+    Axes,  // Across by the width and up or down by the height
+    Area,  // Both ways by the size of the board as a whole
+}
+
+pub struct Speed {
+    acceleration: Acceleration,
+    vertical_factor: f64,
+    scale: (f64, f64), // Of the ticks for each step across and up or down
+}
+
+impl Acceleration {
+    pub const NAMES: &'static [&'static str] = &["linear", "stepped", "capped", "none"];
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(Acceleration::Linear),
+            "stepped" => Some(Acceleration::Stepped),
+            "capped" => Some(Acceleration::Capped),
+            "none" => Some(Acceleration::None),
+            _ => None,
+        }
+    }
+}
+
+impl BoardScaling {
+    pub const NAMES: &'static [&'static str] = &["none", "axes", "area"];
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(BoardScaling::None),
+            "axes" => Some(BoardScaling::Axes),
+            "area" => Some(BoardScaling::Area),
+            _ => None,
+        }
+    }
+
+    // A bigger board takes fewer ticks a step than the reference one
+    pub fn scale(&self, size: (TermInt, TermInt)) -> (f64, f64) {
+        let (x, y) = (REFERENCE_SIZE.0 / max(size.0, 1) as f64, REFERENCE_SIZE.1 / max(size.1, 1) as f64);
+        let (x, y) = match self {
+            BoardScaling::None => (1.0, 1.0),
+            BoardScaling::Axes => (x, y),
+            BoardScaling::Area => ((x * y).sqrt(), (x * y).sqrt()),
+        };
+        (x.clamp(SCALE_RANGE.0, SCALE_RANGE.1), y.clamp(SCALE_RANGE.0, SCALE_RANGE.1))
+    }
+}
+
+impl Speed {
+    pub fn new(acceleration: Acceleration, vertical_factor: f64, scale: (f64, f64)) -> Self {
+        Speed { acceleration, vertical_factor, scale }
+    }
+
+    // Snakes start out heading across
+    pub fn initial_ticks(&self) -> f64 {
+        BASE_TICKS as f64 * self.scale.0
+    }
+
+    pub fn ticks_per_step(&self, score: u64) -> u64 {
+        let speedup = match self.acceleration {
+            Acceleration::Linear => score / 7,
+            Acceleration::Stepped => score / 20 * 2,
+            Acceleration::Capped => (score / 7).min(BASE_TICKS / 2),
+            Acceleration::None => 0,
+        };
+
+        max(BASE_TICKS.saturating_sub(speedup), MIN_TICKS)
+    }
+
+    // Fractional, so the vertical slowdown and the board scaling stay
+    // proportional even at top speed
+    pub fn step_ticks(&self, score: u64, direction: Direction) -> f64 {
+        let ticks = self.ticks_per_step(score) as f64;
+
+        // Make the snake move a bit slower when going vertically, since terminal
+        // characters have a higher height than width
+        if matches!(direction, Up | Down) {
+            ticks * self.vertical_factor * self.scale.1
+        } else {
+            ticks * self.scale.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticks(acceleration: Acceleration, score: u64) -> u64 {
+        Speed::new(acceleration, 1.0, (1.0, 1.0)).ticks_per_step(score)
+    }
+
+    #[test]
+    fn linear_gets_a_tick_faster_every_7_points() {
+        assert_eq!(ticks(Acceleration::Linear, 0), 10);
+        assert_eq!(ticks(Acceleration::Linear, 6), 10);
+        assert_eq!(ticks(Acceleration::Linear, 7), 9);
+        assert_eq!(ticks(Acceleration::Linear, 35), 5);
+    }
+
+    #[test]
+    fn stepped_gets_two_ticks_faster_every_20_points() {
+        assert_eq!(ticks(Acceleration::Stepped, 19), 10);
+        assert_eq!(ticks(Acceleration::Stepped, 20), 8);
+        assert_eq!(ticks(Acceleration::Stepped, 79), 4);
+    }
+
+    #[test]
+    fn capped_stops_at_twice_the_starting_speed() {
+        assert_eq!(ticks(Acceleration::Capped, 34), 6);
+        assert_eq!(ticks(Acceleration::Capped, 35), 5);
+        assert_eq!(ticks(Acceleration::Capped, 1000), 5);
+    }
+
+    #[test]
+    fn none_never_speeds_up() {
+        assert_eq!(ticks(Acceleration::None, 0), 10);
+        assert_eq!(ticks(Acceleration::None, 1000), 10);
+    }
+
+    #[test]
+    fn never_goes_below_the_floor() {
+        assert_eq!(ticks(Acceleration::Linear, 63), 1);
+        assert_eq!(ticks(Acceleration::Linear, 1000), 1);
+        assert_eq!(ticks(Acceleration::Stepped, 1000), 1);
+        assert_eq!(ticks(Acceleration::Linear, u64::MAX), 1);
+    }
+
+    #[test]
+    fn vertical_steps_take_the_factor_longer() {
+        let speed = Speed::new(Acceleration::Linear, 1.5, (1.0, 1.0));
+        assert_eq!(speed.step_ticks(0, Right), 10.0);
+        assert_eq!(speed.step_ticks(0, Up), 15.0);
+        assert_eq!(speed.step_ticks(1000, Down), 1.5);
+    }
+
+    // Ticks to go from one side to the other, both ways
+    fn crossing(scaling: BoardScaling, size: (TermInt, TermInt)) -> (f64, f64) {
+        let speed = Speed::new(Acceleration::None, 1.0, scaling.scale(size));
+        (speed.step_ticks(0, Right) * size.0 as f64, speed.step_ticks(0, Down) * size.1 as f64)
+    }
+
+    fn assert_close(a: (f64, f64), b: (f64, f64)) {
+        assert!((a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn axes_takes_as_long_to_cross_any_board() {
+        for size in [(40, 12), (80, 24), (160, 48), (120, 20)] {
+            assert_close(crossing(BoardScaling::Axes, size), (800.0, 240.0));
+        }
+    }
+
+    #[test]
+    fn area_takes_as_long_to_cross_boards_of_the_same_shape() {
+        for size in [(40, 12), (80, 24), (160, 48)] {
+            assert_close(crossing(BoardScaling::Area, size), (800.0, 240.0));
+        }
+    }
+
+    #[test]
+    fn none_keeps_the_pace_per_cell() {
+        for size in [(40, 12), (80, 24), (160, 48)] {
+            assert_close(crossing(BoardScaling::None, size), (size.0 as f64 * 10.0, size.1 as f64 * 10.0));
+        }
+    }
+
+    #[test]
+    fn scaling_stays_in_range_on_extreme_boards() {
+        assert_eq!(BoardScaling::Axes.scale((10, 3)), (2.0, 2.0));
+        assert_eq!(BoardScaling::Axes.scale((1000, 500)), (0.25, 0.25));
+        assert_eq!(BoardScaling::Area.scale((0, 0)), (2.0, 2.0));
+    }
+
+    #[test]
+    fn scaled_ticks_keep_their_fraction() {
+        // 80 / 60 of the base, which would be 13 rounded down and drift
+        let speed = Speed::new(Acceleration::Linear, 1.0, BoardScaling::Axes.scale((60, 24)));
+        let ticks = speed.step_ticks(0, Right);
+        assert!((ticks - 40.0 / 3.0).abs() < 1e-9);
+        assert!((ticks * 3.0 - 40.0).abs() < 1e-9);
+        assert!((speed.initial_ticks() - ticks).abs() < 1e-9);
+        // Only the base tick count is whole, the scaling goes on top of it
+        assert!((speed.step_ticks(7, Right) - 9.0 * 80.0 / 60.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,115 @@
+use std::{fs, io::{self, Write}, path::{Path, PathBuf}};
+
+// Writes go to a temporary file that then replaces the old one, so a crash
+// halfway through leaves either the old contents or the new ones, never a mix.
+// The previous version is kept as a backup in case the new one gets damaged later.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let tmp = write_tmp(path, contents.as_ref())?;
+    let backed_up = if path.exists() {fs::copy(path, sibling(path, "bak")).map(drop)} else {Ok(())};
+    backed_up.and_then(|_| fs::rename(&tmp, path)).inspect_err(|_| {
+        fs::remove_file(&tmp).ok();
+    })
+}
+
+// The same without the backup, for files the user picked the path of, like an
+// export. Those are theirs, nothing else should show up next to them.
+pub fn write_replacing(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let tmp = write_tmp(path, contents.as_ref())?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        fs::remove_file(&tmp).ok();
+    })
+}
+
+// Reads a file saved with write_atomic, falling back on the backup if it's
+// missing or doesn't parse. A broken file is moved aside instead of deleted,
+// so it doesn't end up as the next backup and can still be looked at.
+pub fn read_recovering<T>(path: &Path, parse: impl Fn(&str) -> Result<T, String>) -> Option<T> {
+    if let Ok(text) = fs::read_to_string(path) {
+        match parse(&text) {
+            Ok(value) => return Some(value),
+            Err(_) => { fs::rename(path, sibling(path, "corrupt")).ok(); },
+        }
+    }
+
+    fs::read_to_string(sibling(path, "bak")).ok().and_then(|text| parse(&text).ok())
+}
+
+///////////////////////////////////////////////////////////////////////////
+
+// Not left behind when it can't be written
+fn write_tmp(path: &Path, contents: &[u8]) -> io::Result<PathBuf> {
+    let tmp = sibling(path, "tmp");
+    fs::File::create(&tmp).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    }).inspect_err(|_| {
+        fs::remove_file(&tmp).ok();
+    })?;
+    Ok(tmp)
+}
+
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A directory of its own for each test, they run at the same time
+    fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("snake-storage-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn files(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn keeps_a_backup_of_save_data() {
+        let dir = dir("backup");
+        let path = dir.join("scores.json");
+        write_atomic(&path, "1").unwrap();
+        write_atomic(&path, "2").unwrap();
+        assert_eq!(files(&dir), ["scores.json", "scores.json.bak"]);
+        assert_eq!(fs::read_to_string(sibling(&path, "bak")).unwrap(), "1");
+
+        fs::write(&path, "broken").unwrap();
+        assert_eq!(read_recovering(&path, |text| text.parse::<u32>().map_err(|e| e.to_string())), Some(1));
+        assert_eq!(files(&dir), ["scores.json.bak", "scores.json.corrupt"]);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn leaves_nothing_next_to_picked_files() {
+        let dir = dir("replacing");
+        let path = dir.join("export.json");
+        write_replacing(&path, "1").unwrap();
+        write_replacing(&path, "2").unwrap();
+        assert_eq!(files(&dir), ["export.json"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "2");
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn failed_writes_leave_no_temporary_file() {
+        let dir = dir("failed");
+        // A file can't replace a directory with something in it
+        let path = dir.join("taken");
+        fs::create_dir_all(path.join("inside")).unwrap();
+        assert!(write_replacing(&path, "1").is_err());
+        assert!(write_atomic(&path, "1").is_err());
+        assert_eq!(files(&dir), ["taken"]);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,55 @@
+use std::time::{Duration, Instant};
+
+use crate::json::Value;
+
+// What happened in one finished round, with a score for each player
+struct Round {
+    mode: &'static str,
+    outcome: &'static str,
+    scores: Vec<u64>,
+    duration: Duration,
+}
+
+// Every round finished since the game started, for scripts that run the game
+// and want the results once it's closed
+pub struct Summary {
+    started: Instant,
+    rounds: Vec<Round>,
+}
+
+impl Summary {
+    pub fn new() -> Self {
+        Summary { started: Instant::now(), rounds: vec![] }
+    }
+
+    pub fn add(&mut self, mode: &'static str, outcome: &'static str, scores: &[u64], duration: Duration) {
+        self.rounds.push(Round { mode, outcome, scores: scores.to_vec(), duration });
+    }
+
+    // On a single line, times in seconds with a decimal
+    pub fn to_json(&self) -> Value {
+        let rounds = self.rounds.iter().map(|round| {
+            let scores = match round.scores[..] {
+                [score] => ("score".to_string(), score.into()),
+                _ => ("scores".to_string(), Value::Array(round.scores.iter().map(|score| (*score).into()).collect())),
+            };
+            Value::Object(vec![
+                ("mode".to_string(), round.mode.into()),
+                ("outcome".to_string(), round.outcome.into()),
+                scores,
+                ("secs".to_string(), secs(round.duration)),
+            ])
+        });
+
+        Value::Object(vec![
+            ("rounds".to_string(), Value::Array(rounds.collect())),
+            ("secs".to_string(), secs(self.started.elapsed())),
+        ])
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////
+
+fn secs(duration: Duration) -> Value {
+    Value::Number((duration.as_secs_f64() * 10.0).round() / 10.0)
+}
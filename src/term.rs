@@ -1,70 +1,329 @@
 use crate::{TermInt, Coords};
-use std::{io::{Stdout, Write, stdout}, time::Duration};
+use crate::caps::{Caps, Glyphs};
+use crate::input;
+use std::{env, fs::OpenOptions, io::{self, Write, stdout}, thread, time::{Duration, Instant}};
+use std::sync::{atomic::{AtomicBool, Ordering}, mpsc::{channel, Receiver, RecvTimeoutError, Sender}};
 
-use crossterm::{cursor, execute, queue, style, terminal};
+use crossterm::{cursor, execute, queue, style, terminal, tty::IsTty};
+use crossterm::style::{Attribute, ContentStyle};
 use crossterm::terminal::{ClearType, EnterAlternateScreen, LeaveAlternateScreen};
-use crossterm::event::{Event, KeyEvent, read, poll};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, read};
+
+const HEADLESS_SIZE: Coords = (80, 24);
+// Terminals start repeating a held key after a delay, and then quite fast
+const FIRST_REPEAT_WAIT: Duration = Duration::from_millis(600);
+const NEXT_REPEAT_WAIT: Duration = Duration::from_millis(120);
+const OVERLAY_DIM_AFTER: Duration = Duration::from_millis(350);
+const OVERLAY_DURATION: Duration = Duration::from_millis(800);
+const TOAST_DURATION: Duration = Duration::from_secs(2);
+const MAX_TOASTS: usize = 4;
+// The xterm title stack, which most terminals have by now and the rest ignore
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+// Arrows as ESC [ A rather than ESC O A, which crossterm can't read, and
+// pastes without the markers around them
+const PLAIN_KEYS: &str = "\x1b[?1l\x1b[?2004l";
+
+// Set by the SIGTSTP handler, raw mode means it only comes from outside the terminal
+static SUSPEND_SIGNALED: AtomicBool = AtomicBool::new(false);
 
 pub struct TermManager {
     width: TermInt,
     height: TermInt,
-    stdout: Stdout,
-    screen: Vec<char>,
-    current_msg: Option<Message>,
+    out: Output,
+    events: Receiver<Event>,
+    screen: Vec<Cell>,
+    // What the terminal shows right now, None where that's not known, and the
+    // cells to go out on the next flush, with where they are in the order they came
+    shown: Vec<Option<Cell>>,
+    pending: Vec<Option<Cell>>,
+    damage: Vec<usize>,
+    cursor: Option<Coords>, // Where the visible cursor goes after each flush
+    messages: Vec<Message>, // Bottom to top, at most one at each anchor
+    alt_screen: bool,
+    output_is_tty: bool,
+    headless: bool,
+    // Only in the simple renderer, what's currently meant to be on screen
+    frame: Option<Vec<char>>,
+    frame_dirty: bool,
+    fog: Option<Fog>,
+    overlays: Vec<Overlay>,
+    toasts: Vec<(String, Instant)>, // Oldest first, all in one message in the corner
+    title: Option<String>,
+    last_key: Instant, // For noticing when nobody's there
+    caps: Caps,
+}
+
+// Everything in the area further away than the radius is hidden, the radius
+// counts rows and twice as many columns so that it looks round
+#[derive(Copy, Clone)]
+pub struct Fog {
+    pub top_left: Coords,
+    pub bottom_right: Coords,
+    pub center: Coords,
+    pub radius: TermInt,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Renderer {
+    Auto,
+    Ansi,
+    Simple, // Reprints the whole screen line by line, for terminals without cursor movement
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct Cell {
+    pub ch: char,
+    pub style: ContentStyle,
+}
+
+// Terminals only report key presses, never releases, so a key counts as held
+// for as long as its auto-repeat keeps coming in
+#[derive(Default)]
+pub struct KeyHold {
+    last_press: Option<Instant>,
+    repeating: bool,
+}
+
+// Short lived text over the board, like score popups. Whatever gets printed
+// underneath goes to the screen buffer and shows up once the overlay is gone.
+struct Overlay {
+    pos: Coords,
+    text: String,
+    shown_at: Instant,
+    dimmed: bool,
+}
+
+// A rectangle of the screen with coordinates of its own, starting at (0, 0)
+// in its top left corner. Anything drawn past its edges is cut off.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Region {
+    pub top_left: Coords,
+    pub width: TermInt,
+    pub height: TermInt,
+}
+
+// How the screen is shared out: the board on the left, the status bars over
+// its top and bottom walls, clear of the corners, and the side panel next to it
+#[derive(Copy, Clone)]
+pub struct Layout {
+    pub board: Region,
+    pub top_bar: Region,
+    pub bottom_bar: Region,
+    pub panel: Region,
+}
+
+// Where on the screen a message goes, a row or column in from the edges
+#[derive(Copy, Clone, PartialEq)]
+pub enum Anchor {
+    Center,
+    Top,
+    Bottom,
+    TopLeft, // Where the toasts go
 }
 
 struct Message {
+    anchor: Anchor,
     top_left: Coords,
     width: TermInt,
     height: TermInt,
+    lines: Vec<String>,
 }
 
 impl TermManager {
-    pub fn new() -> Self {
-        let (width, height) = terminal::size().expect("Error reading size.");
-        let stdout = stdout();
-        let screen = vec![' '; width as usize * height as usize];
-        TermManager { width, height, stdout, screen, current_msg: None }
+    // With stdout taken for something else, the game is drawn on the controlling
+    // terminal instead, or nowhere at all if there isn't one
+    pub fn new(stdout_taken: bool, renderer: Renderer, glyphs: Glyphs) -> Self {
+        let (target, output_is_tty, headless): (Box<dyn Write + Send>, bool, bool) = if stdout_taken {
+            match OpenOptions::new().write(true).open("/dev/tty") {
+                Ok(tty) => (Box::new(tty), true, false),
+                Err(_) => (Box::new(io::sink()), false, true),
+            }
+        } else {
+            (Box::new(stdout()), stdout().is_tty(), false)
+        };
+
+        let (width, height) = if headless {HEADLESS_SIZE} else {terminal::size().expect("Error reading size.")};
+        let screen = vec![Cell::plain(' '); width as usize * height as usize];
+        let simple = match renderer {
+            Renderer::Auto => !headless && (!output_is_tty || is_dumb_term()),
+            Renderer::Ansi => false,
+            Renderer::Simple => true,
+        };
+        let frame = if simple {Some(vec![' '; screen.len()])} else {None};
+
+        TermManager {
+            width, height, out: Output::spawn(target), events: spawn_input_thread(),
+            shown: vec![None; screen.len()], pending: vec![None; screen.len()], damage: vec![], cursor: None,
+            screen, messages: vec![], alt_screen: false, output_is_tty, headless, frame, frame_dirty: false, fog: None,
+            overlays: vec![], toasts: vec![], title: None, last_key: Instant::now(), caps: Caps::detect(glyphs),
+        }
     }
 
     pub fn setup(&mut self) {
-        execute!(self.stdout, EnterAlternateScreen).expect("Error entering alt screen");
+        // Dumb terminals and CI logs don't have an alternate screen, in that case
+        // we just take over the main one and clean it up on exit
+        self.alt_screen = self.output_is_tty && self.frame.is_none() && !is_dumb_term() && execute!(self.out, EnterAlternateScreen).is_ok();
+        if !self.alt_screen {
+            self.clear();
+        }
+
         self.set_raw_mode(true);
+        if self.output_is_tty && !is_dumb_term() {
+            execute!(self.out, style::Print(PLAIN_KEYS)).ok();
+        }
         self.set_cursor_visibility(false);
         self.set_cursor_blink(false);
+
+        // Coming back from a suspend, the title was put back on the way out
+        if let Some(title) = self.title.take() {
+            self.set_title(&title);
+        }
+
+        #[cfg(unix)]
+        unsafe {
+            libc::signal(libc::SIGTSTP, on_sigtstp as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        }
+    }
+
+    // Gives the terminal back while the game is stopped, and puts everything
+    // back as it was when it's continued
+    #[cfg(unix)]
+    pub fn suspend(&mut self) {
+        self.restore();
+        // SIGTSTP would only land in our own handler
+        unsafe {
+            libc::raise(libc::SIGSTOP);
+        }
+
+        let screen = std::mem::take(&mut self.screen);
+        self.setup();
+        self.screen = screen;
+        self.redraw_all();
     }
 
+    #[cfg(not(unix))]
+    pub fn suspend(&mut self) {}
+
     pub fn restore(&mut self) {
         self.set_raw_mode(false);
         self.set_cursor_visibility(true);
         self.set_cursor_blink(true);
-        execute!(self.stdout, LeaveAlternateScreen).expect("Error leaving alt screen");
+
+        if self.title.is_some() {
+            execute!(self.out, style::Print(POP_TITLE)).ok();
+        }
+
+        if self.alt_screen {
+            execute!(self.out, LeaveAlternateScreen).expect("Error leaving alt screen");
+        } else if self.frame.is_some() {
+            // Nothing to clean up, just leave the last frame there
+            execute!(self.out, style::Print("\r\n")).ok();
+        } else {
+            self.clear();
+            execute!(self.out, cursor::MoveTo(0, 0)).ok();
+        }
+
+        // We're usually about to exit, make sure it all reached the terminal
+        self.out.sync();
+    }
+
+    // The window's title, the one from before the game is put back on restore()
+    pub fn set_title(&mut self, title: &str) {
+        if !self.output_is_tty || self.frame.is_some() || self.title.as_deref() == Some(title) {
+            return;
+        }
+
+        if self.title.is_none() {
+            execute!(self.out, style::Print(PUSH_TITLE)).ok();
+        }
+        execute!(self.out, terminal::SetTitle(title)).ok();
+        self.title = Some(title.to_string());
     }
 
-    pub fn read_key_blocking(&self) -> KeyEvent {
+    pub fn read_key_blocking(&mut self) -> KeyEvent {
         loop {
-            if let Event::Key(ev) = read().unwrap() {
+            if let Event::Key(ev) = self.events.recv().expect("Error reading input.") {
+                self.last_key = Instant::now();
                 return ev;
             }
         }
     }
 
-    pub fn read_key_events_queue(&self) -> Vec<KeyEvent> {
-        let mut events = vec![];
+    pub fn read_key_events_queue(&mut self) -> Vec<KeyEvent> {
+        let mut keys = input::without_noise(self.events.try_iter()
+            .filter_map(|ev| match ev {
+                Event::Key(key_ev) => Some(key_ev),
+                _ => None,
+            })
+            .collect());
+        if !keys.is_empty() {
+            self.last_key = Instant::now();
+        }
+
+        // Handled just like pressing CTRL+Z
+        if SUSPEND_SIGNALED.swap(false, Ordering::Relaxed) {
+            keys.push(KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL));
+        }
 
-        while poll(Duration::from_millis(1)).unwrap() {
-            if let Event::Key(ev) = read().unwrap() {
-                events.push(ev);
-            }
+        keys
+    }
+
+    pub fn poll_event(&mut self, timeout: Duration) -> Option<Event> {
+        match self.events.recv_timeout(timeout) {
+            Ok(ev) => {
+                if matches!(ev, Event::Key(_)) {
+                    self.last_key = Instant::now();
+                }
+                Some(ev)
+            },
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => panic!("Error reading input."),
         }
+    }
+
+    // Since any key was last read, on any screen
+    pub fn idle_for(&self) -> Duration {
+        self.last_key.elapsed()
+    }
+
+    pub fn resize(&mut self, width: TermInt, height: TermInt) {
+        self.width = width;
+        self.height = height;
+        self.messages.clear();
+        self.clear();
+    }
 
-        events
+    pub fn is_headless(&self) -> bool {
+        self.headless
     }
 
     pub fn get_terminal_size(&self) -> Coords {
         (self.width, self.height)
     }
 
+    #[cfg(unix)]
+    pub fn cell_aspect_ratio(&self) -> Option<f64> {
+        use std::os::unix::io::AsRawFd;
+
+        // Height / width of a single character cell, if the terminal reports its pixel size
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        let res = unsafe { libc::ioctl(stdout().as_raw_fd(), libc::TIOCGWINSZ, &mut size) };
+
+        if res != 0 || size.ws_xpixel == 0 || size.ws_ypixel == 0 || size.ws_col == 0 || size.ws_row == 0 {
+            return None;
+        }
+
+        let cell_w = size.ws_xpixel as f64 / size.ws_col as f64;
+        let cell_h = size.ws_ypixel as f64 / size.ws_row as f64;
+        Some(cell_h / cell_w)
+    }
+
+    #[cfg(not(unix))]
+    pub fn cell_aspect_ratio(&self) -> Option<f64> {
+        None
+    }
+
     pub fn draw_borders(&mut self, size: Option<Coords>) {
         let (width, height) = match size {
             Some((x, y)) => (x, y),
@@ -76,95 +335,418 @@ impl TermManager {
 
         for x in 0..width {
             let ch = if x == 0 || x == width - 1 {'+'} else {'-'};
-            self.print_at((x, 0), ch);
-            self.print_at((x, end_y), ch);
+            self.print_at((x, 0), Cell::plain(ch));
+            self.print_at((x, end_y), Cell::plain(ch));
         }
 
         for y in 1..height - 1 {
-            self.print_at((0, y), '|');
-            self.print_at((end_x, y), '|');
+            self.print_at((0, y), Cell::plain('|'));
+            self.print_at((end_x, y), Cell::plain('|'));
         }
 
         self.flush();
     }
 
     pub fn show_message(&mut self, lines: &[&str]) {
-        if self.has_message() {
-            self.hide_message();
-        }
+        self.show_message_at(Anchor::Center, lines);
+    }
 
-        let msg_height = (lines.len() + 2) as TermInt;
-        let msg_width = (lines.iter().map(|x| x.len()).max().unwrap() + 2) as TermInt;
-        let center = (self.width / 2, self.height / 2);
-        let top_left = (center.0 - msg_width as TermInt / 2, center.1 - msg_height as TermInt / 2);
+    // Replaces whatever was at that anchor, and goes on top of everything else
+    pub fn show_message_at(&mut self, anchor: Anchor, lines: &[&str]) {
+        self.hide_message_at(anchor);
 
-        // Print the top and bottom empty lines
-        for y in [top_left.1, top_left.1 + msg_height - 1].iter() {
-            for x_diff in 0..msg_width {
-                self.print_at_no_save((top_left.0 + x_diff, *y), ' ');
-            }
-        }
+        self.messages.push(Message::fit(anchor, lines, (self.width, self.height)));
+        self.draw_messages();
+        self.flush();
+    }
 
-        // Print the message lines
-        for (i, line) in lines.iter().enumerate() {
-            let padded_line = format!("{line: ^width$}", line = line, width = msg_width as usize);
-            let y = top_left.1 + i as TermInt + 1;
-            for (x_diff, ch) in padded_line.char_indices() {
-                self.print_at_no_save((top_left.0 + x_diff as TermInt, y), ch);
-            }
+    // A message that waits for a key, then puts back whatever message was up before it
+    pub fn ask_at(&mut self, anchor: Anchor, lines: &[&str]) -> KeyEvent {
+        let previous = self.message_at(anchor).map(|msg| msg.lines().to_vec());
+        self.show_message_at(anchor, lines);
+        let key = self.read_key_blocking();
+
+        match previous {
+            Some(lines) => self.show_message_at(anchor, &lines.iter().map(String::as_str).collect::<Vec<_>>()),
+            None => self.hide_message_at(anchor),
         }
 
-        self.current_msg = Some(Message::new(msg_width, msg_height, top_left));
-        self.flush();
+        key
+    }
+
+    pub fn is_under_message(&self, pos: Coords) -> bool {
+        self.messages.iter().any(|msg| msg.covers(pos))
     }
 
     pub fn hide_message(&mut self) {
-        if !self.has_message() {
-            return;
-        }
+        self.hide_message_at(Anchor::Center);
+    }
 
-        let msg = self.current_msg.take().unwrap(); // take() sets current_msg to None
+    pub fn hide_message_at(&mut self, anchor: Anchor) {
+        let msg = match self.messages.iter().position(|msg| msg.anchor == anchor) {
+            Some(i) => self.messages.remove(i),
+            None => return,
+        };
         let top_left = msg.top_left();
 
-        // Restore the content from the screen buffer
+        // Restore the content from the screen buffer, other messages stay where they were
         for y_diff in 0..msg.height() {
             for x_diff in 0..msg.width() {
                 let (x, y) = (top_left.0 + x_diff, top_left.1 + y_diff);
-                let ch = self.screen[self.width as usize * y as usize + x as usize];
-                self.print_at_no_save((x, y), ch);
+                let cell = self.screen[self.width as usize * y as usize + x as usize];
+                self.print_visible((x, y), cell);
             }
         }
 
+        for overlay in std::mem::take(&mut self.overlays) {
+            self.draw_overlay(&overlay);
+            self.overlays.push(overlay);
+        }
+
         self.flush();
     }
 
-    pub fn print_at(&mut self, pos: Coords, ch: char) {
-        queue!(self.stdout, cursor::MoveTo(pos.0, pos.1), style::Print(ch)).unwrap();
-        self.screen[self.width as usize * pos.1 as usize + pos.0 as usize] = ch;
+    // Blanks everything out with something that looks like a shell, without
+    // touching the screen buffer so the board can be brought back afterwards
+    pub fn show_boss_screen(&mut self, prompt: &str) {
+        if let Some(frame) = &mut self.frame {
+            frame.iter_mut().for_each(|ch| *ch = ' ');
+            frame.iter_mut().zip(prompt.chars()).for_each(|(ch, p)| *ch = p);
+            self.frame_dirty = true;
+            self.flush();
+            return;
+        }
+
+        self.discard_pending();
+        execute!(self.out, terminal::Clear(ClearType::All), cursor::MoveTo(0, 0), style::Print(prompt))
+            .expect("Error clearing.");
+        self.set_cursor_visibility(true);
+        // Nothing on the terminal is what the buffers think anymore
+        self.shown.iter_mut().for_each(|cell| *cell = None);
+    }
+
+    // Everything again from the screen buffer, for when something else made a mess of the terminal
+    pub fn redraw_all(&mut self) {
+        if self.frame.is_none() && !self.headless {
+            self.discard_pending();
+            execute!(self.out, terminal::Clear(ClearType::All)).expect("Error clearing.");
+            self.shown.iter_mut().for_each(|cell| *cell = Some(Cell::plain(' ')));
+        }
+        self.repaint();
+    }
+
+    pub fn hide_boss_screen(&mut self) {
+        self.set_cursor_visibility(false);
+        self.repaint();
+    }
+
+    // Only the cells that can change visibility get redrawn, which is around the
+    // old and new centers when the fog just moves, or the whole area otherwise
+    pub fn set_fog(&mut self, fog: Option<Fog>) {
+        let old = std::mem::replace(&mut self.fog, fog);
+        let regions: Vec<(Coords, Coords)> = match (old, fog) {
+            (Some(a), Some(b)) if (a.top_left, a.bottom_right) == (b.top_left, b.bottom_right) => vec![a.bounds(), b.bounds()],
+            _ => old.iter().chain(fog.iter()).map(|f| (f.top_left, f.bottom_right)).collect(),
+        };
+
+        for (from, to) in regions {
+            for y in from.1..to.1 {
+                for x in from.0..to.0 {
+                    self.print_visible((x, y), self.cell_at((x, y)));
+                }
+            }
+        }
+
+        self.draw_messages();
+    }
+
+    // A visible cursor, for screens where the user points at things
+    pub fn show_cursor_at(&mut self, pos: Coords) {
+        if self.frame.is_none() {
+            self.cursor = Some(pos);
+            self.set_cursor_visibility(true);
+        }
+    }
+
+    pub fn print_at(&mut self, pos: Coords, cell: Cell) {
+        self.print_visible(pos, cell);
+        self.screen[self.width as usize * pos.1 as usize + pos.0 as usize] = cell;
+    }
+
+    pub fn print_text_at(&mut self, pos: Coords, text: &str, style: ContentStyle) {
+        for (i, ch) in text.chars().enumerate() {
+            let x = pos.0 + i as TermInt;
+            if x >= self.width {
+                break;
+            }
+            self.print_at((x, pos.1), Cell::new(ch, style));
+        }
+    }
+
+    // Same, but relative to the region and cut off at its edges
+    pub fn print_in(&mut self, region: Region, pos: Coords, cell: Cell) {
+        if let Some(pos) = region.on_screen(pos).filter(|pos| pos.0 < self.width && pos.1 < self.height) {
+            self.print_at(pos, cell);
+        }
+    }
+
+    pub fn print_text_in(&mut self, region: Region, pos: Coords, text: &str, style: ContentStyle) {
+        for (i, ch) in text.chars().enumerate() {
+            let x = pos.0 + i as TermInt;
+            if x >= region.width {
+                break;
+            }
+            self.print_in(region, (x, pos.1), Cell::new(ch, style));
+        }
+    }
+
+    pub fn cell_at(&self, pos: Coords) -> Cell {
+        self.screen[self.width as usize * pos.1 as usize + pos.0 as usize]
     }
 
     pub fn clear(&mut self) {
-        execute!(self.stdout, terminal::Clear(ClearType::All)).expect("Error clearing.");
-        self.screen = vec![' '; self.width as usize * self.height as usize]
+        if self.frame.is_some() {
+            self.frame = Some(vec![' '; self.width as usize * self.height as usize]);
+            self.frame_dirty = true;
+        } else {
+            execute!(self.out, terminal::Clear(ClearType::All)).expect("Error clearing.");
+        }
+        let size = self.width as usize * self.height as usize;
+        self.screen = vec![Cell::plain(' '); size];
+        self.shown = vec![Some(Cell::plain(' ')); size];
+        self.pending = vec![None; size];
+        self.damage.clear();
+        self.overlays.clear();
+        // Gone with the screen they were on
+        self.toasts.clear();
+        self.messages.retain(|msg| msg.anchor != Anchor::TopLeft);
     }
 
     pub fn flush(&mut self) {
-        self.stdout.flush().expect("Error flushing.");
+        if self.frame_dirty {
+            self.print_frame();
+        }
+        self.print_damage();
+        self.out.flush().expect("Error flushing.");
+    }
+
+    // Kept inside the screen, and fading out as update_overlays is called
+    pub fn show_overlay(&mut self, pos: Coords, text: &str) {
+        let len = text.chars().count() as TermInt;
+        let x = pos.0.min(self.width.saturating_sub(len));
+        let overlay = Overlay { pos: (x, pos.1), text: text.into(), shown_at: Instant::now(), dimmed: false };
+        self.draw_overlay(&overlay);
+        self.overlays.push(overlay);
+        self.flush();
+    }
+
+    pub fn update_overlays(&mut self) {
+        let mut overlays = std::mem::take(&mut self.overlays);
+        let mut changed = false;
+
+        for overlay in overlays.iter_mut().filter(|o| !o.dimmed && o.shown_at.elapsed() >= OVERLAY_DIM_AFTER) {
+            overlay.dimmed = true;
+            self.draw_overlay(overlay);
+            changed = true;
+        }
+
+        let (expired, alive): (Vec<Overlay>, Vec<Overlay>) = overlays.into_iter().partition(|o| o.shown_at.elapsed() >= OVERLAY_DURATION);
+        self.overlays = alive;
+        for overlay in &expired {
+            self.restore_under(overlay);
+            changed = true;
+        }
+
+        let toasts = self.toasts.len();
+        self.toasts.retain(|(_, shown_at)| shown_at.elapsed() < TOAST_DURATION);
+        if self.toasts.len() != toasts {
+            self.draw_toasts();
+            changed = true;
+        }
+
+        if changed {
+            self.flush();
+        }
+    }
+
+    // A short note in the corner that goes away on its own, as update_overlays is called
+    pub fn show_toast(&mut self, text: &str) {
+        self.toasts.push((text.to_string(), Instant::now()));
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+        self.draw_toasts();
     }
 
-    pub fn has_message(&self) -> bool {
-        self.current_msg.is_some()
+    pub fn clear_overlays(&mut self) {
+        for overlay in std::mem::take(&mut self.overlays) {
+            self.restore_under(&overlay);
+        }
+        self.flush();
     }
 
     ///////////////////////////////////////////////////////////////////////////
 
-    fn print_at_no_save(&mut self, pos: Coords, ch: char) {
+    fn draw_toasts(&mut self) {
+        let lines: Vec<String> = self.toasts.iter().map(|(text, _)| text.clone()).collect();
+        if lines.is_empty() {
+            self.hide_message_at(Anchor::TopLeft);
+        } else {
+            self.show_message_at(Anchor::TopLeft, &lines.iter().map(String::as_str).collect::<Vec<_>>());
+        }
+    }
+
+    fn message_at(&self, anchor: Anchor) -> Option<&Message> {
+        self.messages.iter().find(|msg| msg.anchor == anchor)
+    }
+
+    // Bottom to top, so where they overlap the later one wins
+    fn draw_messages(&mut self) {
+        for msg in std::mem::take(&mut self.messages) {
+            self.draw_message(&msg);
+            self.messages.push(msg);
+        }
+    }
+
+    fn draw_message(&mut self, msg: &Message) {
+        let top_left = msg.top_left();
+
+        // Print the top and bottom empty lines
+        for y in [top_left.1, top_left.1 + msg.height() - 1].iter() {
+            for x_diff in 0..msg.width() {
+                self.print_at_no_save((top_left.0 + x_diff, *y), Cell::plain(' '));
+            }
+        }
+
+        // Print the message lines
+        for (i, line) in msg.lines().iter().enumerate() {
+            let padded_line = format!("{line: ^width$}", line = line, width = msg.width() as usize);
+            let y = top_left.1 + i as TermInt + 1;
+            for (x_diff, ch) in padded_line.chars().take(msg.width() as usize).enumerate() {
+                self.print_at_no_save((top_left.0 + x_diff as TermInt, y), Cell::plain(ch));
+            }
+        }
+    }
+
+    fn repaint(&mut self) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let cell = self.cell_at((x, y));
+                self.print_visible((x, y), cell);
+            }
+        }
+
+        for overlay in std::mem::take(&mut self.overlays) {
+            self.draw_overlay(&overlay);
+            self.overlays.push(overlay);
+        }
+
+        self.draw_messages();
+        self.flush();
+    }
+
+    // Whatever is on top of the board stays there
+    fn print_visible(&mut self, pos: Coords, cell: Cell) {
+        if self.is_under_message(pos) || self.overlays.iter().any(|overlay| overlay.covers(pos)) {
+            return;
+        }
+
+        let hidden = self.fog.is_some_and(|fog| fog.hides(pos));
+        self.print_at_no_save(pos, if hidden {Cell::plain(' ')} else {cell});
+    }
+
+    fn print_at_no_save(&mut self, pos: Coords, cell: Cell) {
+        if let Some(frame) = &mut self.frame {
+            frame[self.width as usize * pos.1 as usize + pos.0 as usize] = self.caps.glyph(cell.ch);
+            self.frame_dirty = true;
+            return;
+        }
+
         // To be used for printing messages, where we don't wanna overwrite our
         // local buffer to restore it when the message is hidden
-        queue!(self.stdout, cursor::MoveTo(pos.0, pos.1), style::Print(ch)).unwrap();
+        let i = self.width as usize * pos.1 as usize + pos.0 as usize;
+        if self.pending[i].is_none() {
+            self.damage.push(i);
+        }
+        self.pending[i] = Some(cell);
+    }
+
+    // Only the cells that end up different from what's there already, in screen
+    // order so that runs of them on a row go out without moving the cursor
+    fn print_damage(&mut self) {
+        let mut damage = std::mem::take(&mut self.damage);
+        damage.sort_unstable();
+        let mut next_pos = None;
+
+        for i in damage.drain(..) {
+            let cell = self.pending[i].take().unwrap();
+            if self.shown[i] == Some(cell) {
+                continue;
+            }
+
+            let pos = ((i % self.width as usize) as TermInt, (i / self.width as usize) as TermInt);
+            if next_pos != Some(pos) {
+                queue!(self.out, cursor::MoveTo(pos.0, pos.1)).unwrap();
+            }
+            let (ch, style) = (self.caps.glyph(cell.ch), self.caps.style(cell.style));
+            queue!(self.out, style::PrintStyledContent(style.apply(ch))).unwrap();
+            self.shown[i] = Some(cell);
+            // Wider characters would leave the cursor somewhere else
+            next_pos = if ch.is_ascii() {Some((pos.0 + 1, pos.1))} else {None};
+        }
+
+        self.damage = damage;
+        if let Some(pos) = self.cursor {
+            queue!(self.out, cursor::MoveTo(pos.0, pos.1)).unwrap();
+        }
+    }
+
+    // For when the whole terminal is about to be wiped anyway
+    fn discard_pending(&mut self) {
+        for i in self.damage.drain(..) {
+            self.pending[i] = None;
+        }
+    }
+
+    // Messages stay on top of overlays
+    fn draw_overlay(&mut self, overlay: &Overlay) {
+        let style = ContentStyle::new().attribute(if overlay.dimmed {Attribute::Dim} else {Attribute::Bold});
+        for (i, ch) in overlay.text.chars().enumerate() {
+            let pos = (overlay.pos.0 + i as TermInt, overlay.pos.1);
+            if !self.is_under_message(pos) {
+                self.print_at_no_save(pos, Cell::new(ch, style));
+            }
+        }
+    }
+
+    // Only to be called once the overlay is out of the list
+    fn restore_under(&mut self, overlay: &Overlay) {
+        for i in 0..overlay.text.chars().count() as TermInt {
+            let pos = (overlay.pos.0 + i, overlay.pos.1);
+            if !self.is_under_message(pos) {
+                self.print_visible(pos, self.cell_at(pos));
+            }
+        }
+    }
+
+    fn print_frame(&mut self) {
+        let frame = self.frame.as_ref().unwrap();
+        let mut text = String::from("\r\n");
+        for row in frame.chunks(self.width as usize) {
+            text.extend(row.iter());
+            text.truncate(text.trim_end_matches(' ').len());
+            text.push_str("\r\n");
+        }
+
+        queue!(self.out, style::Print(text)).unwrap();
+        self.frame_dirty = false;
     }
 
     fn set_raw_mode(&self, option: bool) {
+        if self.headless {
+            return;
+        }
+
         let res = if option {
             terminal::enable_raw_mode()
         } else {
@@ -175,29 +757,238 @@ impl TermManager {
     }
 
     fn set_cursor_blink(&mut self, option: bool) {
+        if self.frame.is_some() {
+            return;
+        }
+
         let res = if option {
-            execute!(self.stdout, cursor::EnableBlinking)
+            execute!(self.out, cursor::EnableBlinking)
         } else {
-            execute!(self.stdout, cursor::DisableBlinking)
+            execute!(self.out, cursor::DisableBlinking)
         };
 
-        res.expect("Error setting cursor blink.");
+        // Purely cosmetic, not all terminals support it
+        res.ok();
     }
 
     fn set_cursor_visibility(&mut self, option: bool) {
+        if self.frame.is_some() {
+            return;
+        }
+
+        if !option {
+            self.cursor = None;
+        }
+
         let res = if option {
-            execute!(self.stdout, cursor::Show)
+            execute!(self.out, cursor::Show)
         } else {
-            execute!(self.stdout, cursor::Hide)
+            execute!(self.out, cursor::Hide)
         };
 
-        res.expect("Error setting cursor visibility.");
+        // Purely cosmetic, not all terminals support it
+        res.ok();
+    }
+}
+
+// Terminal writes are handed over to a separate thread, so a slow terminal
+// never holds up the game loop. Everything queued is sent over on flush.
+struct Output {
+    buffer: Vec<u8>,
+    sender: Sender<OutputMsg>,
+}
+
+enum OutputMsg {
+    Write(Vec<u8>),
+    Sync(Sender<()>),
+}
+
+impl Output {
+    fn spawn(mut target: Box<dyn Write + Send>) -> Self {
+        let (sender, receiver) = channel();
+
+        thread::spawn(move || {
+            for msg in receiver {
+                match msg {
+                    OutputMsg::Write(bytes) => {
+                        target.write_all(&bytes).and_then(|_| target.flush()).expect("Error writing to terminal.");
+                    },
+                    OutputMsg::Sync(done) => {
+                        done.send(()).ok();
+                    },
+                }
+            }
+        });
+
+        Output { buffer: vec![], sender }
+    }
+
+    // Blocks until everything written so far has made it to the terminal
+    fn sync(&mut self) {
+        let (done, wait) = channel();
+        self.sender.send(OutputMsg::Sync(done)).expect("Error writing to terminal.");
+        wait.recv().ok();
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let bytes = std::mem::take(&mut self.buffer);
+            self.sender.send(OutputMsg::Write(bytes))
+                .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Output thread is gone"))?;
+        }
+        Ok(())
+    }
+}
+
+// Input is read on its own thread as well, so key presses are picked up the
+// moment they happen rather than whenever the game loop gets around to it
+fn spawn_input_thread() -> Receiver<Event> {
+    let (sender, receiver) = channel();
+
+    thread::spawn(move || {
+        while let Ok(ev) = read() {
+            if sender.send(ev).is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+fn is_dumb_term() -> bool {
+    match env::var("TERM") {
+        Ok(term) => term.is_empty() || term == "dumb",
+        Err(_) => !cfg!(windows), // Windows consoles don't set TERM
+    }
+}
+
+impl Fog {
+    fn hides(&self, pos: Coords) -> bool {
+        let in_area = pos.0 >= self.top_left.0 && pos.1 >= self.top_left.1
+            && pos.0 < self.bottom_right.0 && pos.1 < self.bottom_right.1;
+        let dx = (pos.0 as f64 - self.center.0 as f64) / 2.0;
+        let dy = pos.1 as f64 - self.center.1 as f64;
+        in_area && dx * dx + dy * dy > (self.radius as f64).powi(2)
+    }
+
+    // The part of the area where visibility can differ from a fully fogged one
+    fn bounds(&self) -> (Coords, Coords) {
+        let from_x = self.center.0.saturating_sub(self.radius * 2 + 1).max(self.top_left.0);
+        let from_y = self.center.1.saturating_sub(self.radius + 1).max(self.top_left.1);
+        let to_x = (self.center.0 + self.radius * 2 + 2).min(self.bottom_right.0);
+        let to_y = (self.center.1 + self.radius + 2).min(self.bottom_right.1);
+        ((from_x, from_y), (to_x, to_y))
+    }
+}
+
+impl KeyHold {
+    pub fn press(&mut self) {
+        self.repeating = self.is_held();
+        self.last_press = Some(Instant::now());
+    }
+
+    pub fn is_held(&self) -> bool {
+        let wait = if self.repeating {NEXT_REPEAT_WAIT} else {FIRST_REPEAT_WAIT};
+        self.last_press.is_some_and(|t| t.elapsed() < wait)
+    }
+}
+
+impl Renderer {
+    pub const NAMES: &'static [&'static str] = &["auto", "ansi", "simple"];
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Renderer::Auto),
+            "ansi" => Some(Renderer::Ansi),
+            "simple" => Some(Renderer::Simple),
+            _ => None,
+        }
+    }
+}
+
+impl Cell {
+    pub fn new(ch: char, style: ContentStyle) -> Self {
+        Cell { ch, style }
+    }
+
+    pub fn plain(ch: char) -> Self {
+        Cell { ch, style: ContentStyle::new() }
+    }
+}
+
+impl Region {
+    pub fn new(top_left: Coords, width: TermInt, height: TermInt) -> Self {
+        Region { top_left, width, height }
+    }
+
+    pub fn contains(&self, pos: Coords) -> bool {
+        pos.0 < self.width && pos.1 < self.height
+    }
+
+    pub fn on_screen(&self, pos: Coords) -> Option<Coords> {
+        self.contains(pos).then(|| (self.top_left.0 + pos.0, self.top_left.1 + pos.1))
+    }
+
+    // The first rows, and whatever is left below them
+    pub fn split_rows(&self, rows: TermInt) -> (Region, Region) {
+        let rows = rows.min(self.height);
+        let top = Region::new(self.top_left, self.width, rows);
+        let rest = Region::new((self.top_left.0, self.top_left.1 + rows), self.width, self.height - rows);
+        (top, rest)
+    }
+}
+
+impl Layout {
+    // The panel is left out when it has no width, and keeps a blank column off the board
+    pub fn new(screen: Coords, panel_width: TermInt) -> Self {
+        let board = Region::new((0, 0), screen.0.saturating_sub(panel_width), screen.1);
+        let bar_width = board.width.saturating_sub(4);
+        Layout {
+            board,
+            top_bar: Region::new((2, 0), bar_width, 1.min(board.height)),
+            bottom_bar: Region::new((2, board.height.saturating_sub(1)), bar_width, 1.min(board.height)),
+            panel: Region::new((board.width + 1, 0), panel_width.saturating_sub(1), screen.1),
+        }
+    }
+}
+
+impl Anchor {
+    fn top_left(&self, size: Coords, screen: Coords) -> Coords {
+        let center_x = (screen.0 / 2).saturating_sub(size.0 / 2);
+        match self {
+            Anchor::Center => (center_x, (screen.1 / 2).saturating_sub(size.1 / 2)),
+            Anchor::Top => (center_x, 1),
+            Anchor::Bottom => (center_x, screen.1.saturating_sub(size.1 + 1)),
+            Anchor::TopLeft => (1, 1),
+        }
     }
 }
 
 impl Message {
-    pub fn new(width: TermInt, height: TermInt, top_left: Coords) -> Self {
-        Message { width, height, top_left }
+    pub fn new(width: TermInt, height: TermInt, top_left: Coords, anchor: Anchor, lines: Vec<String>) -> Self {
+        Message { anchor, width, height, top_left, lines }
+    }
+
+    // Wrapped to fit with a blank column on each side, and the lines that
+    // still don't fit are left out, so the box never goes past the screen
+    fn fit(anchor: Anchor, lines: &[&str], screen: Coords) -> Self {
+        let max_width = screen.0.saturating_sub(2).max(1) as usize;
+        let mut lines: Vec<String> = lines.iter().flat_map(|line| wrap(line, max_width)).collect();
+        lines.truncate(screen.1.saturating_sub(2) as usize);
+
+        let height = (lines.len() + 2).min(screen.1 as usize) as TermInt;
+        let width = (lines.iter().map(|x| x.chars().count()).max().unwrap_or(0) + 2).min(screen.0 as usize) as TermInt;
+        let top_left = anchor.top_left((width, height), screen);
+        let top_left = (top_left.0.min(screen.0 - width), top_left.1.min(screen.1 - height));
+        Message::new(width, height, top_left, anchor, lines)
     }
 
     pub fn width(&self) -> TermInt {
@@ -211,4 +1002,103 @@ impl Message {
     pub fn top_left(&self) -> Coords {
         self.top_left
     }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    fn covers(&self, pos: Coords) -> bool {
+        pos.0 >= self.top_left.0 && pos.1 >= self.top_left.1
+            && pos.0 < self.top_left.0 + self.width && pos.1 < self.top_left.1 + self.height
+    }
+}
+
+impl Overlay {
+    fn covers(&self, pos: Coords) -> bool {
+        pos.1 == self.pos.1 && pos.0 >= self.pos.0 && pos.0 < self.pos.0 + self.text.chars().count() as TermInt
+    }
+}
+
+// At spaces where possible, words too long for a line of their own are cut
+pub fn wrap(line: &str, width: usize) -> Vec<String> {
+    // Nothing fits in no width at all, a character a line is the least it can do
+    let width = width.max(1);
+    let mut lines = vec![];
+    let mut current = String::new();
+
+    for word in line.split(' ') {
+        let mut word: Vec<char> = word.chars().collect();
+        let current_len = current.chars().count();
+        if current_len > 0 && current_len + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+
+        while current.chars().count() + word.len() > width {
+            let fits = width - current.chars().count();
+            current.extend(word.drain(..fits));
+            lines.push(std::mem::take(&mut current));
+        }
+        current.extend(word);
+    }
+
+    lines.push(current);
+    lines
+}
+
+#[cfg(unix)]
+extern "C" fn on_sigtstp(_: libc::c_int) {
+    SUSPEND_SIGNALED.store(true, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines_of(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn wrap_breaks_at_spaces() {
+        assert_eq!(wrap("the quick brown fox", 10), lines_of(&["the quick", "brown fox"]));
+        assert_eq!(wrap("short", 10), lines_of(&["short"]));
+    }
+
+    #[test]
+    fn wrap_cuts_words_longer_than_the_width() {
+        assert_eq!(wrap("abcdefgh", 3), lines_of(&["abc", "def", "gh"]));
+        assert_eq!(wrap("ab cdefgh", 4), lines_of(&["ab", "cdef", "gh"]));
+        assert_eq!(wrap("aé→bcd", 2), lines_of(&["aé", "→b", "cd"]));
+    }
+
+    #[test]
+    fn wrap_gets_by_with_no_room() {
+        assert_eq!(wrap("ab c", 1), lines_of(&["a", "b", "c"]));
+        assert_eq!(wrap("ab c", 0), wrap("ab c", 1));
+        assert_eq!(wrap("", 0), lines_of(&[""]));
+    }
+
+    #[test]
+    fn message_fits_a_terminal_narrower_than_it() {
+        let text = "A message far too long for a terminal this narrow";
+        for anchor in [Anchor::Center, Anchor::Top, Anchor::Bottom, Anchor::TopLeft] {
+            for screen in [(10, 5), (3, 3), (1, 1), (2, 40), (0, 0)] {
+                let msg = Message::fit(anchor, &[text, ""], screen);
+                assert!(msg.top_left().0 + msg.width() <= screen.0, "{:?} too wide", screen);
+                assert!(msg.top_left().1 + msg.height() <= screen.1, "{:?} too high", screen);
+                assert!(msg.lines().iter().all(|line| line.chars().count() <= screen.0.saturating_sub(2).max(1) as usize));
+            }
+        }
+    }
+
+    #[test]
+    fn message_keeps_its_place_when_it_fits() {
+        let msg = Message::fit(Anchor::Center, &["Paused"], (80, 24));
+        assert_eq!((msg.width(), msg.height()), (8, 3));
+        assert_eq!(msg.top_left(), (36, 11));
+        assert_eq!(Message::fit(Anchor::TopLeft, &["Hi"], (80, 24)).top_left(), (1, 1));
+    }
 }
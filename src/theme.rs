@@ -0,0 +1,194 @@
+use std::cmp::max;
+
+use crate::term::Cell;
+use crate::snake::Direction::{self, *};
+
+use crossterm::style::{Attribute, Color, ContentStyle};
+
+pub struct Theme {
+    pub name: &'static str,
+    pub head: ContentStyle,
+    pub head_glyphs: HeadGlyphs,
+    pub body: Cell,
+    pub tail: Cell,
+    pub body_paint: BodyPaint,
+    pub apple: Cell,
+    pub dead: Cell,
+    pub ghost: Cell,
+    pub danger: Cell, // Next to the head, on the way to a certain crash
+    pub wall: Cell,
+    pub magnet: Cell,
+    pub rival: Cell, // The second player's body
+    pub heat: Option<[(u8, u8, u8); 2]>, // Heatmap colors for the least and most visited cells
+}
+
+#[derive(Copy, Clone)]
+pub struct HeadGlyphs {
+    pub up: char,
+    pub down: char,
+    pub left: char,
+    pub right: char,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum BodyPaint {
+    Solid,
+    Gradient { head: (u8, u8, u8), tail: (u8, u8, u8) },
+    Rainbow,
+}
+
+impl Theme {
+    pub const NAMES: &'static [&'static str] = &["classic", "color", "blocks", "gradient", "rainbow"];
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Self::classic()),
+            "color" => Some(Self::color()),
+            "blocks" => Some(Self::blocks()),
+            "gradient" => Some(Self::gradient()),
+            "rainbow" => Some(Self::rainbow()),
+            _ => None,
+        }
+    }
+
+    pub fn classic() -> Self {
+        Theme {
+            name: "classic",
+            head: ContentStyle::new(),
+            head_glyphs: HeadGlyphs::ARROWS,
+            body: Cell::plain('█'),
+            tail: Cell::plain('▓'),
+            body_paint: BodyPaint::Solid,
+            apple: Cell::plain('O'),
+            dead: Cell::plain('X'),
+            ghost: Cell::new('·', ContentStyle::new().attribute(Attribute::Dim)),
+            danger: Cell::new('x', ContentStyle::new().attribute(Attribute::Dim)),
+            wall: Cell::plain('#'),
+            magnet: Cell::plain('U'),
+            rival: Cell::plain('▒'),
+            heat: None,
+        }
+    }
+
+    pub fn color() -> Self {
+        Theme {
+            name: "color",
+            head: ContentStyle::new().foreground(Color::Green),
+            head_glyphs: HeadGlyphs::ARROWS,
+            body: Cell::new('█', ContentStyle::new().foreground(Color::Green)),
+            tail: Cell::new('▓', ContentStyle::new().foreground(Color::Green)),
+            body_paint: BodyPaint::Solid,
+            apple: Cell::new('O', ContentStyle::new().foreground(Color::Red)),
+            dead: Cell::new('X', ContentStyle::new().foreground(Color::DarkGrey)),
+            ghost: Cell::new('·', ContentStyle::new().foreground(Color::DarkGrey)),
+            danger: Cell::new('x', ContentStyle::new().foreground(Color::DarkRed)),
+            wall: Cell::new('#', ContentStyle::new().foreground(Color::Grey)),
+            magnet: Cell::new('U', ContentStyle::new().foreground(Color::Magenta)),
+            rival: Cell::new('█', ContentStyle::new().foreground(Color::Blue)),
+            heat: Some([(40, 60, 200), (250, 60, 30)]),
+        }
+    }
+
+    // Colored background cells instead of glyphs, for a chunkier look
+    pub fn blocks() -> Self {
+        Theme {
+            name: "blocks",
+            head: ContentStyle::new().foreground(Color::Black).background(Color::DarkGreen),
+            head_glyphs: HeadGlyphs::TRIANGLES,
+            body: Cell::new(' ', ContentStyle::new().background(Color::Green)),
+            tail: Cell::new('░', ContentStyle::new().foreground(Color::DarkGreen).background(Color::Green)),
+            body_paint: BodyPaint::Solid,
+            apple: Cell::new(' ', ContentStyle::new().background(Color::Red)),
+            dead: Cell::new(' ', ContentStyle::new().background(Color::DarkGrey)),
+            ghost: Cell::new('·', ContentStyle::new().foreground(Color::DarkGreen)),
+            danger: Cell::new('x', ContentStyle::new().foreground(Color::DarkRed)),
+            wall: Cell::new(' ', ContentStyle::new().background(Color::Grey)),
+            magnet: Cell::new('U', ContentStyle::new().foreground(Color::Black).background(Color::Magenta)),
+            rival: Cell::new(' ', ContentStyle::new().background(Color::Blue)),
+            heat: Some([(40, 60, 200), (250, 60, 30)]),
+        }
+    }
+
+    pub fn gradient() -> Self {
+        Theme {
+            name: "gradient",
+            body_paint: BodyPaint::Gradient { head: (80, 250, 80), tail: (10, 70, 120) },
+            head: ContentStyle::new().foreground(Color::Rgb { r: 80, g: 250, b: 80 }),
+            heat: Some([(10, 70, 120), (80, 250, 80)]),
+            ..Self::color()
+        }
+    }
+
+    pub fn rainbow() -> Self {
+        Theme { name: "rainbow", body_paint: BodyPaint::Rainbow, head: ContentStyle::new(), ..Self::blocks() }
+    }
+
+    pub fn head_cell(&self, direction: Direction) -> Cell {
+        let glyphs = &self.head_glyphs;
+        let ch = match direction {
+            Up => glyphs.up,
+            Down => glyphs.down,
+            Left => glyphs.left,
+            Right => glyphs.right,
+        };
+
+        Cell::new(ch, self.head)
+    }
+
+    // Body segment with the theme's paint applied, `index` counts from the head
+    // and the last one is the tail. `step` makes the rainbow cycle along the
+    // body as the snake moves.
+    pub fn body_cell(&self, index: usize, len: usize, step: u64) -> Cell {
+        let base = if index + 1 == len {self.tail} else {self.body};
+        let (r, g, b) = match self.body_paint {
+            BodyPaint::Solid => return base,
+            BodyPaint::Gradient { head, tail } => blend(head, tail, index as f64 / (max(len, 2) - 1) as f64),
+            BodyPaint::Rainbow => hue_to_rgb((index as u64 * 15 + step * 5) % 360),
+        };
+
+        // Glyph themes get a colored glyph, block themes a colored background
+        let color = Color::Rgb { r, g, b };
+        let style = if base.style.background_color.is_some() {
+            base.style.background(color)
+        } else {
+            base.style.foreground(color)
+        };
+
+        Cell::new(base.ch, style)
+    }
+
+    // `heat` goes from 0 for cells never visited to 1 for the busiest one. The
+    // colors are exact, the terminal layer brings them down to what it can show.
+    pub fn heat_cell(&self, ch: char, heat: f64) -> Cell {
+        match self.heat {
+            Some([cold, hot]) => {
+                let (r, g, b) = blend(cold, hot, heat);
+                Cell::new(ch, ContentStyle::new().foreground(Color::Rgb { r, g, b }))
+            },
+            None => Cell::plain(ch),
+        }
+    }
+}
+
+impl HeadGlyphs {
+    pub const ARROWS: Self = HeadGlyphs { up: '^', down: 'v', left: '<', right: '>' };
+    pub const TRIANGLES: Self = HeadGlyphs { up: '▲', down: '▼', left: '◀', right: '▶' };
+}
+
+fn blend(from: (u8, u8, u8), to: (u8, u8, u8), t: f64) -> (u8, u8, u8) {
+    let lerp = |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * t).round() as u8;
+    (lerp(from.0, to.0), lerp(from.1, to.1), lerp(from.2, to.2))
+}
+
+// Full saturation and value, which is all we need for a rainbow
+fn hue_to_rgb(hue: u64) -> (u8, u8, u8) {
+    let x = ((hue % 60) * 255 / 60) as u8;
+    match hue / 60 {
+        0 => (255, x, 0),
+        1 => (255 - x, 255, 0),
+        2 => (0, 255, x),
+        3 => (0, 255 - x, 255),
+        4 => (x, 0, 255),
+        _ => (255, 0, 255 - x),
+    }
+}
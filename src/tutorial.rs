@@ -0,0 +1,210 @@
+use std::{thread::sleep, time::{Duration, Instant}};
+
+use crate::{Coords, TermInt};
+use crate::board::{Board, Shape};
+use crate::config::Config;
+use crate::input::Action;
+use crate::menu::Pick;
+use crate::snake::{Snake, Direction::Right, MoveResult::*};
+use crate::term::{Anchor, Cell, TermManager};
+
+use rand::seq::SliceRandom;
+
+const TICK_MS: u64 = 10;
+const STEP_MS: u64 = 150; // Slower than any real game, there's reading to do
+
+// What the player has to do to get through a lesson
+enum Checkpoint {
+    Turn,
+    Eat(u64),     // That many apples
+    Pause,        // And carry on again
+    Survive(u64), // That many steps
+}
+
+// One scripted scenario: a fresh snake on an empty board, a prompt over it
+// and a checkpoint to reach. Crashing starts the lesson over.
+struct Lesson {
+    prompt: &'static str,
+    length: i16,
+    apple_ahead: Option<TermInt>, // Cells in front of the head for the first apple, the rest go anywhere
+    checkpoint: Checkpoint,
+    done: &'static str,
+}
+
+enum Outcome {
+    Passed,
+    Crashed(&'static str),
+    Left(Pick),
+}
+
+const LESSONS: [Lesson; 5] = [
+    Lesson {
+        prompt: "The snake never stops moving. Try turning now.",
+        length: 4, apple_ahead: None, checkpoint: Checkpoint::Turn,
+        done: "That's all there is to steering.",
+    },
+    Lesson {
+        prompt: "Now eat the apple to your right.",
+        length: 4, apple_ahead: Some(8), checkpoint: Checkpoint::Eat(1),
+        done: "Every apple is a point, and makes the snake longer.",
+    },
+    Lesson {
+        prompt: "Apples show up anywhere. Eat three of them.",
+        length: 4, apple_ahead: None, checkpoint: Checkpoint::Eat(3),
+        done: "In a real game, the snake also gets faster as it eats.",
+    },
+    Lesson {
+        prompt: "Press Esc to pause, then Esc again to carry on.",
+        length: 4, apple_ahead: None, checkpoint: Checkpoint::Pause,
+        done: "The game waits for you as long as it's paused.",
+    },
+    Lesson {
+        prompt: "Your own body is as deadly as the walls. Stay alive for 150 steps.",
+        length: 20, apple_ahead: None, checkpoint: Checkpoint::Survive(150),
+        done: "Keep some room to turn around, and you're ready to play.",
+    },
+];
+
+// Walks through the lessons in order on a board the size of the terminal,
+// and leaves the screen clear for whatever comes after
+pub fn run(term: &mut TermManager, config: &Config) -> Pick {
+    let (width, height) = term.get_terminal_size();
+    let board = Board::new(Shape::Rectangle, width, height);
+    let mut lesson = 0;
+    // Whatever menu it was started from
+    term.hide_message();
+
+    let pick = loop {
+        let (lines, over) = match play(term, config, &board, &LESSONS[lesson]) {
+            Outcome::Passed if lesson + 1 == LESSONS.len() => {
+                (vec!["Well done!", LESSONS[lesson].done, "", "Press any key to go back"], true)
+            },
+            Outcome::Passed => {
+                lesson += 1;
+                (vec!["Well done!", LESSONS[lesson - 1].done, "", "Press any key for the next one"], false)
+            },
+            Outcome::Crashed(why) => (vec![why, "", "Press any key to try again"], false),
+            Outcome::Left(pick) => break pick,
+        };
+
+        match Action::of(&term.ask_at(Anchor::Center, &lines)) {
+            Some(Action::Quit) => break Pick::Quit,
+            _ if over => break Pick::Back,
+            _ => {},
+        }
+    };
+
+    term.hide_message_at(Anchor::Top);
+    term.clear();
+    pick
+}
+
+///////////////////////////////////////////////////////////////////////////
+
+fn play(term: &mut TermManager, config: &Config, board: &Board, lesson: &Lesson) -> Outcome {
+    let (width, height) = board.size();
+    let spawn = board.spawn_point(lesson.length as TermInt).expect("Error spawning: the terminal is too small");
+    let mut snake = Snake::new(spawn, lesson.length, Right);
+    let mut rng = rand::thread_rng();
+
+    // Right in front if it fits, anywhere free and in sight otherwise
+    let mut place_apple = |term: &TermManager, snake: &Snake, ahead: Option<TermInt>| {
+        let in_front = ahead.map(|cells| (snake.head().0 + cells, snake.head().1)).filter(|pos| board.is_playable(*pos));
+        let free: Vec<Coords> = board.positions().iter()
+            .filter(|pos| !snake.body().contains(pos) && !term.is_under_message(**pos))
+            .copied()
+            .collect();
+        in_front.or_else(|| free.choose(&mut rng).copied()).expect("Error placing apple: the terminal is too small")
+    };
+
+    term.clear();
+    term.draw_borders(Some((width, height)));
+    term.show_message_at(Anchor::Top, &[lesson.prompt, config.controls.help(config.keys)]);
+    let mut apple = place_apple(term, &snake, lesson.apple_ahead);
+    term.print_at(apple, config.theme.apple);
+    draw_snake(term, config, &snake);
+    term.flush();
+
+    let mut eaten = 0;
+    let mut paused = false;
+    let mut was_paused = false;
+    let mut turn = None;
+    let mut next_step = Instant::now() + Duration::from_millis(STEP_MS);
+
+    loop {
+        sleep(Duration::from_millis(TICK_MS));
+
+        for key in term.read_key_events_queue() {
+            match Action::of(&key) {
+                Some(Action::Quit) => return Outcome::Left(Pick::Quit),
+                Some(Action::Suspend) => term.suspend(),
+                Some(Action::Redraw) => term.redraw_all(),
+                Some(Action::Menu) if paused => return Outcome::Left(Pick::Back),
+                Some(Action::Pause) => {
+                    paused = !paused;
+                    if paused {
+                        was_paused = true;
+                        term.show_message(&["Paused", "", "Esc to carry on, M for the menu"]);
+                    } else {
+                        term.hide_message();
+                        next_step = Instant::now() + Duration::from_millis(STEP_MS);
+                    }
+                },
+                _ if paused => {},
+                _ => if let Some(dir) = config.controls.direction(config.keys, key.code, snake.get_direction()) {
+                    turn = Some(dir);
+                },
+            }
+        }
+
+        if paused || Instant::now() < next_step {
+            continue;
+        }
+        next_step += Duration::from_millis(STEP_MS);
+
+        if let Some(dir) = turn.take() {
+            let heading = snake.get_direction();
+            snake.set_direction(dir);
+            if matches!(lesson.checkpoint, Checkpoint::Turn) && snake.get_direction() != heading {
+                return Outcome::Passed;
+            }
+        }
+
+        let next = snake.get_direction().step_from(snake.head());
+        match snake.move_step(board) {
+            Crashed if board.is_playable(next) => return Outcome::Crashed("Ouch! That was the snake's own body."),
+            Crashed => return Outcome::Crashed("Ouch! That was a wall."),
+            Moved { new_head, old_tail, .. } => {
+                if let Some(tail) = old_tail {
+                    term.print_at(tail, Cell::plain(' '));
+                }
+                if new_head == apple {
+                    eaten += 1;
+                    snake.grow(1);
+                    apple = place_apple(term, &snake, None);
+                    term.print_at(apple, config.theme.apple);
+                }
+                draw_snake(term, config, &snake);
+                term.flush();
+            },
+        }
+
+        let passed = match lesson.checkpoint {
+            Checkpoint::Turn => false,
+            Checkpoint::Eat(apples) => eaten >= apples,
+            Checkpoint::Pause => was_paused,
+            Checkpoint::Survive(steps) => snake.moves() >= steps,
+        };
+        if passed {
+            return Outcome::Passed;
+        }
+    }
+}
+
+fn draw_snake(term: &mut TermManager, config: &Config, snake: &Snake) {
+    let body = &snake.body()[..snake.body().len() - 1];
+    for (i, pos) in body.iter().rev().enumerate() {
+        term.print_at(*pos, config.theme.body_cell(i, body.len(), snake.moves()));
+    }
+    term.print_at(snake.head(), config.theme.head_cell(snake.get_direction()));
+}
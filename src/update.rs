@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+use crate::http;
+use crate::json::Value;
+
+const RELEASES_URL: &str = "https://api.github.com/repos/agubelu/snake/releases/latest";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// Only ever run when asked to, and nothing about the player is sent along
+pub fn check() -> Result<String, String> {
+    let current = env!("CARGO_PKG_VERSION");
+    let text = http::get(RELEASES_URL, TIMEOUT)?;
+    let json = Value::parse(&text).map_err(|e| format!("unexpected response: {}", e))?;
+    let tag = json.get("tag_name").and_then(Value::as_str).ok_or("unexpected response: no tag_name")?;
+
+    let latest = parse_version(tag).ok_or(format!("unexpected version {}", tag))?;
+    if latest > parse_version(current).unwrap() {
+        let url = json.get("html_url").and_then(Value::as_str).unwrap_or("https://github.com/agubelu/snake/releases");
+        Ok(format!("Version {} is out, you have {}. Get it at {}", tag.trim_start_matches('v'), current, url))
+    } else {
+        Ok(format!("You have the latest version, {}", current))
+    }
+}
+
+// Release tags look like v1.2.3
+fn parse_version(text: &str) -> Option<Vec<u64>> {
+    text.trim_start_matches('v').split('.').map(|part| part.parse().ok()).collect()
+}
@@ -0,0 +1,144 @@
+use crate::Coords;
+use crate::hunger::Hunger;
+use crate::recording::{self, Recording};
+use crate::replay::Frame;
+use crate::snake::{Snake, Direction::{self, *}, MoveResult::*};
+
+// What a recording turned out to be worth once played again
+pub struct Verified {
+    pub score: u64,
+    pub steps: usize,
+    pub apples: u64,
+}
+
+// Plays a recording again on the engine, with no terminal involved, and checks
+// that every step is one the rules allow and that it adds up to the score it
+// claims. Apples are taken as recorded, so this catches edited moves and scores
+// but not a player who got to choose where their apples went.
+pub fn verify(recording: &Recording) -> Result<Verified, String> {
+    let board = &recording.board;
+    let replay = &recording.replay;
+    let start = replay.frame(0);
+
+    if !start.body.iter().all(|pos| board.is_playable(*pos)) {
+        return Err("the snake starts on a wall".into());
+    }
+    let mut snake = Snake::new(start.head(), start.body.len() as i16, start.direction());
+    if snake.body() != start.body.as_slice() {
+        return Err("the snake doesn't start in a straight line".into());
+    }
+    if !is_free(&start, start.apple, recording) {
+        return Err("the first apple isn't on a free cell".into());
+    }
+
+    let value_at = |head: Coords, apple: Coords| if recording.far_apples {board.distance_value(head, apple)} else {1};
+    let mut apple = start.apple;
+    let mut apple_value = value_at(snake.head(), apple);
+    let mut score = 0;
+    let mut apples = 0;
+    let mut last_time = 0;
+    let mut hunger = recording.hunger.map(Hunger::new);
+
+    for (i, step) in replay.steps().iter().enumerate() {
+        let fail = |what: &str| Err(format!("step {}: {}", i + 1, what));
+        if step.time_ms < last_time {
+            return fail("it happened before the one before it");
+        }
+        last_time = step.time_ms;
+
+        let dir = match direction_between(snake.head(), step.head) {
+            Some(dir) if dir != snake.get_direction().opposite() => dir,
+            _ => return fail("the snake can't move there"),
+        };
+        snake.set_direction(dir);
+        let grew = match snake.move_step(board) {
+            Crashed => return fail("the snake crashed but the game went on"),
+            Moved { old_tail, .. } => old_tail.is_none(),
+        };
+        if grew != step.grew {
+            return fail("the snake grew when it shouldn't have, or the other way around");
+        }
+
+        // Starving is the one way a hungry step ends the game
+        let ate = step.head == apple;
+        let hungry = match hunger.as_mut() {
+            Some(hunger) if ate => {
+                hunger.feed();
+                false
+            },
+            Some(hunger) => hunger.tick(),
+            None => false,
+        };
+        if hungry && !step.shrank && snake.body().len() == 1 {
+            if i + 1 != replay.steps().len() {
+                return fail("the snake starved, but the game went on");
+            }
+        } else if hungry != step.shrank || (hungry && snake.shrink().is_none()) {
+            return fail("the snake went hungry when it shouldn't have, or the other way around");
+        }
+
+        let frame = replay.frame(i + 1);
+        if ate {
+            score += apple_value;
+            apples += 1;
+            snake.grow(recording.growth.segments(score));
+
+            // The game ends right there when there's nowhere left for the next one
+            if step.apple == apple {
+                if i + 1 != replay.steps().len() {
+                    return fail("no new apple appeared, but the game went on");
+                }
+                continue;
+            }
+            if !is_free(&frame, step.apple, recording) {
+                return fail("the new apple isn't on a free cell");
+            }
+            apple = step.apple;
+            apple_value = value_at(snake.head(), apple);
+        } else if step.apple != apple {
+            // Only a magnet moves an apple, one cell at a time towards the head,
+            // unless apples drift, one cell any way at a time
+            let pulled = manhattan(step.apple, step.head) < manhattan(apple, step.head) && manhattan(step.apple, apple) == 1;
+            let drifted = recording.drifting_apples && chebyshev(step.apple, apple) == 1;
+            if !(pulled || drifted) || !is_free(&frame, step.apple, recording) {
+                return fail("the apple moved in a way it can't");
+            }
+            apple = step.apple;
+        }
+    }
+
+    if score != recording.score {
+        return Err(format!("it claims a score of {}, but plays out to {}", recording.score, score));
+    }
+
+    Ok(Verified { score, steps: replay.steps().len(), apples })
+}
+
+// Decoding and verifying in one go, for whoever receives the files
+pub fn verify_bytes(bytes: &[u8]) -> Result<Verified, String> {
+    verify(&recording::decode(bytes)?)
+}
+
+///////////////////////////////////////////////////////////////////////////
+
+fn is_free(frame: &Frame, pos: Coords, recording: &Recording) -> bool {
+    recording.board.is_playable(pos) && !frame.body.contains(&pos)
+}
+
+fn manhattan(a: Coords, b: Coords) -> i32 {
+    (a.0 as i32 - b.0 as i32).abs() + (a.1 as i32 - b.1 as i32).abs()
+}
+
+fn chebyshev(a: Coords, b: Coords) -> i32 {
+    (a.0 as i32 - b.0 as i32).abs().max((a.1 as i32 - b.1 as i32).abs())
+}
+
+fn direction_between(from: Coords, to: Coords) -> Option<Direction> {
+    match (to.0 as i32 - from.0 as i32, to.1 as i32 - from.1 as i32) {
+        (0, -1) => Some(Up),
+        (0, 1) => Some(Down),
+        (-1, 0) => Some(Left),
+        (1, 0) => Some(Right),
+        _ => None,
+    }
+}
@@ -0,0 +1,120 @@
+use std::{fs, time::{Duration, Instant}};
+
+use crate::TermInt;
+use crate::recording;
+use crate::replay::{Frame, Replay};
+use crate::caps::Glyphs;
+use crate::term::{Cell, Renderer, TermManager};
+use crate::theme::Theme;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::{Attribute, ContentStyle};
+
+const FRAME_MS: u64 = 50;
+const SPEEDS: [u64; 3] = [1, 2, 4];
+
+pub enum Exit {
+    Back,
+    Quit, // CTRL+C, the whole game should go
+}
+
+// Plays a replay back over the board, which is expected to be drawn already.
+// The status goes on the given row, usually the bottom wall.
+pub fn watch(term: &mut TermManager, theme: &Theme, replay: &Replay, status_row: TermInt) -> Exit {
+    let mut current = 0;
+    let mut shown: Option<Frame> = None;
+    let mut paused = false;
+    let mut speed = 0;
+    let mut next_frame = Instant::now();
+
+    loop {
+        if shown.is_none() {
+            let frame = replay.frame(current);
+            draw_frame(term, theme, &frame, None);
+            shown = Some(frame);
+        }
+
+        let status = format!(" Replay {}/{}  {}x{} ", current, replay.frame_count() - 1, SPEEDS[speed],
+            if paused {"  paused"} else {""});
+        let hint = " Space pause, arrows step, 1/2/4 speed, Esc back ";
+        let width = term.get_terminal_size().0.saturating_sub(2) as usize;
+        let line = format!("{:<w$.w$}", format!("{}{}", status, hint), w = width);
+        term.print_text_at((1, status_row), &line, ContentStyle::new().attribute(Attribute::Reverse));
+        term.flush();
+
+        let wait = if paused {Duration::from_millis(FRAME_MS)} else {next_frame.saturating_duration_since(Instant::now())};
+        let mut target = current;
+
+        match term.poll_event(wait) {
+            Some(Event::Key(KeyEvent { code: KeyCode::Char('c'), modifiers: KeyModifiers::CONTROL })) => return Exit::Quit,
+            Some(Event::Key(KeyEvent { code, .. })) => match code {
+                KeyCode::Esc | KeyCode::Char('q') => return Exit::Back,
+                KeyCode::Char(' ') => paused = !paused,
+                KeyCode::Right => { paused = true; target = current + 1; },
+                KeyCode::Left => { paused = true; target = current.saturating_sub(1); },
+                KeyCode::Home => target = 0,
+                KeyCode::End => target = replay.frame_count() - 1,
+                KeyCode::Char('1') => speed = 0,
+                KeyCode::Char('2') => speed = 1,
+                KeyCode::Char('4') => speed = 2,
+                _ => {}
+            },
+            Some(_) => {},
+            None if !paused => {
+                next_frame = Instant::now() + Duration::from_millis(FRAME_MS / SPEEDS[speed]);
+                target = current + 1;
+                // Stop at the end instead of looping, so the crash can be stared at
+                if target >= replay.frame_count() {
+                    paused = true;
+                }
+            },
+            None => {},
+        }
+
+        let target = target.min(replay.frame_count() - 1);
+        if target != current {
+            let frame = replay.frame(target);
+            draw_frame(term, theme, &frame, shown.as_ref());
+            shown = Some(frame);
+            current = target;
+        }
+    }
+}
+
+// A saved game on a terminal of its own, big enough for the board it was played on
+pub fn watch_file(path: &str, theme: &Theme, renderer: Renderer, glyphs: Glyphs) -> Result<(), String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let recording = recording::decode(&bytes)?;
+
+    let mut term = TermManager::new(false, renderer, glyphs);
+    let (width, height) = recording.board.size();
+    let (term_width, term_height) = term.get_terminal_size();
+    if width > term_width || height > term_height {
+        return Err(format!("the board is {}x{}, it doesn't fit in the terminal", width, height));
+    }
+
+    term.setup();
+    for pos in recording.board.outline() {
+        term.print_at(pos, theme.wall);
+    }
+    watch(&mut term, theme, &recording.replay, height - 1);
+    term.restore();
+    Ok(())
+}
+
+///////////////////////////////////////////////////////////////////////////
+
+fn draw_frame(term: &mut TermManager, theme: &Theme, frame: &Frame, previous: Option<&Frame>) {
+    if let Some(prev) = previous {
+        for pos in prev.body.iter().chain(std::iter::once(&prev.apple)) {
+            term.print_at(*pos, Cell::plain(' '));
+        }
+    }
+
+    term.print_at(frame.apple, theme.apple);
+    let body = &frame.body[..frame.body.len() - 1];
+    for (i, pos) in body.iter().rev().enumerate() {
+        term.print_at(*pos, theme.body_cell(i, body.len(), 0));
+    }
+    term.print_at(frame.head(), theme.head_cell(frame.direction()));
+}
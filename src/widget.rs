@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use crate::{Coords, TermInt};
+use crate::env::{self, SnakeEnv};
+use crate::snake::Direction::{self, *};
+
+const STEP_TIME: Duration = Duration::from_millis(120);
+
+// What goes in a cell of the caller's buffer, for them to draw however their
+// own toolkit does
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Glyph {
+    Empty,
+    Wall,
+    Body,
+    Head(Direction),
+    Apple,
+}
+
+// Whatever the caller's own key handling makes of a key press
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Input {
+    Steer(Direction),
+    Pause,   // And carry on again
+    Restart, // Also the way out of a game over
+}
+
+// A whole game for other terminal apps to embed, like an easter egg in a
+// dashboard. It never touches the terminal itself: keys and time come from
+// the caller, and it draws into a buffer the caller owns. The rules are the
+// engine's, so it plays like SnakeEnv and takes its goal, growth and hunger.
+pub struct SnakeWidget {
+    env: SnakeEnv,
+    turn: Option<Direction>, // Only taken on the next step
+    since_step: Duration,
+    paused: bool,
+}
+
+impl Glyph {
+    // One plain character each, for callers with no look of their own
+    pub fn ch(&self) -> char {
+        match self {
+            Glyph::Empty => ' ',
+            Glyph::Wall => '#',
+            Glyph::Body => '█',
+            Glyph::Head(Up) => '^',
+            Glyph::Head(Down) => 'v',
+            Glyph::Head(Left) => '<',
+            Glyph::Head(Right) => '>',
+            Glyph::Apple => 'O',
+        }
+    }
+}
+
+impl SnakeWidget {
+    // Walls included, so the playable area is two cells smaller each way
+    pub fn new(width: TermInt, height: TermInt) -> Self {
+        Self::with_env(SnakeEnv::new(width, height))
+    }
+
+    // Same seed, same apples
+    pub fn seeded(width: TermInt, height: TermInt, seed: u64) -> Self {
+        Self::with_env(SnakeEnv::seeded(width, height, seed))
+    }
+
+    // For other boards or rules, set up on the env first
+    pub fn with_env(mut env: SnakeEnv) -> Self {
+        env.reset();
+        SnakeWidget { env, turn: None, since_step: Duration::ZERO, paused: false }
+    }
+
+    pub fn handle(&mut self, input: Input) {
+        match input {
+            Input::Steer(dir) => self.turn = Some(dir),
+            Input::Pause => self.paused = !self.paused,
+            Input::Restart => self.restart(),
+        }
+    }
+
+    // As many steps as are due after that much time, none while paused or over
+    pub fn advance(&mut self, elapsed: Duration) {
+        if self.paused || self.env.is_done() {
+            return;
+        }
+
+        self.since_step += elapsed;
+        while self.since_step >= STEP_TIME && !self.env.is_done() {
+            self.since_step -= STEP_TIME;
+            let dir = self.turn.take().unwrap_or_else(|| self.env.snake().get_direction());
+            self.env.step(dir);
+        }
+    }
+
+    // With the board's top left corner at that spot in a buffer of that many
+    // columns, leaving out whatever falls outside of it
+    pub fn render(&self, buffer: &mut [Glyph], buffer_width: usize, at: Coords) {
+        let observation = self.env.observation();
+        let (width, height) = (observation.width, observation.height);
+        for y in 0..height {
+            for x in 0..width {
+                let (bx, by) = (at.0 as usize + x as usize, at.1 as usize + y as usize);
+                if bx < buffer_width {
+                    if let Some(cell) = buffer.get_mut(by * buffer_width + bx) {
+                        *cell = self.glyph(observation.at((x, y)));
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn size(&self) -> Coords {
+        self.env.board().size()
+    }
+
+    pub fn score(&self) -> u64 {
+        self.env.score()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.env.is_done()
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn restart(&mut self) {
+        self.env.reset();
+        (self.turn, self.since_step, self.paused) = (None, Duration::ZERO, false);
+    }
+
+    fn glyph(&self, cell: u8) -> Glyph {
+        match cell {
+            env::CELL_HEAD => Glyph::Head(self.env.snake().get_direction()),
+            env::CELL_BODY => Glyph::Body,
+            env::CELL_APPLE => Glyph::Apple,
+            env::CELL_EMPTY => Glyph::Empty,
+            _ => Glyph::Wall,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn head(widget: &SnakeWidget) -> Coords {
+        widget.env.snake().head()
+    }
+
+    #[test]
+    fn renders_clipped_to_the_buffer() {
+        let widget = SnakeWidget::seeded(12, 6, 1);
+        // Anything still an apple in row 0 or column 0-1 was never drawn over
+        let (width, height) = (8, 4);
+        let mut buffer = vec![Glyph::Apple; width * height];
+        widget.render(&mut buffer, width, (2, 1));
+
+        assert!(buffer[..width].iter().all(|glyph| *glyph == Glyph::Apple));
+        for y in 1..height {
+            assert_eq!(&buffer[y * width..y * width + 2], [Glyph::Apple; 2]);
+            // The board's left wall
+            assert_eq!(buffer[y * width + 2], Glyph::Wall);
+        }
+        assert_eq!(&buffer[width + 2..2 * width], [Glyph::Wall; 6]);
+        let observation = widget.env.observation();
+        for y in 1..height {
+            for x in 3..width {
+                assert_eq!(buffer[y * width + x], widget.glyph(observation.at((x as TermInt - 2, y as TermInt - 1))));
+            }
+        }
+    }
+
+    #[test]
+    fn steps_with_time_and_not_while_paused() {
+        let mut widget = SnakeWidget::seeded(20, 10, 3);
+        let start = head(&widget);
+        widget.advance(STEP_TIME / 2);
+        assert_eq!(head(&widget), start);
+        widget.advance(STEP_TIME / 2);
+        assert_eq!(head(&widget), Right.step_from(start));
+
+        widget.handle(Input::Pause);
+        widget.advance(STEP_TIME * 3);
+        assert_eq!(head(&widget), Right.step_from(start));
+
+        widget.handle(Input::Pause);
+        widget.handle(Input::Steer(Down));
+        widget.advance(STEP_TIME);
+        assert_eq!(head(&widget), Down.step_from(Right.step_from(start)));
+        assert_eq!(widget.glyph(env::CELL_HEAD), Glyph::Head(Down));
+    }
+}